@@ -115,9 +115,13 @@ def main():
 
 
 def harvest_metadata(manifest_path):
-    p = subprocess.run(["cargo", "tree"], check=True, cwd=manifest_path.parent, capture_output=True, encoding="utf-8")
+    # Dev-dependencies like the shared corpus are not what the example is measuring.
+    p = subprocess.run(["cargo", "tree", "--edges=normal,build"], check=True, cwd=manifest_path.parent, capture_output=True, encoding="utf-8")
     lines = p.stdout.strip().splitlines()
     app_line = lines.pop(0)
+    # Path dependencies, like the shared `ast`, are part of this repo rather
+    # than the library being measured.
+    lines = [line for line in lines if " (/" not in line]
     if lines:
         self_line = lines.pop(0)
         name, version = _extract_line(self_line)
@@ -0,0 +1,35 @@
+//! The JSON value every parser example builds, other than rust-sitter's, which
+//! is its own grammar.
+//!
+//! It is `no_std`, needing only `alloc`, so the hand-written parser can still
+//! do without `std`. The shared corpus compares it against what each input
+//! should produce with `corpus::same_value`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+
+/// A parsed JSON document.
+///
+/// Strings and keys are `S`, which is `String` unless a parser borrows them
+/// from the input, as winnow's does with `Cow<str>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue<S = String> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(S),
+    Array(Vec<JsonValue<S>>),
+    /// Members in document order, duplicates included, as in the rust-sitter
+    /// example.
+    Object(Vec<(S, JsonValue<S>)>),
+}
+
+/// The deepest nesting of arrays and objects the parsers accept, the same as
+/// the rust-sitter example's default.
+///
+/// They all recurse at least once per level, so this keeps hostile input from
+/// overflowing the stack.
+pub const MAX_DEPTH: usize = 128;
@@ -0,0 +1,154 @@
+//! JSON documents of a given size, one shape per function.
+//!
+//! Each function seeds its own generator, so the same size always gives the
+//! same bytes.
+
+use std::fmt::Write;
+
+use crate::Rng;
+
+/// Objects and arrays nested `depth` levels deep, alternating and starting with
+/// an object, around a single `0`.
+pub fn deep(depth: usize) -> String {
+    let mut out = String::new();
+    for i in 0..depth {
+        if i % 2 == 0 {
+            out.push_str(r#"{"a":"#);
+        } else {
+            out.push('[');
+        }
+    }
+    out.push('0');
+    for i in (0..depth).rev() {
+        out.push(if i % 2 == 0 { '}' } else { ']' });
+    }
+    out
+}
+
+/// An array of `n` records mixing every kind of value; 40,000 records come to a
+/// little under 4 MB.
+pub fn wide_array(n: usize) -> String {
+    let mut rng = Rng::new(0x5eed);
+    let mut out = String::from("[");
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(
+            out,
+            r#"{{"id":{i},"x":{:.6},"y":{:.6},"label":"item-{:08x}","flags":[{},{},null]}}"#,
+            rng.float(),
+            rng.float(),
+            rng.next(),
+            rng.next().is_multiple_of(2),
+            rng.next().is_multiple_of(3),
+        )
+        .unwrap();
+    }
+    out.push(']');
+    out
+}
+
+/// An object of `n` long string values, with escapes sprinkled through.
+pub fn string_heavy(n: usize) -> String {
+    const WORDS: &[&str] = &[
+        "lorem",
+        "ipsum",
+        "dolor",
+        "sit",
+        "amet",
+        "caf\u{e9}",
+        "\\n",
+        "\\\"quoted\\\"",
+        "\\u00e9",
+        "\\ud83d\\ude10",
+    ];
+    let mut rng = Rng::new(0xc0ffee);
+    let mut out = String::from("{");
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(out, r#""key{i}":""#).unwrap();
+        for w in 0..64 {
+            if w > 0 {
+                out.push(' ');
+            }
+            out.push_str(WORDS[rng.next() as usize % WORDS.len()]);
+        }
+        out.push('"');
+    }
+    out.push('}');
+    out
+}
+
+/// An array of `n` numbers in every form JSON has: integers, negatives,
+/// fractions and exponents.
+pub fn numbers(n: usize) -> String {
+    let mut rng = Rng::new(0xdec1a1);
+    let mut out = String::from("[");
+    for i in 0..n {
+        if i > 0 {
+            out.push(',');
+        }
+        let bits = rng.next();
+        match bits % 4 {
+            0 => write!(out, "{}", bits >> 8),
+            1 => write!(out, "-{}", bits >> 40),
+            2 => write!(out, "{:.6}", rng.float()),
+            _ => write!(
+                out,
+                "{}.{}e{}",
+                bits % 10,
+                (bits >> 8) % 1000,
+                rng.float() as i64 / 10
+            ),
+        }
+        .unwrap();
+    }
+    out.push(']');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    fn parse(text: &str) -> Value {
+        serde_json::from_str(text).unwrap_or_else(|err| panic!("{err}: {text}"))
+    }
+
+    #[test]
+    fn deep_nests_depth_levels() {
+        fn depth(value: &Value) -> usize {
+            match value {
+                Value::Array(values) => 1 + depth(&values[0]),
+                Value::Object(properties) => 1 + depth(&properties["a"]),
+                _ => 0,
+            }
+        }
+        for n in [0, 1, 2, 100] {
+            assert_eq!(depth(&parse(&deep(n))), n);
+        }
+    }
+
+    #[test]
+    fn element_counts() {
+        for n in [0, 1, 1_000] {
+            assert_eq!(parse(&wide_array(n)).as_array().unwrap().len(), n);
+            assert_eq!(parse(&string_heavy(n)).as_object().unwrap().len(), n);
+            let numbers = parse(&numbers(n));
+            let numbers = numbers.as_array().unwrap();
+            assert_eq!(numbers.len(), n);
+            assert!(numbers.iter().all(Value::is_number));
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(wide_array(100), wide_array(100));
+        assert_eq!(string_heavy(100), string_heavy(100));
+        assert_eq!(numbers(100), numbers(100));
+    }
+}
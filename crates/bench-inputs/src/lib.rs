@@ -0,0 +1,109 @@
+//! Representative JSON documents for benchmarking the parser examples.
+//!
+//! Everything is generated from a fixed seed, so every backend sees the same
+//! bytes on every run and throughput numbers stay comparable. The generators
+//! behind most of them are in [`gen`], for inputs of other sizes.
+
+use std::fmt::Write;
+
+pub mod gen;
+
+/// A named benchmark input.
+pub struct Document {
+    pub name: &'static str,
+    pub text: String,
+}
+
+/// Nesting depth of the `deeply_nested` document.
+///
+/// Kept below the 128 level default limit of the rust-sitter example so the
+/// document is accepted everywhere.
+pub const NESTING_DEPTH: usize = 100;
+
+/// The full benchmark corpus: a small object, a multi-megabyte array, a deeply
+/// nested document, a string-heavy document, an array of numbers and an
+/// indented one.
+pub fn documents() -> Vec<Document> {
+    vec![
+        Document {
+            name: "small_object",
+            text: small_object(),
+        },
+        Document {
+            name: "large_array",
+            text: gen::wide_array(40_000),
+        },
+        Document {
+            name: "deeply_nested",
+            text: gen::deep(NESTING_DEPTH),
+        },
+        Document {
+            name: "string_heavy",
+            text: gen::string_heavy(2_000),
+        },
+        Document {
+            name: "numbers",
+            text: gen::numbers(100_000),
+        },
+        Document {
+            name: "indented",
+            text: indented(5_000),
+        },
+    ]
+}
+
+fn small_object() -> String {
+    r#"{"id": 42, "name": "rosetta", "active": true, "score": -1.5e3, "tags": ["json", "parser"], "owner": null}"#
+        .to_string()
+}
+
+/// `len` records like those in [`gen::wide_array`], pretty-printed with four-space
+/// indents and nested deep enough that most runs of whitespace are longer than
+/// 16 bytes; 5,000 records come to about 1 MB.
+fn indented(len: usize) -> String {
+    let mut rng = Rng::new(0x1de47);
+    let mut out = String::from("{\n    \"data\": {\n        \"items\": [\n");
+    for i in 0..len {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        write!(
+            out,
+            r#"            {{
+                "id": {i},
+                "x": {:.6},
+                "label": "item-{:08x}",
+                "flags": [
+                    {},
+                    null
+                ]
+            }}"#,
+            rng.float(),
+            rng.next(),
+            rng.next().is_multiple_of(2),
+        )
+        .unwrap();
+    }
+    out.push_str("\n        ]\n    }\n}");
+    out
+}
+
+/// A small xorshift generator, so the corpus doesn't depend on `rand`.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn float(&mut self) -> f64 {
+        (self.next() % 2_000_000) as f64 / 1000.0 - 1000.0
+    }
+}
@@ -0,0 +1,206 @@
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId, Criterion,
+    Throughput,
+};
+use std::{fmt, hint::black_box};
+
+#[cfg(feature = "count-alloc")]
+#[global_allocator]
+static ALLOC: bench::CountingAlloc = bench::CountingAlloc;
+
+fn parse(c: &mut Criterion) {
+    for doc in bench_inputs::documents() {
+        let mut group = c.benchmark_group(doc.name);
+        // The multi-megabyte inputs take long enough per iteration that the
+        // default 100 samples would make a run drag on for minutes.
+        group.sample_size(10);
+        group.throughput(Throughput::Bytes(doc.text.len() as u64));
+        let text = &doc.text[..];
+        bench(&mut group, doc.name, "chumsky", text, chumsky_app::parse);
+        bench(&mut group, doc.name, "combine", text, combine_app::parse);
+        bench(&mut group, doc.name, "grmtools", text, grmtools_app::parse);
+        bench(
+            &mut group,
+            doc.name,
+            "handwritten",
+            text,
+            handwritten_app::parse,
+        );
+        // An LR parser like grmtools above. Its tables are generated by a build
+        // script, which costs it more compile time than most entries, and
+        // `ValueParser::new` is hoisted out of the loop, as a caller parsing
+        // many documents would.
+        let parser = lalrpop_app::ValueParser::new();
+        bench(&mut group, doc.name, "lalrpop", text, |text| {
+            parser.parse(text)
+        });
+        bench(&mut group, doc.name, "logos", text, logos_app::parse);
+        bench(&mut group, doc.name, "nom", text, nom_app::parse);
+        bench(&mut group, doc.name, "parol", text, parol_app::parse);
+        bench(&mut group, doc.name, "peg", text, peg_app::parse);
+        bench(&mut group, doc.name, "pest", text, pest_app::parse);
+        bench(
+            &mut group,
+            doc.name,
+            "rust-sitter",
+            text,
+            rust_sitter_app::parser::parse,
+        );
+        // Same parser, but strings without escapes borrow from the input, so
+        // the gap to the entry above is the cost of copying them.
+        bench(
+            &mut group,
+            doc.name,
+            "rust-sitter-borrowed",
+            text,
+            rust_sitter_app::parser::parse_borrowed,
+        );
+        // Same parser again, with the whole tree in one reused arena.
+        let mut arena = bumpalo::Bump::new();
+        bench(&mut group, doc.name, "rust-sitter-arena", text, |text| {
+            arena.reset();
+            rust_sitter_app::parser::parse_in_arena(&arena, text).map(|value| {
+                black_box(value);
+            })
+        });
+        // The baseline: plain `serde_json::from_str` into its own `Value`.
+        bench(
+            &mut group,
+            doc.name,
+            "serde_json",
+            text,
+            serde_json_app::parse_value,
+        );
+        // The runtime under rust-sitter with a C grammar and a hand-written
+        // walk, so the gap to rust-sitter is its generated extraction, and the
+        // gap to the rest is what a generic, error-recovering runtime costs.
+        bench(
+            &mut group,
+            doc.name,
+            "tree-sitter",
+            text,
+            tree_sitter_app::parse,
+        );
+        bench(&mut group, doc.name, "winnow", text, winnow_app::parse);
+        bench(&mut group, doc.name, "zerodep", text, zerodep_app::parse);
+        group.finish();
+    }
+}
+
+/// Benchmarks one backend's `parse` on `text`.
+///
+/// With the `count-alloc` feature, it first prints what a single parse
+/// allocates. Only allocations through Rust's global allocator count, which
+/// leaves out the syntax tree tree-sitter's C runtime builds for rust-sitter.
+/// Counting slows the timings down a little, so compare those against a run
+/// with the feature or one without, not a mix.
+fn bench<'a, T, E: fmt::Debug>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    #[cfg_attr(not(feature = "count-alloc"), allow(unused_variables))] doc: &str,
+    backend: &str,
+    text: &'a str,
+    mut parse: impl FnMut(&'a str) -> Result<T, E>,
+) {
+    #[cfg(feature = "count-alloc")]
+    {
+        let (_, allocations) = bench::count(|| parse(text).expect("benchmark input should parse"));
+        println!(
+            "{doc}/{backend}: {} allocations, {} bytes per parse",
+            allocations.count, allocations.bytes
+        );
+    }
+    group.bench_function(BenchmarkId::from_parameter(backend), |b| {
+        b.iter(|| parse(black_box(text)).expect("benchmark input should parse"))
+    });
+}
+
+/// Compares rust-sitter's `unescape` on a string it can borrow with one of the
+/// same length that has to be decoded into a new allocation.
+fn unescape(c: &mut Criterion) {
+    let plain = "plain text ".repeat(100);
+    let escaped = "plain\\ttext".repeat(100);
+    let mut group = c.benchmark_group("unescape");
+    for (name, body) in [("borrowed", &plain), ("owned", &escaped)] {
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), body, |b, body| {
+            b.iter(|| {
+                rust_sitter_app::parser::unescape(black_box(body))
+                    .expect("benchmark input should unescape")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Times rust-sitter parsing each document again after one digit in its second
+/// half changes: from scratch, and from the syntax tree from before the edit.
+/// Both stop at tree-sitter's syntax tree, as checking it and extracting the
+/// value walk all of it either way.
+fn reparse(c: &mut Criterion) {
+    use rust_sitter_app::incremental::{self, Edit};
+    for doc in bench_inputs::documents() {
+        let text = &doc.text[..];
+        let middle = text.len() / 2;
+        let Some(at) = text[middle..]
+            .find(|c: char| c.is_ascii_digit())
+            .map(|offset| middle + offset)
+        else {
+            continue;
+        };
+        // Another digit that isn't 0, so a number can't gain a leading zero.
+        let digit = match text.as_bytes()[at] {
+            b'9' => '1',
+            d => char::from(d + 1),
+        }
+        .to_string();
+        let edit = Edit::replace(text, at..at + 1, &digit);
+        let edited = format!("{}{digit}{}", &text[..at], &text[at + 1..]);
+        let old_tree = incremental::parse_tree(text);
+        let mut group = c.benchmark_group(format!("reparse/{}", doc.name));
+        group.sample_size(10);
+        group.bench_function("full", |b| {
+            b.iter(|| incremental::parse_tree(black_box(&edited)))
+        });
+        group.bench_function("incremental", |b| {
+            b.iter(|| incremental::reparse(&old_tree, &edit, black_box(&edited)))
+        });
+        group.finish();
+    }
+}
+
+/// Compares rust-sitter parsing an array of records, which repeat the same
+/// few keys, with and without `ParseOptions::intern_keys`.
+///
+/// With the `count-alloc` feature, it first prints how many bytes the value
+/// from each holds on to, which is what interning saves; the timings show what
+/// it costs.
+fn intern_keys(c: &mut Criterion) {
+    use rust_sitter_app::parser::{parse_with_options, ParseOptions};
+    let text = bench_inputs::gen::wide_array(40_000);
+    let mut group = c.benchmark_group("intern_keys");
+    group.sample_size(10);
+    for (name, intern_keys) in [("owned", false), ("interned", true)] {
+        let options = ParseOptions {
+            intern_keys,
+            ..ParseOptions::default()
+        };
+        #[cfg(feature = "count-alloc")]
+        {
+            let (value, retained) = bench::retained(|| {
+                parse_with_options(&text, &options).expect("benchmark input should parse")
+            });
+            println!("intern_keys/{name}: {retained} bytes retained");
+            drop(value);
+        }
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                parse_with_options(black_box(&text), &options)
+                    .expect("benchmark input should parse")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, unescape, reparse, intern_keys);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+//! Criterion benchmarks comparing the parser examples; see `benches/json.rs`.
+//!
+//! This library only holds [`CountingAlloc`], which the benchmarks install as
+//! the global allocator under the `count-alloc` feature to report what each
+//! parse allocates, and how much of that the parsed value keeps.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    ops::Sub,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting every allocation made through it, from any
+/// thread.
+///
+/// A `realloc` counts as an allocation of its new size, since a growing `Vec`
+/// or `String` costs about as much either way. It also keeps track of how many
+/// bytes are allocated and not yet freed, for [`retained`].
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+fn record(size: usize) {
+    COUNT.fetch_add(1, Ordering::Relaxed);
+    BYTES.fetch_add(size, Ordering::Relaxed);
+    LIVE.fetch_add(size, Ordering::Relaxed);
+}
+
+/// Running totals of the allocations made through [`CountingAlloc`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Allocations {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl Allocations {
+    /// The totals so far. These stay at zero unless [`CountingAlloc`] is the
+    /// global allocator.
+    pub fn now() -> Self {
+        Allocations {
+            count: COUNT.load(Ordering::Relaxed),
+            bytes: BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Sub for Allocations {
+    type Output = Allocations;
+
+    fn sub(self, earlier: Allocations) -> Allocations {
+        Allocations {
+            count: self.count - earlier.count,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+/// Runs `f`, returning what it allocated along with its result. Allocations
+/// other threads make meanwhile are counted too.
+pub fn count<T>(f: impl FnOnce() -> T) -> (T, Allocations) {
+    let before = Allocations::now();
+    let value = f();
+    (value, Allocations::now() - before)
+}
+
+/// Runs `f`, returning its result along with how many of the bytes it
+/// allocated are still allocated afterwards, which for a parse is about the
+/// size of the value it returns. Like [`count`], this sees other threads too.
+pub fn retained<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = LIVE.load(Ordering::Relaxed);
+    let value = f();
+    (value, LIVE.load(Ordering::Relaxed).saturating_sub(before))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn counts_allocations() {
+        let (s, allocations) = count(|| "x".repeat(1000));
+        assert_eq!(s.len(), 1000);
+        assert!(allocations.count >= 1, "{allocations:?}");
+        assert!(allocations.bytes >= 1000, "{allocations:?}");
+
+        let (_, allocations) = count(|| {
+            let mut values = Vec::new();
+            for i in 0..100 {
+                values.push(Box::new(i));
+            }
+            values
+        });
+        assert!(allocations.count >= 100, "{allocations:?}");
+    }
+
+    #[test]
+    fn retained_leaves_out_what_was_freed() {
+        let (s, retained) = retained(|| {
+            drop("x".repeat(1_000_000));
+            "y".repeat(100_000)
+        });
+        assert_eq!(s.len(), 100_000);
+        // Other tests allocate and free a few kilobytes meanwhile.
+        assert!((90_000..200_000).contains(&retained), "{retained}");
+    }
+}
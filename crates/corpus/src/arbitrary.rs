@@ -0,0 +1,57 @@
+//! Randomly generated JSON values for property tests.
+//!
+//! Backends convert a [`Json`] to their own AST, serialize it, parse the text
+//! back and check that nothing changed.
+
+use proptest::prelude::*;
+
+/// An owned, backend-independent JSON value, like [`Value`](crate::Value) but
+/// built at runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Always finite, so every number has a JSON literal that parses back to
+    /// exactly the same `f64`.
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep their order and may repeat.
+    Object(Vec<(String, Json)>),
+}
+
+/// Any finite `f64`, from zero and subnormals up to the largest magnitudes.
+pub fn number() -> impl Strategy<Value = f64> {
+    use proptest::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+    POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO
+}
+
+/// Strings weighted towards the characters that need care when escaping:
+/// control characters, quotes and backslashes, and code points outside the
+/// Basic Multilingual Plane.
+pub fn string() -> impl Strategy<Value = String> {
+    let ch = prop_oneof![
+        prop::char::range('\0', '\u{1F}'),
+        prop::sample::select(vec!['"', '\\', '/', '\u{7F}']),
+        prop::char::range(' ', '~'),
+        prop::char::range('\u{10000}', '\u{10FFFF}'),
+        any::<char>(),
+    ];
+    prop::collection::vec(ch, 0..16).prop_map(String::from_iter)
+}
+
+/// Trees of arrays and objects a few levels deep, with any value at the leaves.
+pub fn json() -> impl Strategy<Value = Json> {
+    let leaf = prop_oneof![
+        Just(Json::Null),
+        any::<bool>().prop_map(Json::Bool),
+        number().prop_map(Json::Number),
+        string().prop_map(Json::String),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Json::Array),
+            prop::collection::vec((string(), inner), 0..8).prop_map(Json::Object),
+        ]
+    })
+}
@@ -0,0 +1,363 @@
+//! JSON inputs every parser example is expected to agree on.
+//!
+//! Each case pairs an input with an [`Expectation`]: either it must be
+//! rejected, or it must be accepted and, where it matters, produce a given
+//! [`Value`]. Backends compare their AST against it, with [`same_value`] for
+//! those that build the shared [`ast::JsonValue`].
+
+use std::{fmt, fs, path::Path, str};
+
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+
+/// What a parser should do with a corpus input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expectation {
+    /// The input is valid JSON. When present, this is the value it denotes.
+    Accept(Option<Value>),
+    /// The input is not valid JSON.
+    Reject,
+}
+
+/// A backend-independent JSON value, small enough to write out as a
+/// `static`.
+///
+/// Numbers compare as `f64`, so `1` and `1.0` are the same value. Object
+/// members keep their order and duplicates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(&'static str),
+    Array(&'static [Value]),
+    Object(&'static [(&'static str, Value)]),
+}
+
+use Expectation::{Accept, Reject};
+
+const fn value(v: Value) -> Expectation {
+    Accept(Some(v))
+}
+
+const fn string(s: &'static str) -> Expectation {
+    value(Value::String(s))
+}
+
+const fn number(n: f64) -> Expectation {
+    value(Value::Number(n))
+}
+
+/// An object holding one of each kind of value, with uneven whitespace around
+/// every token.
+pub const WHITESPACE: &str = r#"
+  {
+    "null" : null,
+    "true"  :true ,
+    "false":  false  ,
+    "number" : 123e4 ,
+    "string" : " abc 123 " ,
+    "array" : [ false , 1 , "two" ] ,
+    "object" : { "a" : 1.0 , "b" : "c" } ,
+    "empty_array" : [  ] ,
+    "empty_object" : {   }
+  }
+  "#;
+
+static CASES: &[(&str, Expectation)] = &[
+    // Keywords
+    ("null", value(Value::Null)),
+    ("true", value(Value::Bool(true))),
+    ("false", value(Value::Bool(false))),
+    ("truex", Reject),
+    ("[truex]", Reject),
+    ("falsey", Reject),
+    ("nullx", Reject),
+    ("tru", Reject),
+    // Strings
+    (r#""""#, string("")),
+    (r#""abc""#, string("abc")),
+    (
+        "\"abc\\\"\\\\\\/\\b\\f\\n\\r\\t\\u0001\\u2014\u{2014}def\"",
+        string("abc\"\\/\x08\x0C\n\r\t\x01——def"),
+    ),
+    (r#""\uD83D\uDE10""#, string("😐")),
+    ("\"😐\\n😐\"", string("😐\n😐")),
+    (r#""\u0009""#, string("\t")),
+    (r#""\u000F""#, string("\x0F")),
+    (r#""\uFFFF""#, string("\u{FFFF}")),
+    (r#""\u9F9F""#, string("\u{9F9F}")),
+    (r#""\u00e9""#, string("é")),
+    (r#""\uabcd""#, string("\u{abcd}")),
+    (r#""\uAbCd""#, string("\u{abcd}")),
+    // `b` and `f` are hex digits here, not the `\b` / `\f` escapes.
+    (r#""\u00bf""#, string("\u{00bf}")),
+    (r#""a\nb""#, string("a\nb")),
+    (r#""a\u0001b""#, string("a\x01b")),
+    ("\" \x7f\"", string(" \x7f")),
+    ("\"", Reject),
+    ("\"abc", Reject),
+    (r#""\""#, Reject),
+    (r#"\""#, Reject),
+    ("\\", Reject),
+    (r#""\u123""#, Reject),
+    (r#""\x""#, Reject),
+    (r#"["ok", "\x"]"#, Reject),
+    // Lone and mismatched surrogates
+    (r#""\uD800""#, Reject),
+    (r#""\uD800\uD800""#, Reject),
+    (r#""\uDC00""#, Reject),
+    (r#""\uDE10\uD83D""#, Reject),
+    // Unescaped control characters
+    ("\"a\nb\"", Reject),
+    ("\"a\x01b\"", Reject),
+    ("\"a\tb\"", Reject),
+    ("\"\x00\"", Reject),
+    ("\"\x1f\"", Reject),
+    // Numbers
+    ("0", number(0.0)),
+    ("10", number(10.0)),
+    ("100", number(100.0)),
+    ("0.5", number(0.5)),
+    ("-0.5", number(-0.5)),
+    ("0e1", number(0.0)),
+    ("-0", number(-0.0)),
+    ("-42", number(-42.0)),
+    ("-3.25", number(-3.25)),
+    ("-1e3", number(-1e3)),
+    ("1e-4", number(1e-4)),
+    ("6.022e+23", number(6.022e23)),
+    ("5E10", number(5e10)),
+    ("-2.5E-3", number(-2.5e-3)),
+    ("10000000000000000000000", number(1e22)),
+    // Underflow only loses precision, so it is still accepted.
+    ("1e-400", number(0.0)),
+    ("+1", Reject),
+    ("-", Reject),
+    ("--1", Reject),
+    ("1e", Reject),
+    ("1e+", Reject),
+    ("1E-", Reject),
+    ("01", Reject),
+    ("00", Reject),
+    ("007", Reject),
+    ("-01", Reject),
+    ("[01]", Reject),
+    ("0.", Reject),
+    (".5", Reject),
+    ("1e400", Reject),
+    ("-1e400", Reject),
+    ("1e99999999999999999999", Reject),
+    // Arrays and objects
+    (
+        r#"[42,"x"]"#,
+        value(Value::Array(&[Value::Number(42.0), Value::String("x")])),
+    ),
+    (
+        "[1, -1]",
+        value(Value::Array(&[Value::Number(1.0), Value::Number(-1.0)])),
+    ),
+    (
+        r#"{"a":42,"b":"x"}"#,
+        value(Value::Object(&[
+            ("a", Value::Number(42.0)),
+            ("b", Value::String("x")),
+        ])),
+    ),
+    (
+        WHITESPACE,
+        value(Value::Object(&[
+            ("null", Value::Null),
+            ("true", Value::Bool(true)),
+            ("false", Value::Bool(false)),
+            ("number", Value::Number(123e4)),
+            ("string", Value::String(" abc 123 ")),
+            (
+                "array",
+                Value::Array(&[Value::Bool(false), Value::Number(1.0), Value::String("two")]),
+            ),
+            (
+                "object",
+                Value::Object(&[("a", Value::Number(1.0)), ("b", Value::String("c"))]),
+            ),
+            ("empty_array", Value::Array(&[])),
+            ("empty_object", Value::Object(&[])),
+        ])),
+    ),
+    ("[", Reject),
+    ("[1,]", Reject),
+    ("[,1]", Reject),
+    ("{", Reject),
+    (r#"{"a"}"#, Reject),
+    (r#"{"a":}"#, Reject),
+    (r#"{"a":1,}"#, Reject),
+    ("{1:1}", Reject),
+    ("[1] [2]", Reject),
+];
+
+/// The shared corpus, in a stable order.
+pub fn canonical_cases() -> &'static [(&'static str, Expectation)] {
+    CASES
+}
+
+/// Runs every [`canonical_cases`] input through `parse`, panicking with all of
+/// the mismatches at once.
+///
+/// `same_value` compares a backend's AST with the expected [`Value`].
+pub fn check_canonical_cases<V: fmt::Debug, E: fmt::Debug>(
+    parse: impl Fn(&'static str) -> Result<V, E>,
+    same_value: impl Fn(&V, &Value) -> bool,
+) {
+    let mut mismatches = Vec::new();
+    for (input, expectation) in canonical_cases() {
+        match (parse(input), expectation) {
+            (Ok(value), Accept(Some(expected))) if !same_value(&value, expected) => {
+                mismatches.push(format!("{input:?}: got {value:?}, expected {expected:?}"))
+            }
+            (Ok(value), Reject) => {
+                mismatches.push(format!("{input:?}: expected an error, got {value:?}"))
+            }
+            (Err(err), Accept(_)) => {
+                mismatches.push(format!("{input:?}: expected a value, got {err:?}"))
+            }
+            _ => {}
+        }
+    }
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+/// Whether `value` is `expected`, for [`check_canonical_cases`].
+pub fn same_value<S: AsRef<str>>(value: &ast::JsonValue<S>, expected: &Value) -> bool {
+    use ast::JsonValue;
+    match (value, expected) {
+        (JsonValue::Null, Value::Null) => true,
+        (JsonValue::Bool(b), Value::Bool(e)) => b == e,
+        (JsonValue::Number(n), Value::Number(e)) => n == e,
+        (JsonValue::String(s), Value::String(e)) => s.as_ref() == *e,
+        (JsonValue::Array(items), Value::Array(e)) => {
+            items.len() == e.len() && items.iter().zip(*e).all(|(v, e)| same_value(v, e))
+        }
+        (JsonValue::Object(members), Value::Object(e)) => {
+            members.len() == e.len()
+                && members
+                    .iter()
+                    .zip(*e)
+                    .all(|((k, v), (ek, ev))| k.as_ref() == *ek && same_value(v, ev))
+        }
+        _ => false,
+    }
+}
+
+/// Checks that `parse` accepts arrays nested [`ast::MAX_DEPTH`] deep and rejects
+/// one more level, returning that error for the caller to check it is the
+/// depth limit's.
+///
+/// The inputs are leaked to make them `'static`, as [`canonical_cases`]' are,
+/// so that errors may borrow from them.
+pub fn check_max_depth<V: fmt::Debug, E>(parse: impl Fn(&'static str) -> Result<V, E>) -> E {
+    let nested = |depth| String::leak(format!("{}{}", "[".repeat(depth), "]".repeat(depth)));
+    let depth = ast::MAX_DEPTH;
+    assert!(
+        parse(nested(depth)).is_ok(),
+        "rejected arrays nested {depth} deep"
+    );
+    match parse(nested(depth + 1)) {
+        Ok(value) => panic!("accepted arrays nested {} deep: {value:?}", depth + 1),
+        Err(err) => err,
+    }
+}
+
+/// What [JSONTestSuite](https://github.com/nst/JSONTestSuite) expects of one of
+/// its inputs, going by the file name prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuiteExpectation {
+    /// `y_`: must be accepted.
+    Accept,
+    /// `n_`: must be rejected.
+    Reject,
+    /// `i_`: implementation defined, either outcome is fine.
+    Either,
+}
+
+/// One file from the vendored JSONTestSuite.
+pub struct SuiteCase {
+    /// The file name without its `.json` extension, e.g. `y_array_empty`.
+    pub name: String,
+    /// The raw file contents. Some are deliberately not UTF-8.
+    pub input: Vec<u8>,
+    pub expectation: SuiteExpectation,
+}
+
+/// Loads every `test_parsing` case from the vendored JSONTestSuite, sorted by
+/// name.
+pub fn json_test_suite() -> Vec<SuiteCase> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("JSONTestSuite/test_parsing");
+    let mut cases: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", dir.display()))
+        .map(|entry| {
+            let path = entry.expect("failed to read directory entry").path();
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("file names are ASCII")
+                .to_owned();
+            let expectation = match &name[..2] {
+                "y_" => SuiteExpectation::Accept,
+                "n_" => SuiteExpectation::Reject,
+                "i_" => SuiteExpectation::Either,
+                _ => panic!("unexpected file in JSONTestSuite: {name}"),
+            };
+            let input = fs::read(&path)
+                .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+            SuiteCase {
+                name,
+                input,
+                expectation,
+            }
+        })
+        .collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+/// Runs the whole [`json_test_suite`] through `accepts`, printing a summary of
+/// the pass counts.
+///
+/// `known_failures` names the `y_` and `n_` cases a backend is known to get
+/// wrong. Any other wrong answer panics, and so does a listed case that starts
+/// passing, so the list can only shrink. Input that isn't UTF-8 counts as
+/// rejected without calling `accepts`.
+pub fn check_json_test_suite(accepts: impl Fn(&str) -> bool, known_failures: &[&str]) {
+    let (mut y, mut n, mut i) = ((0, 0), (0, 0), (0, 0));
+    let mut unexpected = Vec::new();
+    let mut fixed = Vec::new();
+    for case in json_test_suite() {
+        let accepted = str::from_utf8(&case.input).is_ok_and(&accepts);
+        let (tally, ok) = match case.expectation {
+            SuiteExpectation::Accept => (&mut y, accepted),
+            SuiteExpectation::Reject => (&mut n, !accepted),
+            SuiteExpectation::Either => (&mut i, accepted),
+        };
+        tally.1 += 1;
+        if ok {
+            tally.0 += 1;
+        }
+        let known = known_failures.contains(&case.name.as_str());
+        if case.expectation != SuiteExpectation::Either && ok == known {
+            if known {
+                fixed.push(case.name);
+            } else {
+                unexpected.push(case.name);
+            }
+        }
+    }
+    println!(
+        "JSONTestSuite: y_ {}/{} accepted, n_ {}/{} rejected, i_ {}/{} accepted",
+        y.0, y.1, n.0, n.1, i.0, i.1
+    );
+    assert!(unexpected.is_empty(), "unexpected results: {unexpected:?}");
+    assert!(
+        fixed.is_empty(),
+        "now passing, remove from the known failures: {fixed:?}"
+    );
+}
@@ -0,0 +1,35 @@
+//! A `#![no_std]` crate using the hand-written parser without its `std`
+//! feature, so that `cargo build -p no-std` fails if the parser comes to need
+//! `std` there.
+
+#![no_std]
+
+extern crate alloc;
+
+use handwritten_app::{parse, JsonValue, ParseError};
+
+/// How many values `input` holds, itself included.
+pub fn count_values(input: &str) -> Result<usize, ParseError> {
+    let mut count = 0;
+    let mut values = alloc::vec![parse(input)?];
+    while let Some(value) = values.pop() {
+        count += 1;
+        match value {
+            JsonValue::Array(items) => values.extend(items),
+            JsonValue::Object(members) => values.extend(members.into_iter().map(|(_, v)| v)),
+            _ => {}
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_a_small_document() {
+        assert_eq!(count_values(r#"{"a": [1, "two", null], "b": true}"#), Ok(6));
+        assert!(count_values("[1,").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Turns the output of `cargo bench -p bench` into markdown, run with
+//! `cargo run -p results [CRITERION_DIR]`.
+//!
+//! Each benchmark group, which is one input document, gets a table of the
+//! backends' throughput in MB/s, fastest first. The groups go from the smallest
+//! input to the largest. A backend that wasn't benchmarked on an input has no
+//! row in its table.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, String>;
+
+fn main() {
+    let dir = match env::args().nth(1) {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/criterion"),
+    };
+    match read(&dir) {
+        Ok(measurements) if measurements.is_empty() => {
+            eprintln!(
+                "error: no results in {}; run `cargo bench -p bench` first",
+                dir.display()
+            );
+            process::exit(1);
+        }
+        Ok(measurements) => print!("{}", tables(measurements)),
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+/// The parts of criterion's `new/benchmark.json` used here.
+#[derive(Deserialize)]
+struct Benchmark {
+    group_id: String,
+    full_id: String,
+    /// Like `{"Bytes": 105}`, or another unit for benchmarks that count
+    /// something else.
+    throughput: Option<serde_json::Value>,
+}
+
+/// The parts of criterion's `new/estimates.json` used here.
+#[derive(Deserialize)]
+struct Estimates {
+    median: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    /// In nanoseconds per iteration.
+    point_estimate: f64,
+}
+
+/// The latest run of one backend on one input.
+#[derive(Debug, PartialEq)]
+struct Measurement {
+    input: String,
+    backend: String,
+    /// The size of the input.
+    bytes: u64,
+    /// The median time to parse the input once.
+    nanos: f64,
+}
+
+impl Measurement {
+    fn megabytes_per_second(&self) -> f64 {
+        self.bytes as f64 / 1e6 / (self.nanos / 1e9)
+    }
+}
+
+/// Every benchmark under `dir` that measured bytes of throughput, in no
+/// particular order.
+fn read(dir: &Path) -> Result<Vec<Measurement>> {
+    let mut measurements = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let new = dir.join("new");
+        if new.join("benchmark.json").is_file() {
+            let benchmark: Benchmark = read_json(&new.join("benchmark.json"))?;
+            let estimates: Estimates = read_json(&new.join("estimates.json"))?;
+            let bytes = benchmark
+                .throughput
+                .as_ref()
+                .and_then(|t| t["Bytes"].as_u64());
+            if let Some(bytes) = bytes {
+                let backend = benchmark
+                    .full_id
+                    .strip_prefix(&format!("{}/", benchmark.group_id))
+                    .unwrap_or(&benchmark.full_id)
+                    .to_owned();
+                measurements.push(Measurement {
+                    input: benchmark.group_id,
+                    backend,
+                    bytes,
+                    nanos: estimates.median.point_estimate,
+                });
+            }
+            continue;
+        }
+        let entries = fs::read_dir(&dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|err| format!("{}: {err}", dir.display()))?
+                .path();
+            // Criterion's HTML reports sit alongside the benchmarks.
+            if path.is_dir() && !path.ends_with("report") {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(measurements)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// A markdown table per input, smallest input first, with the backends in
+/// each fastest first.
+fn tables(mut measurements: Vec<Measurement>) -> String {
+    measurements.sort_by(|a, b| {
+        (a.bytes, &a.input)
+            .cmp(&(b.bytes, &b.input))
+            .then(a.nanos.total_cmp(&b.nanos))
+    });
+    let mut out = String::new();
+    for (i, measurement) in measurements.iter().enumerate() {
+        if i == 0 || measurements[i - 1].input != measurement.input {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "### {} ({})\n\n",
+                measurement.input,
+                size(measurement.bytes)
+            ));
+            out.push_str("Backend | Throughput\n");
+            out.push_str("--------|-----------\n");
+        }
+        out.push_str(&format!(
+            "{} | {:.1} MB/s\n",
+            measurement.backend,
+            measurement.megabytes_per_second()
+        ));
+    }
+    out
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{bytes} B"),
+        1_000..1_000_000 => format!("{:.1} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixture_tables() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixture/criterion");
+        let measurements = read(&fixture).unwrap();
+        assert_eq!(measurements.len(), 5);
+        assert_eq!(
+            tables(measurements),
+            "\
+### small_object (105 B)
+
+Backend | Throughput
+--------|-----------
+serde_json | 197.9 MB/s
+peg | 106.2 MB/s
+lalrpop | 6.1 MB/s
+
+### large_array (4.1 MB)
+
+Backend | Throughput
+--------|-----------
+serde_json | 82.2 MB/s
+peg | 74.4 MB/s
+"
+        );
+    }
+}
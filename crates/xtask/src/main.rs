@@ -0,0 +1,176 @@
+//! Repository chores, run with `cargo xtask <command>`.
+//!
+//! `cargo xtask cost [--output PATH]` builds every `examples/*-app` in release
+//! from scratch and writes a markdown table of each one's stripped binary size
+//! and build time, as reported by `cargo build --timings`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::Duration,
+};
+
+const USAGE: &str = "usage: cargo xtask cost [--output PATH]";
+
+type Result<T> = std::result::Result<T, String>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let output = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["cost"] => None,
+        ["cost", "--output", path] => Some(PathBuf::from(path)),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    if let Err(err) = cost(output.as_deref()) {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+/// What building one example cost.
+struct Cost {
+    name: String,
+    /// The size of the stripped release binary, in bytes.
+    size: u64,
+    /// The total time `cargo build --timings` reports for a clean build.
+    build: Duration,
+}
+
+fn cost(output: Option<&Path>) -> Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let names = examples(&root.join("examples"))?;
+    let mut costs = Vec::with_capacity(names.len());
+    for name in names {
+        eprintln!("building {name}");
+        costs.push(measure(&root, name)?);
+    }
+    let table = table(&costs);
+    match output {
+        Some(path) => fs::write(path, table).map_err(|err| format!("{}: {err}", path.display())),
+        None => {
+            print!("{table}");
+            Ok(())
+        }
+    }
+}
+
+/// The package names of the `*-app` directories in `dir`, sorted. Each one's
+/// binary is named after its package, which is named after its directory.
+fn examples(dir: &Path) -> Result<Vec<String>> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| format!("{}: {err}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.ends_with("-app") && entry.path().join("Cargo.toml").is_file() {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Builds `name` in a target directory of its own, emptied first, so that its
+/// dependencies count towards its build time.
+fn measure(root: &Path, name: String) -> Result<Cost> {
+    let target_dir = root.join("target/xtask").join(&name);
+    if target_dir.exists() {
+        fs::remove_dir_all(&target_dir)
+            .map_err(|err| format!("{}: {err}", target_dir.display()))?;
+    }
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .current_dir(root)
+        .args(["build", "--release", "--timings", "--package", &name])
+        .args(["--config", "profile.release.strip=true"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .map_err(|err| format!("failed to run cargo: {err}"))?;
+    if !status.success() {
+        return Err(format!("building {name} failed"));
+    }
+
+    let binary = target_dir
+        .join("release")
+        .join(name.clone() + env::consts::EXE_SUFFIX);
+    let size = fs::metadata(&binary)
+        .map_err(|err| format!("{}: {err}", binary.display()))?
+        .len();
+    let report = target_dir.join("cargo-timings/cargo-timing.html");
+    let html = fs::read_to_string(&report).map_err(|err| format!("{}: {err}", report.display()))?;
+    let build = total_time(&html)
+        .ok_or_else(|| format!("{}: no total time in the report", report.display()))?;
+    Ok(Cost { name, size, build })
+}
+
+/// Reads the total build time out of a `--timings` HTML report, where it is
+/// written like `12.3s`, or `67.9s (1m 7.9s)` past a minute.
+fn total_time(html: &str) -> Option<Duration> {
+    let (_, rest) = html.split_once("Total time:</td><td>")?;
+    let seconds = rest.split(|c: char| c.is_whitespace() || c == '<').next()?;
+    Some(Duration::from_secs_f64(
+        seconds.strip_suffix('s')?.parse().ok()?,
+    ))
+}
+
+/// A markdown table with a row for each example, in the order given.
+fn table(costs: &[Cost]) -> String {
+    let mut table = String::from("Name | Size (release, stripped) | Build (release)\n");
+    table.push_str("-----|--------------------------|----------------\n");
+    for cost in costs {
+        let name = cost.name.strip_suffix("-app").unwrap_or(&cost.name);
+        table.push_str(&format!(
+            "{name} | {} KiB | {:.1}s\n",
+            cost.size.div_ceil(1024),
+            cost.build.as_secs_f64()
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn one_row_per_example() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+        let names = examples(&dir).unwrap();
+        assert!(names.contains(&"null-app".to_owned()), "{names:?}");
+        assert!(names.contains(&"rust-sitter-app".to_owned()), "{names:?}");
+
+        let costs: Vec<_> = names
+            .iter()
+            .map(|name| Cost {
+                name: name.clone(),
+                size: 300_000,
+                build: Duration::from_millis(61_300),
+            })
+            .collect();
+        let table = table(&costs);
+        let rows: Vec<_> = table.lines().skip(2).collect();
+        assert_eq!(rows.len(), names.len(), "{table}");
+        assert_eq!(
+            rows[names.iter().position(|n| n == "peg-app").unwrap()],
+            "peg | 293 KiB | 61.3s"
+        );
+    }
+
+    #[test]
+    fn total_time_formats() {
+        let report = |time| format!("<tr><td>Total time:</td><td>{time}</td></tr>");
+        assert_eq!(
+            total_time(&report("0.2s")),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            total_time(&report("62.5s (1m 2.5s)")),
+            Some(Duration::from_millis(62_500))
+        );
+        assert_eq!(total_time("no report"), None);
+    }
+}
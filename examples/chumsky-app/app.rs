@@ -1,18 +1,99 @@
 //! This is a parser for JSON.
 //! Run it with the following command:
-//! cargo run --example json -- examples/sample.json
-
-mod parser;
+//! cargo run -p chumsky-app -- examples/sample.json
 
 use std::{env, fs};
 
-use chumsky::Parser;
+use chumsky_app::{ParseError, ParseErrorReason};
+use codemap::CodeMap;
+use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, SpanStyle};
+
+fn describe(c: &Option<char>) -> String {
+    match c {
+        Some(c) => format!("{c:?}"),
+        None => "end of input".to_string(),
+    }
+}
+
+fn convert_parse_error_to_diagnostic(file_span: &codemap::Span, error: &ParseError) -> Diagnostic {
+    let span = file_span.subspan(error.start as u64, error.end as u64);
+    let (message, mut spans) = match &error.reason {
+        ParseErrorReason::Unexpected { found, expected } => {
+            let mut expected: Vec<_> = expected.iter().map(describe).collect();
+            expected.sort();
+            let label = match expected.as_slice() {
+                [] => "unexpected".to_string(),
+                [one] => format!("expected {one}"),
+                many => format!("expected one of {}", many.join(", ")),
+            };
+            (
+                format!("Unexpected {}", describe(found)),
+                vec![SpanLabel {
+                    span,
+                    style: SpanStyle::Primary,
+                    label: Some(label),
+                }],
+            )
+        }
+
+        ParseErrorReason::Unclosed {
+            delimiter,
+            start,
+            end,
+        } => (
+            format!("Unclosed delimiter {delimiter:?}"),
+            vec![
+                SpanLabel {
+                    span,
+                    style: SpanStyle::Primary,
+                    label: Some(format!("expected the closing delimiter for {delimiter:?}")),
+                },
+                SpanLabel {
+                    span: file_span.subspan(*start as u64, *end as u64),
+                    style: SpanStyle::Secondary,
+                    label: Some("opened here".to_string()),
+                },
+            ],
+        ),
+
+        ParseErrorReason::Invalid(message) => (
+            format!("Invalid {}: {message}", error.label.unwrap_or("value")),
+            vec![SpanLabel {
+                span,
+                style: SpanStyle::Primary,
+                label: Some(message.clone()),
+            }],
+        ),
+
+        ParseErrorReason::DepthLimitExceeded(max) => (
+            format!("Nesting too deep: more than {max} levels of arrays and objects"),
+            vec![SpanLabel {
+                span,
+                style: SpanStyle::Primary,
+                label: Some("exceeds the depth limit".to_string()),
+            }],
+        ),
+    };
+    spans.sort_by_key(|label| label.style != SpanStyle::Primary);
+    let message = match (&error.reason, error.label) {
+        (ParseErrorReason::Unexpected { .. }, Some(label)) => {
+            format!("{message} while parsing {label}")
+        }
+        _ => message,
+    };
+    Diagnostic {
+        level: Level::Error,
+        message,
+        code: Some("S000".to_string()),
+        spans,
+    }
+}
 
 fn main() {
-    let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
-        .expect("Failed to read file");
+    let file = env::args().nth(1).expect("Expected file argument");
+    let src = fs::read_to_string(&file).expect("Failed to read file");
 
-    let (json, errs) = parser::parser().parse_recovery(src.trim());
+    let (json, errs) = chumsky_app::parse_recovery(src.as_str());
     #[cfg(debug_assertions)]
     {
         println!("{:#?}", json);
@@ -21,7 +102,16 @@ fn main() {
     {
         std::hint::black_box(json);
     }
-    for err in errs {
-        eprintln!("{err}");
+    if !errs.is_empty() {
+        let mut codemap = CodeMap::new();
+        let file_span = codemap.add_file(file, src.to_string());
+        let diagnostics: Vec<_> = errs
+            .iter()
+            .map(|error| convert_parse_error_to_diagnostic(&file_span.span, error))
+            .collect();
+
+        let mut emitter = Emitter::stderr(ColorConfig::Always, Some(&codemap));
+        emitter.emit(&diagnostics);
+        std::process::exit(1);
     }
 }
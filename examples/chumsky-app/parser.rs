@@ -1,24 +1,158 @@
 //! This is a parser for JSON.
 //! Run it with the following command:
-//! cargo run --example json -- examples/sample.json
+//! cargo run -p chumsky-app -- examples/sample.json
 
+use chumsky::error::SimpleReason;
 use chumsky::prelude::*;
-use std::collections::HashMap;
-
-#[allow(dead_code)]
-#[derive(Clone, Debug)]
-pub enum Json {
-    Invalid,
-    Null,
-    Bool(bool),
-    Str(String),
-    Num(f64),
-    Array(Vec<Json>),
-    Object(HashMap<String, Json>),
+
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// An error found while parsing, with byte offsets into the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub reason: ParseErrorReason,
+    /// The construct being parsed when the error happened, such as `"string"`.
+    pub label: Option<&'static str>,
+    /// Inclusive start of the error.
+    pub start: usize,
+    /// Exclusive end of the error.
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorReason {
+    /// Found `found` (`None` for the end of input) where only `expected` would do.
+    Unexpected {
+        found: Option<char>,
+        expected: Vec<Option<char>>,
+    },
+    /// A `[` or `{` was never closed.
+    Unclosed {
+        delimiter: char,
+        start: usize,
+        end: usize,
+    },
+    /// A literal that matches the grammar but does not denote a value, like a
+    /// lone surrogate or a number too large for an `f64`.
+    Invalid(String),
+    /// Arrays and objects are nested more deeply than [`MAX_DEPTH`].
+    DepthLimitExceeded(usize),
+}
+
+/// Parses a JSON document, recovering from errors where it can.
+///
+/// Even when there are errors, the value is usually still returned, with
+/// [`JsonValue::Null`] in place of the parts that failed; the errors say which
+/// those were.
+pub fn parse_recovery(src: &str) -> (Option<JsonValue>, Vec<ParseError>) {
+    if let Err(err) = check_depth(src) {
+        return (None, vec![err]);
+    }
+    let (json, errs) = parser().parse_recovery(src);
+    // Chumsky counts `char`s; everything else in the repo counts bytes.
+    let offsets: Vec<usize> = src
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([src.len()])
+        .collect();
+    let byte = |i: usize| offsets[i.min(offsets.len() - 1)];
+    let errs = errs
+        .into_iter()
+        .map(|err| {
+            let span = err.span();
+            let reason = match err.reason() {
+                SimpleReason::Unexpected => ParseErrorReason::Unexpected {
+                    found: err.found().copied(),
+                    expected: err.expected().copied().collect(),
+                },
+                SimpleReason::Unclosed { span, delimiter } => ParseErrorReason::Unclosed {
+                    delimiter: *delimiter,
+                    start: byte(span.start),
+                    end: byte(span.end),
+                },
+                SimpleReason::Custom(message) => ParseErrorReason::Invalid(message.clone()),
+            };
+            ParseError {
+                reason,
+                label: err.label(),
+                start: byte(span.start),
+                end: byte(span.end),
+            }
+        })
+        .collect();
+    (json, errs)
+}
+
+/// Parses a JSON document, failing if there was any error at all.
+pub fn parse(src: &str) -> Result<JsonValue, Vec<ParseError>> {
+    match parse_recovery(src) {
+        (Some(json), errs) if errs.is_empty() => Ok(json),
+        (_, errs) => Err(errs),
+    }
+}
+
+/// Chumsky's parsers recurse once per level of nesting, so deep input has to be
+/// turned away before it reaches them.
+fn check_depth(src: &str) -> Result<(), ParseError> {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (i, c) in src.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => {
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err(ParseError {
+                        reason: ParseErrorReason::DepthLimitExceeded(MAX_DEPTH),
+                        label: None,
+                        start: i,
+                        end: i + 1,
+                    });
+                }
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Part of a string body: either a character that stands for itself, or one
+/// UTF-16 code unit from a `\u` escape.
+#[derive(Clone)]
+enum Piece {
+    Char(char),
+    Unit(u16),
+}
+
+/// Pairs up the surrogates from `\u` escapes, as rust-sitter's `unescape` does.
+fn decode(pieces: Vec<Piece>) -> Option<String> {
+    let mut units = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match piece {
+            Piece::Char(c) => units.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+            Piece::Unit(u) => units.push(u),
+        }
+    }
+    String::from_utf16(&units).ok()
 }
 
-pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
-    recursive(|value| {
+pub fn parser() -> impl Parser<char, JsonValue, Error = Simple<char>> {
+    // Only JSON's four whitespace characters, unlike `.padded()`, which takes
+    // anything `char::is_whitespace` does.
+    let ws = filter(|c: &char| matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .repeated()
+        .ignored();
+
+    let value = recursive(|value| {
         let frac = just('.').chain(text::digits(10));
 
         let exp = just('e')
@@ -34,6 +168,12 @@ pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
             .collect::<String>()
             .from_str()
             .unwrapped()
+            .validate(|n: f64, span, emit| {
+                if !n.is_finite() {
+                    emit(Simple::custom(span, "number out of range"));
+                }
+                n
+            })
             .labelled("number");
 
         let escape = just('\\').ignore_then(
@@ -45,61 +185,140 @@ pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
                 .or(just('n').to('\n'))
                 .or(just('r').to('\r'))
                 .or(just('t').to('\t'))
+                .map(Piece::Char)
                 .or(just('u').ignore_then(
-                    filter(|c: &char| c.is_digit(16))
+                    filter(|c: &char| c.is_ascii_hexdigit())
                         .repeated()
                         .exactly(4)
                         .collect::<String>()
-                        .validate(|digits, span, emit| {
-                            char::from_u32(u32::from_str_radix(&digits, 16).unwrap())
-                                .unwrap_or_else(|| {
-                                    emit(Simple::custom(span, "invalid unicode character"));
-                                    '\u{FFFD}' // unicode replacement character
-                                })
-                        }),
+                        .map(|digits| Piece::Unit(u16::from_str_radix(&digits, 16).unwrap())),
                 )),
         );
 
         let string = just('"')
-            .ignore_then(filter(|c| *c != '\\' && *c != '"').or(escape).repeated())
+            .ignore_then(
+                filter(|c: &char| *c != '\\' && *c != '"' && *c >= ' ')
+                    .map(Piece::Char)
+                    .or(escape)
+                    .repeated(),
+            )
             .then_ignore(just('"'))
-            .collect::<String>()
+            .validate(|pieces, span, emit| {
+                decode(pieces).unwrap_or_else(|| {
+                    emit(Simple::custom(span, "unpaired surrogate"));
+                    String::new()
+                })
+            })
             .labelled("string");
 
+        // A missing or broken value is replaced with `Null`, skipping to the
+        // next separator so the remaining members are still parsed.
+        let element = |close| {
+            value
+                .clone()
+                .recover_with(skip_until([',', close], |_| JsonValue::Null))
+        };
+
         let array = value
             .clone()
-            .chain(just(',').ignore_then(value.clone()).repeated())
+            .chain(just(',').ignore_then(element(']')).repeated())
             .or_not()
             .flatten()
+            .padded_by(ws)
             .delimited_by(just('['), just(']'))
-            .map(Json::Array)
+            .map(JsonValue::Array)
             .labelled("array");
 
-        let member = string.clone().then_ignore(just(':').padded()).then(value);
+        let member = string
+            .padded_by(ws)
+            .then_ignore(just(':'))
+            .then(element('}'));
         let object = member
             .clone()
-            .chain(just(',').padded().ignore_then(member).repeated())
+            .chain(just(',').ignore_then(member).repeated())
             .or_not()
             .flatten()
-            .padded()
+            .padded_by(ws)
             .delimited_by(just('{'), just('}'))
-            .collect::<HashMap<String, Json>>()
-            .map(Json::Object)
+            .map(JsonValue::Object)
             .labelled("object");
 
         just("null")
-            .to(Json::Null)
+            .to(JsonValue::Null)
             .labelled("null")
-            .or(just("true").to(Json::Bool(true)).labelled("true"))
-            .or(just("false").to(Json::Bool(false)).labelled("false"))
-            .or(number.map(Json::Num))
-            .or(string.map(Json::Str))
+            .or(just("true").to(JsonValue::Bool(true)).labelled("true"))
+            .or(just("false").to(JsonValue::Bool(false)).labelled("false"))
+            .or(number.map(JsonValue::Number))
+            .or(string.map(JsonValue::String))
             .or(array)
             .or(object)
-            .recover_with(nested_delimiters('{', '}', [('[', ']')], |_| Json::Invalid))
-            .recover_with(nested_delimiters('[', ']', [('{', '}')], |_| Json::Invalid))
-            .recover_with(skip_then_retry_until(['}', ']']))
-            .padded()
-    })
-    .then_ignore(end().recover_with(skip_then_retry_until([])))
+            .recover_with(nested_delimiters('{', '}', [('[', ']')], |_| {
+                JsonValue::Null
+            }))
+            .recover_with(nested_delimiters('[', ']', [('{', '}')], |_| {
+                JsonValue::Null
+            }))
+            .padded_by(ws)
+    });
+
+    value.then_ignore(end().recover_with(skip_then_retry_until([])))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn recovers_from_a_missing_value() {
+        let (json, errs) = parse_recovery(r#"{"a": , "b": 1}"#);
+        assert_eq!(
+            json,
+            Some(JsonValue::Object(vec![
+                ("a".to_string(), JsonValue::Null),
+                ("b".to_string(), JsonValue::Number(1.0)),
+            ]))
+        );
+        assert_eq!(errs.len(), 1, "{errs:?}");
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::Unexpected {
+                found: Some(','),
+                ..
+            }
+        ));
+        assert_eq!((errs[0].start, errs[0].end), (6, 7));
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let errs = parse(r#"["é", "\uD800"]"#).unwrap_err();
+        assert_eq!(errs.len(), 1, "{errs:?}");
+        assert_eq!(
+            errs[0].reason,
+            ParseErrorReason::Invalid("unpaired surrogate".to_string())
+        );
+        assert_eq!((errs[0].start, errs[0].end), (7, 15));
+    }
+
+    #[test]
+    fn max_depth() {
+        let errs = corpus::check_max_depth(parse);
+        assert_eq!(
+            errs[0].reason,
+            ParseErrorReason::DepthLimitExceeded(MAX_DEPTH)
+        );
+        assert_eq!(errs[0].start, MAX_DEPTH);
+        // Brackets inside strings don't count.
+        assert!(parse(&format!("\"{}\"", "[".repeat(1000))).is_ok());
+    }
 }
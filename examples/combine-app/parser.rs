@@ -1,73 +1,101 @@
-use std::collections::HashMap;
+#[macro_use]
+extern crate combine;
 
-use combine::error::ParseError;
+use combine::error::{ParseError, StreamError};
+use combine::stream::{easy, StreamErrorFor};
 use combine::{Parser, RangeStream, StreamOnce};
 
-use combine::parser::byte::{byte, spaces};
+use combine::parser::byte::{byte, hex_digit};
 use combine::parser::choice::{choice, optional};
 use combine::parser::combinator::no_partial;
-use combine::parser::item::{one_of, satisfy_map};
+use combine::parser::item::{eof, one_of, satisfy};
 use combine::parser::range;
-use combine::parser::repeat::{escaped, sep_by};
+use combine::parser::repeat::{count_min_max, escaped, sep_by};
 use combine::parser::sequence::between;
 
-#[derive(PartialEq, Debug)]
-pub enum Value {
-    Number(f64),
-    String(String),
-    Bool(bool),
-    Null,
-    Object(HashMap<String, Value>),
-    Array(Vec<Value>),
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// A parse failure at a byte offset into the input.
+pub type Error = easy::Errors<char, String, usize>;
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, Error> {
+    let mut parser = (json_value(), eof()).map(|(value, _)| value);
+    match parser.easy_parse(input.as_bytes()) {
+        Ok((json, _)) => Ok(json),
+        Err(err) => Err(err
+            .map_position(|p| p.translate_position(input))
+            .map_token(char::from)
+            .map_range(|r| String::from_utf8_lossy(r).into_owned())),
+    }
 }
 
 #[inline(always)]
-pub fn json_value<'a, I>() -> impl Parser<Input = I, Output = Value> + 'a
+pub fn json_value<'a, I>() -> impl Parser<Input = I, Output = JsonValue> + 'a
 where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    spaces().with(json_value_())
+    range::take_while(is_whitespace).with(json_value_(0))
 }
 
 // We need to use `parser!` to break the recursive use of `value` to prevent the returned parser
 // from containing itself
 parser! {
+    /// `depth` counts the arrays and objects enclosing this value
     #[inline(always)]
-    fn json_value_['a, I]()(I) -> Value
+    fn json_value_['a, I](depth: usize)(I) -> JsonValue
         where [ I: RangeStream<Item = u8, Range = &'a [u8]> + 'a ]
     {
         choice((
-            json_string().map(Value::String),
-            object().map(Value::Object),
-            array().map(Value::Array),
-            number().map(Value::Number),
-            lex(range::range(&b"false"[..]).map(|_| Value::Bool(false))),
-            lex(range::range(&b"true"[..]).map(|_| Value::Bool(true))),
-            lex(range::range(&b"null"[..]).map(|_| Value::Null)),
+            json_string().map(JsonValue::String),
+            object(*depth + 1).map(JsonValue::Object),
+            array(*depth + 1).map(JsonValue::Array),
+            number().map(JsonValue::Number),
+            lex(range::range(&b"false"[..]).map(|_| JsonValue::Bool(false))),
+            lex(range::range(&b"true"[..]).map(|_| JsonValue::Bool(true))),
+            lex(range::range(&b"null"[..]).map(|_| JsonValue::Null)),
         ))
     }
 }
 
-fn object<'a, I>() -> impl Parser<Input = I, Output = HashMap<String, Value>> + 'a
+/// An opening `[` or `{` at `depth`, failing past [`MAX_DEPTH`] so that
+/// hostile input can't recurse without bound.
+fn open<'a, I>(delimiter: u8, depth: usize) -> impl Parser<Input = I, Output = ()> + 'a
+where
+    I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    lex(byte(delimiter).and_then(move |_| {
+        if depth > MAX_DEPTH {
+            Err(StreamErrorFor::<I>::message_static_message(
+                "nesting too deep",
+            ))
+        } else {
+            Ok(())
+        }
+    }))
+}
+
+fn object<'a, I>(depth: usize) -> impl Parser<Input = I, Output = Vec<(String, JsonValue)>> + 'a
 where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    let field = (json_string(), lex(byte(b':')), json_value_()).map(|t| (t.0, t.2));
+    let field = (json_string(), lex(byte(b':')), json_value_(depth)).map(|t| (t.0, t.2));
     let fields = sep_by(field, lex(byte(b',')));
-    between(lex(byte(b'{')), lex(byte(b'}')), fields).expected("object")
+    between(open(b'{', depth), lex(byte(b'}')), fields).expected("object")
 }
 
-fn array<'a, I>() -> impl Parser<Input = I, Output = Vec<Value>> + 'a
+fn array<'a, I>(depth: usize) -> impl Parser<Input = I, Output = Vec<JsonValue>> + 'a
 where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
     between(
-        lex(byte(b'[')),
+        open(b'[', depth),
         lex(byte(b']')),
-        sep_by(json_value_(), lex(byte(b','))),
+        sep_by(json_value_(depth), lex(byte(b','))),
     )
     .expected("array")
 }
@@ -77,28 +105,56 @@ where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    let back_slash_byte = satisfy_map(|c| {
-        Some(match c {
-            b'"' => b'"',
-            b'\\' => b'\\',
-            b'/' => b'/',
-            b'b' => '\u{0008}' as u8,
-            b'f' => '\u{000c}' as u8,
-            b'n' => b'\n',
-            b'r' => b'\r',
-            b't' => b'\t',
-            _ => return None,
-        })
-    });
+    let back_slash_byte = one_of(b"\"\\/bfnrt".iter().copied()).map(|_| ()).or((
+        byte(b'u'),
+        count_min_max::<Vec<_>, _>(4, 4, hex_digit()),
+    )
+        .map(|_| ()));
+    // Recognizing the body first leaves the decoding to `unescape`, where the
+    // surrogates from `\u` escapes can be paired up.
     let inner = range::recognize(escaped(
-        range::take_while1(|b| b != b'\\' && b != b'"'),
+        range::take_while1(|b| b != b'\\' && b != b'"' && b >= 0x20),
         b'\\',
         back_slash_byte,
     ))
-    .map(|s| std::str::from_utf8(s).unwrap().to_owned());
+    .and_then(|s: &'a [u8]| {
+        unescape(std::str::from_utf8(s).unwrap())
+            .ok_or_else(|| StreamErrorFor::<I>::message_static_message("unpaired surrogate"))
+    });
     between(byte(b'"'), lex(byte(b'"')), inner).expected("string")
 }
 
+/// Decodes a string body that has already been checked against the grammar,
+/// as rust-sitter's `unescape` does.
+fn unescape(body: &str) -> Option<String> {
+    if !body.contains('\\') {
+        return Some(body.to_owned());
+    }
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match chars.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let rest = chars.as_str();
+                chars = rest[4..].chars();
+                u16::from_str_radix(&rest[..4], 16).unwrap()
+            }
+            c => c as u16,
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).ok()
+}
+
 fn number<'a, I>() -> impl Parser<Input = I, Output = f64> + 'a
 where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
@@ -106,14 +162,28 @@ where
 {
     no_partial(
         lex(range::recognize(no_partial((
-            optional(one_of("+-".bytes())),
-            byte(b'0').or((digits(), optional((byte(b'.'), digits()))).map(|_| b'0')),
+            optional(byte(b'-')),
+            byte(b'0').map(|_| ()).or((
+                satisfy(|b: u8| b.is_ascii_digit() && b != b'0'),
+                range::take_while(|b: u8| b.is_ascii_digit()),
+            )
+                .map(|_| ())),
+            optional((byte(b'.'), digits())),
             optional((
                 (one_of("eE".bytes()), optional(one_of("+-".bytes()))),
                 digits(),
             )),
-        ))))
-        .map(|s: &'a [u8]| std::str::from_utf8(s).unwrap().parse().unwrap())
+        )))
+        .and_then(|s: &'a [u8]| {
+            let n: f64 = std::str::from_utf8(s).unwrap().parse().unwrap();
+            if n.is_finite() {
+                Ok(n)
+            } else {
+                Err(StreamErrorFor::<I>::message_static_message(
+                    "number out of range",
+                ))
+            }
+        }))
         .expected("number"),
     )
 }
@@ -123,20 +193,67 @@ where
     I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    range::take_while1(|b| b >= b'0' && b <= b'9')
+    range::take_while1(|b: u8| b.is_ascii_digit())
+}
+
+/// JSON's four whitespace bytes.
+///
+/// rust-sitter declares whitespace once, as an `extra`, and the generated
+/// parser lets it appear between any two tokens. combine has no such notion:
+/// every token parser has to skip its own trailing whitespace, which is what
+/// [`lex`] is for, and the leading whitespace of the document is skipped by
+/// hand in [`json_value`]. `combine::parser::byte::spaces` would have been the
+/// obvious choice, but it also skips form feeds and vertical tabs.
+fn is_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
 }
 
 fn lex<'a, P>(p: P) -> impl Parser<Input = P::Input, Output = P::Output>
 where
     P: Parser,
-    P::Input: RangeStream<Item = u8, Range = &'a [u8]>,
+    P::Input: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
     <P::Input as StreamOnce>::Error: ParseError<
         <P::Input as StreamOnce>::Item,
         <P::Input as StreamOnce>::Range,
         <P::Input as StreamOnce>::Position,
     >,
 {
-    no_partial(p.skip(range::take_while(|b| {
-        b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
-    })))
+    no_partial(p.skip(range::take_while(is_whitespace)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    /// Unoptimized, each level of nesting takes enough stack that
+    /// [`MAX_DEPTH`] levels don't fit in the 2 MiB a test thread gets by
+    /// default, though they do in a main thread.
+    fn with_main_thread_stack(f: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(f)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn json_test_suite() {
+        with_main_thread_stack(|| {
+            corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+        });
+    }
+
+    #[test]
+    fn max_depth() {
+        with_main_thread_stack(|| {
+            let err = corpus::check_max_depth(parse);
+            assert_eq!(err.position, MAX_DEPTH);
+        });
+    }
 }
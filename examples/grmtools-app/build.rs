@@ -1,11 +1,15 @@
 use cfgrammar::yacc::YaccKind;
 use lrlex::CTLexerBuilder;
+use lrpar::RecoveryKind;
 use std::{env, path::PathBuf};
 
 fn main() {
     CTLexerBuilder::new()
         .lrpar_config(|ctp| {
+            // Stop at the first syntax error, as the other examples do, rather
+            // than searching for repairs to report more of them.
             ctp.yacckind(YaccKind::Grmtools)
+                .recoverer(RecoveryKind::None)
                 .grammar_path("json.y")
                 .output_path(
                     [env::var("OUT_DIR").unwrap().as_str(), "json.y.rs"]
@@ -1,50 +1,41 @@
-%start Object
+%start Value
 %expect-unused Unmatched "UNMATCHED"
 
 %%
 
-Object -> Result<Value, Box<dyn Error>>:
-    "{" ObjectMembersOpt "}" { Ok(Value::Object(HashMap::from_iter($2?))) }
+Value -> Result<JsonValue, Box<dyn Error>>:
+    "{" MembersOpt "}" { Ok(JsonValue::Object($2?)) }
+  | "[" ElementsOpt "]" { Ok(JsonValue::Array($2?)) }
+  | "FALSE" { Ok(JsonValue::Bool(false)) }
+  | "NUMBER" { number($lexer.span_str($1?.span())) }
+  | "NULL" { Ok(JsonValue::Null) }
+  | "STRING" { string($lexer.span_str($1?.span())).map(JsonValue::String) }
+  | "TRUE" { Ok(JsonValue::Bool(true)) }
   ;
 
-ObjectMembersOpt -> Result<Vec<(String, Value)>, Box<dyn Error>>:
-    ObjectMembers { $1 }
+// Members in document order, duplicates included.
+MembersOpt -> Result<Vec<(String, JsonValue)>, Box<dyn Error>>:
+    Members { $1 }
   | { Ok(Vec::new()) }
   ;
 
-ObjectMembers -> Result<Vec<(String, Value)>, Box<dyn Error>>:
-    ObjectMembers "," ObjectMember { flatten($1, $3) }
-  | ObjectMember { Ok(vec![$1?]) }
+Members -> Result<Vec<(String, JsonValue)>, Box<dyn Error>>:
+    Members "," Member { flatten($1, $3) }
+  | Member { Ok(vec![$1?]) }
   ;
 
-ObjectMember -> Result<(String, Value), Box<dyn Error>>:
-    "STRING" ":" Member {
-      let s = $lexer.span_str($1.unwrap().span());
-      Ok((s[1..s.len() - 1].to_owned(), $3?))
-    }
+Member -> Result<(String, JsonValue), Box<dyn Error>>:
+    "STRING" ":" Value { Ok((string($lexer.span_str($1?.span()))?, $3?)) }
   ;
 
-Member -> Result<Value, Box<dyn Error>>:
-    "[" ArrayMembersOpt "]" { Ok(Value::Array($2?)) }
-  | "FALSE" { Ok(Value::Boolean(false)) }
-  | "FLOAT" { Ok(Value::Num($lexer.span_str($1?.span()).parse::<f64>().unwrap())) }
-  | "NULL" { Ok(Value::Null) }
-  | Object { $1 }
-  | "STRING" {
-      let s = $lexer.span_str($1.unwrap().span());
-      Ok(Value::Str(s[1..s.len() - 1].to_owned()))
-    }
-  | "TRUE" { Ok(Value::Boolean(true)) }
-  ;
-
-ArrayMembersOpt -> Result<Vec<Value>, Box<dyn Error>>:
-    ArrayMembers { $1 }
+ElementsOpt -> Result<Vec<JsonValue>, Box<dyn Error>>:
+    Elements { $1 }
   | { Ok(Vec::new()) }
   ;
 
-ArrayMembers -> Result<Vec<Value>, Box<dyn Error>>:
-    ArrayMembers "," Member { flatten($1, $3) }
-  | Member { Ok(vec![$1?])}
+Elements -> Result<Vec<JsonValue>, Box<dyn Error>>:
+    Elements "," Value { flatten($1, $3) }
+  | Value { Ok(vec![$1?]) }
   ;
 
 Unmatched -> ():
@@ -53,8 +44,23 @@ Unmatched -> ():
 
 %%
 
-use crate::json_val::Value;
-use std::{collections::HashMap, error::Error};
+use crate::{unescape, JsonValue};
+use std::error::Error;
+
+// The lexer's patterns are the rust-sitter example's, so only overflow is left
+// to check.
+fn number(s: &str) -> Result<JsonValue, Box<dyn Error>> {
+    let n = s.parse::<f64>().unwrap();
+    if n.is_finite() {
+        Ok(JsonValue::Number(n))
+    } else {
+        Err("number out of range".into())
+    }
+}
+
+fn string(s: &str) -> Result<String, Box<dyn Error>> {
+    Ok(unescape(&s[1..s.len() - 1])?)
+}
 
 fn flatten<T>(lhs: Result<Vec<T>, Box<dyn Error>>, rhs: Result<T, Box<dyn Error>>)
   -> Result<Vec<T>, Box<dyn Error>>
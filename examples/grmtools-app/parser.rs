@@ -0,0 +1,100 @@
+//! JSON as a Yacc grammar, `json.y`, over an lrlex lexer, `json.l`, both
+//! turned into Rust by grmtools in the build script.
+//!
+//! Like the lalrpop example this is an LR parser that never recurses, so there
+//! is no depth limit, but its tables come from a grammar in the same format as
+//! Yacc's, with the action code in the grammar file too.
+
+use std::sync::OnceLock;
+
+use lrlex::{lrlex_mod, DefaultLexerTypes, LRNonStreamingLexerDef};
+use lrpar::lrpar_mod;
+
+pub use ast::JsonValue;
+
+lrlex_mod!("json.l");
+lrpar_mod!("json.y");
+
+/// Parses a complete JSON document, failing with each error described as
+/// lrpar prints it.
+pub fn parse(input: &str) -> Result<JsonValue, Vec<String>> {
+    // Building the lexer compiles a regex for every rule in `json.l`, which
+    // would take longer than lexing most documents, so it is only done once.
+    static LEXERDEF: OnceLock<LRNonStreamingLexerDef<DefaultLexerTypes<u32>>> = OnceLock::new();
+    let lexer = LEXERDEF.get_or_init(json_l::lexerdef).lexer(input);
+    let (value, errors) = json_y::parse(&lexer);
+    if !errors.is_empty() {
+        return Err(errors
+            .iter()
+            .map(|error| error.pp(&lexer, &json_y::token_epp))
+            .collect());
+    }
+    match value {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(error)) => Err(vec![error.to_string()]),
+        None => unreachable!("lrpar only gives no value along with errors"),
+    }
+}
+
+/// Decodes the escapes in the body of a string token, pairing up the
+/// surrogates from `\u` escapes as rust-sitter's `unescape` does.
+///
+/// The token's pattern has already checked that every escape is well-formed.
+pub(crate) fn unescape(body: &str) -> Result<String, &'static str> {
+    if !body.contains('\\') {
+        return Ok(body.to_owned());
+    }
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match chars.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let rest = chars.as_str();
+                chars = rest[4..].chars();
+                u16::from_str_radix(&rest[..4], 16).unwrap()
+            }
+            c => c as u16,
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| "unpaired surrogate in string")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn errors() {
+        let errors = parse("[1, tru]").unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].contains("line 1 column 5"), "{errors:?}");
+        assert_eq!(
+            parse(r#"["\uD800"]"#),
+            Err(vec!["unpaired surrogate in string".to_owned()])
+        );
+        assert_eq!(
+            parse("[1e400]"),
+            Err(vec!["number out of range".to_owned()])
+        );
+    }
+}
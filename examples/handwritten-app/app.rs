@@ -0,0 +1,23 @@
+use std::{env, fs};
+
+fn main() {
+    let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
+        .expect("Failed to read file");
+
+    match handwritten_app::parse(src.as_str()) {
+        Ok(json) => {
+            #[cfg(debug_assertions)]
+            {
+                println!("{:#?}", json);
+            }
+            #[cfg(not(debug_assertions))]
+            {
+                std::hint::black_box(json);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! A recursive-descent JSON parser with no dependencies beyond the shared
+//! `ast`, as a baseline for the libraries.
+//!
+//! It works on the input's bytes and only allocates for the values it returns:
+//! a string without escapes is copied out in one go, and only strings with
+//! escapes are built up piece by piece.
+//!
+//! Without the default `std` feature it is `no_std`, needing only `alloc`.
+//! rust-sitter's runtime needs `std`, so this is the parser to reach for where
+//! that isn't available.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::fmt;
+
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// Why and where parsing stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset of the offending input, or the input's length if it ended
+    /// too soon.
+    pub offset: usize,
+    /// A lowercase description such as `"expected ':'"`, suitable as a
+    /// diagnostic label.
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, ParseError> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let json = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != input.len() {
+        return Err(parser.error("expected end of input"));
+    }
+    Ok(json)
+}
+
+/// How many bytes at the start of `bytes` are whitespace, counting 16 at a time
+/// and leaving a tail shorter than that for the scalar loop.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn whitespace_chunks(bytes: &[u8]) -> usize {
+    use core::arch::x86_64::{
+        _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_or_si128, _mm_set1_epi8,
+    };
+
+    let mut len = 0;
+    for chunk in bytes.chunks_exact(16) {
+        // SAFETY: SSE2 is part of the x86_64 baseline, and the unaligned load
+        // reads exactly the 16 bytes of `chunk`.
+        let mask = unsafe {
+            let x = _mm_loadu_si128(chunk.as_ptr().cast());
+            let space = _mm_cmpeq_epi8(x, _mm_set1_epi8(b' ' as i8));
+            let tab = _mm_cmpeq_epi8(x, _mm_set1_epi8(b'\t' as i8));
+            let cr = _mm_cmpeq_epi8(x, _mm_set1_epi8(b'\r' as i8));
+            let lf = _mm_cmpeq_epi8(x, _mm_set1_epi8(b'\n' as i8));
+            _mm_movemask_epi8(_mm_or_si128(_mm_or_si128(space, tab), _mm_or_si128(cr, lf)))
+        };
+        // One bit per byte, set for whitespace, with the first byte lowest.
+        let whitespace = mask as u16;
+        if whitespace != u16::MAX {
+            return len + whitespace.trailing_ones() as usize;
+        }
+        len += 16;
+    }
+    len
+}
+
+struct Parser<'i> {
+    input: &'i str,
+    /// Always on a `char` boundary, since it only ever stops on ASCII bytes.
+    pos: usize,
+    /// The number of arrays and objects enclosing `pos`.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+            self.pos += 1;
+            // Most calls find no whitespace at all, so chunks only start once
+            // there is a run.
+            #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+            {
+                self.pos += whitespace_chunks(&self.input.as_bytes()[self.pos..]);
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, ParseError> {
+        match self.peek() {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b'-' | b'0'..=b'9') => self.number().map(JsonValue::Number),
+            Some(b't') => self.keyword("true", JsonValue::Bool(true)),
+            Some(b'f') => self.keyword("false", JsonValue::Bool(false)),
+            Some(b'n') => self.keyword("null", JsonValue::Null),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, ParseError> {
+        if self.input[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    /// Runs `f` one level deeper, failing without recursing past [`MAX_DEPTH`].
+    fn nested(
+        &mut self,
+        f: fn(&mut Self) -> Result<JsonValue, ParseError>,
+    ) -> Result<JsonValue, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<JsonValue, ParseError> {
+        self.pos += 1;
+        self.skip_whitespace();
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                }
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, ParseError> {
+        self.pos += 1;
+        self.skip_whitespace();
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':', "expected ':'")?;
+            self.skip_whitespace();
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                }
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    /// Parses a string, starting on its opening quote.
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let run = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            let run = &self.input[run..self.pos];
+            match self.peek() {
+                Some(b'"') if out.is_empty() => {
+                    self.pos += 1;
+                    return Ok(run.to_owned());
+                }
+                Some(b'"') => {
+                    out.push_str(run);
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    out.push_str(run);
+                    out.push(self.escape()?);
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Decodes one escape sequence, starting on its backslash, pairing up the
+    /// surrogates from `\u` escapes as rust-sitter's `unescape` does.
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0C',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let unit = self.hex4()?;
+                let unpaired = ParseError {
+                    offset: start,
+                    message: "unpaired surrogate",
+                };
+                return match unit {
+                    0xD800..=0xDBFF => {
+                        if !self.input[self.pos..].starts_with("\\u") {
+                            return Err(unpaired);
+                        }
+                        self.pos += 2;
+                        let low = self.hex4()?;
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(unpaired);
+                        }
+                        let c = 0x10000
+                            + ((u32::from(unit) - 0xD800) << 10)
+                            + (u32::from(low) - 0xDC00);
+                        Ok(char::from_u32(c).unwrap())
+                    }
+                    0xDC00..=0xDFFF => Err(unpaired),
+                    _ => Ok(char::from_u32(u32::from(unit)).unwrap()),
+                };
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u16, ParseError> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        Ok(u16::from_str_radix(digits, 16).unwrap())
+    }
+
+    /// Scans `-?(0|[1-9]\d*)(\.\d+)?([eE][+-]?\d+)?`, then leaves the
+    /// conversion to the standard library.
+    fn number(&mut self) -> Result<f64, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits1()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits1()?;
+        }
+        let n: f64 = self.input[start..self.pos].parse().unwrap();
+        if !n.is_finite() {
+            return Err(ParseError {
+                offset: start,
+                message: "number out of range",
+            });
+        }
+        Ok(n)
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn digits1(&mut self) -> Result<(), ParseError> {
+        if !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(self.error("expected a digit"));
+        }
+        self.digits();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn error_offsets() {
+        let error = |input| parse(input).unwrap_err();
+        assert_eq!(
+            error(r#"{"a" 1}"#),
+            ParseError {
+                offset: 5,
+                message: "expected ':'"
+            }
+        );
+        assert_eq!(
+            error(r#"["é", "\uD800"]"#),
+            ParseError {
+                offset: 8,
+                message: "unpaired surrogate"
+            }
+        );
+        assert_eq!(
+            error("[1e400]"),
+            ParseError {
+                offset: 1,
+                message: "number out of range"
+            }
+        );
+        assert_eq!(
+            error("[1,"),
+            ParseError {
+                offset: 3,
+                message: "expected a value"
+            }
+        );
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn whitespace_chunks_stop_at_the_first_other_byte() {
+        // Bytes next to the whitespace ones, and one that only differs from a
+        // space in its high bit.
+        for stop in [b'x', b'\0', 0x0B, 0x0C, 0x1F, 0x21, 0xA0] {
+            for run in 0..50 {
+                let mut bytes: Vec<u8> = b" \t\r\n".iter().copied().cycle().take(run).collect();
+                bytes.push(stop);
+                bytes.extend_from_slice(b"          ");
+                let skipped = whitespace_chunks(&bytes);
+                let whole_chunks = bytes.len() / 16 * 16;
+                assert_eq!(skipped, run.min(whole_chunks), "{stop:#x} after {run}");
+            }
+        }
+        assert_eq!(whitespace_chunks(&[b' '; 40]), 32);
+        assert_eq!(
+            parse("\n\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t\t[ 1 ,\n                    2 ]"),
+            parse("[1,2]")
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        assert_eq!(
+            corpus::check_max_depth(parse),
+            ParseError {
+                offset: MAX_DEPTH,
+                message: "nesting too deep"
+            }
+        );
+    }
+}
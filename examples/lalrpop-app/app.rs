@@ -1,18 +1,10 @@
-extern crate lalrpop_util;
-
-use std::env;
-use std::fs;
-
-#[rustfmt::skip]
-#[allow(clippy::all)]
-mod json;
-mod json_val;
+use std::{env, fs};
 
 fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match json::ValueParser::new().parse(&src) {
+    match lalrpop_app::parse(&src) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -27,5 +19,5 @@ fn main() {
             eprintln!("{}", err);
             std::process::exit(1);
         }
-    }
+    };
 }
@@ -0,0 +1,91 @@
+//! JSON as a `lalrpop` LR(1) grammar, `json.lalrpop`, over lalrpop's built-in
+//! lexer.
+//!
+//! The parse tables are generated by the build script, which makes this the
+//! slowest example to compile, but parsing itself never recurses, so unlike
+//! the recursive descent examples there is no depth limit.
+
+use lalrpop_util::lalrpop_mod;
+
+lalrpop_mod!(
+    #[rustfmt::skip]
+    #[allow(clippy::all)]
+    json
+);
+
+pub use ast::JsonValue;
+pub use json::ValueParser;
+
+pub type Error<'input> =
+    lalrpop_util::ParseError<usize, lalrpop_util::lexer::Token<'input>, &'static str>;
+
+/// Parses a complete JSON document.
+///
+/// Building a [`ValueParser`] is cheap, but callers parsing many documents can
+/// keep one around and call it directly.
+pub fn parse(input: &str) -> Result<JsonValue, Error<'_>> {
+    ValueParser::new().parse(input)
+}
+
+/// Decodes the escapes in the body of a string token, pairing up the
+/// surrogates from `\u` escapes as rust-sitter's `unescape` does.
+///
+/// The token's pattern has already checked that every escape is well-formed.
+pub(crate) fn unescape(body: &str) -> Result<String, &'static str> {
+    if !body.contains('\\') {
+        return Ok(body.to_owned());
+    }
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match chars.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let rest = chars.as_str();
+                chars = rest[4..].chars();
+                u16::from_str_radix(&rest[..4], 16).unwrap()
+            }
+            c => c as u16,
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| "unpaired surrogate in string")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // Deeper than any recursive descent example allows.
+        let depth = 10_000;
+        let nested = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let mut value = parse(&nested).unwrap();
+        // Take the tree apart by hand, as dropping it would recurse.
+        let mut levels = 0;
+        while let JsonValue::Array(mut items) = value {
+            levels += 1;
+            value = items.pop().unwrap_or(JsonValue::Null);
+        }
+        assert_eq!(levels, depth);
+    }
+}
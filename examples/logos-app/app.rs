@@ -1,15 +1,10 @@
-mod parser;
-
 use std::{env, fs};
 
-use logos::Logos as _;
-
 fn main() {
     let filename = env::args().nth(1).expect("Expected file argument");
     let src = fs::read_to_string(&filename).expect("Failed to read file");
 
-    let mut lexer = parser::Token::lexer(src.as_str());
-    match parser::parse_value(&mut lexer) {
+    match logos_app::parse(src.as_str()) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -22,6 +17,7 @@ fn main() {
         }
         Err((msg, span)) => {
             eprintln!("{filename}:{span:?}: {msg}");
+            std::process::exit(1);
         }
     }
 }
@@ -1,36 +1,45 @@
-use std::collections::HashMap;
+//! JSON split into a `logos` lexer, [`Token`], and a hand-written parser over
+//! the token stream.
 
 use logos::{Lexer, Logos, Span};
 
-type Error = (String, Span);
+pub use ast::{JsonValue, MAX_DEPTH};
+
+pub type Error = (String, Span);
 
 type Result<T> = std::result::Result<T, Error>;
 
-/// Represent any valid JSON value.
-#[allow(dead_code)]
-#[derive(Debug)]
-pub enum Value {
-    /// null.
-    Null,
-    /// true or false.
-    Bool(bool),
-    /// Any floating point number.
-    Number(f64),
-    /// Any quoted string.
-    String(String),
-    /// An array of values
-    Array(Vec<Value>),
-    /// An dictionary mapping keys and values.
-    Object(HashMap<String, Value>),
+/// Why a slice of the input did not lex as a [`Token`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LexError {
+    /// Nothing matches here.
+    #[default]
+    UnexpectedCharacter,
+    /// A string whose `\u` escapes don't pair up into characters.
+    UnpairedSurrogate,
+    /// A number too large for an `f64`.
+    NumberOutOfRange,
+}
+
+impl LexError {
+    fn message(&self) -> &'static str {
+        match self {
+            LexError::UnexpectedCharacter => "unexpected character",
+            LexError::UnpairedSurrogate => "unpaired surrogate in string",
+            LexError::NumberOutOfRange => "number out of range",
+        }
+    }
 }
 
 /// All meaningful JSON tokens.
 ///
-/// > NOTE: regexes for [`Token::Number`] and [`Token::String`] may not
-/// > catch all possible values, especially for strings. If you find
-/// > errors, please report them so that we can improve the regex.
-#[derive(Debug, Logos)]
-#[logos(skip r"[ \t\r\n\f]+")]
+/// The patterns are the rust-sitter example's leaf patterns, so strings and
+/// numbers are already known to be well-formed by the time their callbacks
+/// run; the callbacks only reject what a regex can't, surrogates that don't
+/// pair up and numbers that overflow.
+#[derive(Debug, Logos, PartialEq)]
+#[logos(error = LexError)]
+#[logos(skip r"[ \t\r\n]+")]
 pub enum Token {
     #[token("false", |_| false)]
     #[token("true", |_| true)]
@@ -57,71 +66,122 @@ pub enum Token {
     #[token("null")]
     Null,
 
-    #[regex(r"-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?", |lex| lex.slice().parse::<f64>().unwrap())]
+    #[regex(r"-?(?:0|[1-9][0-9]*)(?:\.[0-9]+)?(?:[eE][+-]?[0-9]+)?", number)]
     Number(f64),
 
-    #[regex(r#""([^"\\]|\\["\\bnfrt]|u[a-fA-F0-9]{4})*""#, |lex| lex.slice().to_owned())]
+    #[regex(r#""(?:[^"\\\x00-\x1F]|\\["\\/bfnrt]|\\u[a-fA-F0-9]{4})*""#, string)]
     String(String),
 }
 
+fn number(lex: &mut Lexer<'_, Token>) -> std::result::Result<f64, LexError> {
+    let n: f64 = lex.slice().parse().unwrap();
+    if n.is_finite() {
+        Ok(n)
+    } else {
+        Err(LexError::NumberOutOfRange)
+    }
+}
+
+/// Decodes the escapes in a string token, pairing up the surrogates from `\u`
+/// escapes as rust-sitter's `unescape` does.
+fn string(lex: &mut Lexer<'_, Token>) -> std::result::Result<String, LexError> {
+    let slice = lex.slice();
+    let body = &slice[1..slice.len() - 1];
+    if !body.contains('\\') {
+        return Ok(body.to_owned());
+    }
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match chars.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let rest = chars.as_str();
+                chars = rest[4..].chars();
+                u16::from_str_radix(&rest[..4], 16).unwrap()
+            }
+            c => c as u16,
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| LexError::UnpairedSurrogate)
+}
+
+/// Parse a complete JSON document.
+pub fn parse(src: &str) -> Result<JsonValue> {
+    let mut lexer = Token::lexer(src);
+    let value = parse_value(&mut lexer, 0)?;
+    match lexer.next() {
+        None => Ok(value),
+        Some(_) => Err(("unexpected token after the value".to_owned(), lexer.span())),
+    }
+}
+
+/// Turns a token that failed to lex into an error, or leaves it be.
+fn lexed(token: std::result::Result<Token, LexError>, lexer: &Lexer<'_, Token>) -> Result<Token> {
+    token.map_err(|err| (err.message().to_owned(), lexer.span()))
+}
+
 /// Parse a token stream into a JSON value.
-pub fn parse_value<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
+///
+/// `depth` counts the arrays and objects enclosing the value.
+pub fn parse_value<'source>(lexer: &mut Lexer<'source, Token>, depth: usize) -> Result<JsonValue> {
     if let Some(token) = lexer.next() {
-        match token {
-            Ok(Token::Bool(b)) => Ok(Value::Bool(b)),
-            Ok(Token::BraceOpen) => parse_object(lexer),
-            Ok(Token::BracketOpen) => parse_array(lexer),
-            Ok(Token::Null) => Ok(Value::Null),
-            Ok(Token::Number(n)) => Ok(Value::Number(n)),
-            Ok(Token::String(s)) => Ok(Value::String(s)),
-            _ => Err((
-                "unexpected token here (context: value)".to_owned(),
-                lexer.span(),
-            )),
-        }
+        parse_token(lexer, lexed(token, lexer)?, depth, "value")
     } else {
         Err(("empty values are not allowed".to_owned(), lexer.span()))
     }
 }
 
+/// Finish parsing the value that starts with `token`.
+fn parse_token<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    token: Token,
+    depth: usize,
+    context: &str,
+) -> Result<JsonValue> {
+    match token {
+        Token::Bool(b) => Ok(JsonValue::Bool(b)),
+        Token::BraceOpen | Token::BracketOpen if depth == MAX_DEPTH => Err((
+            format!("more than {MAX_DEPTH} levels of arrays and objects"),
+            lexer.span(),
+        )),
+        Token::BraceOpen => parse_object(lexer, depth + 1),
+        Token::BracketOpen => parse_array(lexer, depth + 1),
+        Token::Null => Ok(JsonValue::Null),
+        Token::Number(n) => Ok(JsonValue::Number(n)),
+        Token::String(s) => Ok(JsonValue::String(s)),
+        _ => Err((
+            format!("unexpected token here (context: {context})"),
+            lexer.span(),
+        )),
+    }
+}
+
 /// Parse a token stream into an array and return when
 /// a valid terminator is found.
 ///
 /// > NOTE: we assume '[' was consumed.
-fn parse_array<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
+fn parse_array<'source>(lexer: &mut Lexer<'source, Token>, depth: usize) -> Result<JsonValue> {
     let mut array = Vec::new();
     let span = lexer.span();
     let mut awaits_comma = false;
     let mut awaits_value = false;
 
     while let Some(token) = lexer.next() {
-        match token {
-            Ok(Token::Bool(b)) if !awaits_comma => {
-                array.push(Value::Bool(b));
-                awaits_value = false;
-            }
-            Ok(Token::BraceOpen) if !awaits_comma => {
-                let object = parse_object(lexer)?;
-                array.push(object);
-                awaits_value = false;
-            }
-            Ok(Token::BracketOpen) if !awaits_comma => {
-                let sub_array = parse_array(lexer)?;
-                array.push(sub_array);
-                awaits_value = false;
-            }
-            Ok(Token::BracketClose) if !awaits_value => return Ok(Value::Array(array)),
-            Ok(Token::Comma) if awaits_comma => awaits_value = true,
-            Ok(Token::Null) if !awaits_comma => {
-                array.push(Value::Null);
-                awaits_value = false
-            }
-            Ok(Token::Number(n)) if !awaits_comma => {
-                array.push(Value::Number(n));
-                awaits_value = false;
-            }
-            Ok(Token::String(s)) if !awaits_comma => {
-                array.push(Value::String(s));
+        match lexed(token, lexer)? {
+            Token::BracketClose if !awaits_value => return Ok(JsonValue::Array(array)),
+            Token::Comma if awaits_comma => awaits_value = true,
+            token if !awaits_comma => {
+                array.push(parse_token(lexer, token, depth, "array")?);
                 awaits_value = false;
             }
             _ => {
@@ -140,17 +200,17 @@ fn parse_array<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
 /// a valid terminator is found.
 ///
 /// > NOTE: we assume '{' was consumed.
-fn parse_object<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
-    let mut map = HashMap::new();
+fn parse_object<'source>(lexer: &mut Lexer<'source, Token>, depth: usize) -> Result<JsonValue> {
+    let mut members = Vec::new();
     let span = lexer.span();
     let mut awaits_comma = false;
     let mut awaits_key = false;
 
     while let Some(token) = lexer.next() {
-        match token {
-            Ok(Token::BraceClose) if !awaits_key => return Ok(Value::Object(map)),
-            Ok(Token::Comma) if awaits_comma => awaits_key = true,
-            Ok(Token::String(key)) if !awaits_comma => {
+        match lexed(token, lexer)? {
+            Token::BraceClose if !awaits_key => return Ok(JsonValue::Object(members)),
+            Token::Comma if awaits_comma => awaits_key = true,
+            Token::String(key) if !awaits_comma => {
                 match lexer.next() {
                     Some(Ok(Token::Colon)) => (),
                     _ => {
@@ -160,8 +220,8 @@ fn parse_object<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
                         ))
                     }
                 }
-                let value = parse_value(lexer)?;
-                map.insert(key, value);
+                let value = parse_value(lexer, depth)?;
+                members.push((key, value));
                 awaits_key = false;
             }
             _ => {
@@ -175,3 +235,85 @@ fn parse_object<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Value> {
     }
     Err(("unmatched opening brace defined here".to_owned(), span))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<std::result::Result<Token, LexError>> {
+        Token::lexer(src).collect()
+    }
+
+    #[test]
+    fn punctuation_and_keywords() {
+        assert_eq!(
+            tokens("{ } [ ] : , true false null"),
+            vec![
+                Ok(Token::BraceOpen),
+                Ok(Token::BraceClose),
+                Ok(Token::BracketOpen),
+                Ok(Token::BracketClose),
+                Ok(Token::Colon),
+                Ok(Token::Comma),
+                Ok(Token::Bool(true)),
+                Ok(Token::Bool(false)),
+                Ok(Token::Null),
+            ]
+        );
+    }
+
+    #[test]
+    fn number_tokens() {
+        assert_eq!(
+            tokens("0 -1 2.5 1e3 -0.5E-2"),
+            vec![
+                Ok(Token::Number(0.0)),
+                Ok(Token::Number(-1.0)),
+                Ok(Token::Number(2.5)),
+                Ok(Token::Number(1000.0)),
+                Ok(Token::Number(-0.005)),
+            ]
+        );
+        assert_eq!(tokens("1e400"), vec![Err(LexError::NumberOutOfRange)]);
+        // The lexer splits a leading zero off; it is the parser that rejects
+        // two numbers in a row.
+        assert_eq!(
+            tokens("01"),
+            vec![Ok(Token::Number(0.0)), Ok(Token::Number(1.0))]
+        );
+    }
+
+    #[test]
+    fn string_tokens() {
+        assert_eq!(
+            tokens(r#""plain" "a\"b\\c\/d\ne" "😐""#),
+            vec![
+                Ok(Token::String("plain".to_owned())),
+                Ok(Token::String("a\"b\\c/d\ne".to_owned())),
+                Ok(Token::String("😐".to_owned())),
+            ]
+        );
+        assert_eq!(
+            tokens(r#""\uD800""#),
+            vec![Err(LexError::UnpairedSurrogate)]
+        );
+        assert!(tokens("\"a\tb\"").contains(&Err(LexError::UnexpectedCharacter)));
+        assert!(tokens(r#""\x""#).contains(&Err(LexError::UnexpectedCharacter)));
+    }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn max_depth() {
+        let (_, span) = corpus::check_max_depth(parse);
+        assert_eq!(span, MAX_DEPTH..MAX_DEPTH + 1);
+    }
+}
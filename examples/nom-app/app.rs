@@ -1,16 +1,10 @@
-mod parser;
-
 use std::{env, fs};
 
-use nom::error::convert_error;
-use nom::error::VerboseError;
-use nom::Err;
-
 fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match parser::root::<VerboseError<&str>>(src.as_str()) {
+    match nom_app::parse(src.as_str()) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -21,11 +15,6 @@ fn main() {
                 std::hint::black_box(json);
             }
         }
-        Err(Err::Error(err)) | Err(Err::Failure(err)) => {
-            let err = convert_error(src.as_str(), err);
-            eprintln!("{}", err);
-            std::process::exit(1);
-        }
         Err(err) => {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -1,77 +1,94 @@
 use nom::{
     branch::alt,
-    bytes::complete::{escaped, tag, take_while},
-    character::complete::{alphanumeric1 as alphanumeric, char, one_of},
-    combinator::{cut, map, opt, value},
-    error::{context, ContextError, ParseError},
-    multi::separated_list0,
-    number::complete::double,
-    sequence::{delimited, preceded, separated_pair, terminated},
+    bytes::complete::{tag, take_while, take_while1, take_while_m_n},
+    character::complete::{char, digit0, digit1, one_of},
+    combinator::{all_consuming, cut, map, opt, recognize, value},
+    error::{context, convert_error, ContextError, ErrorKind, ParseError, VerboseError},
+    multi::{fold_many0, separated_list0},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
-use std::collections::HashMap;
-use std::str;
+use std::{fmt, str};
 
-#[derive(Debug, PartialEq)]
-pub enum JsonValue {
-    Null,
-    Str(String),
-    Boolean(bool),
-    Num(f64),
-    Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// A parse failure, rendered with the offending line and the parsers that
+/// were active at the time.
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, Error> {
+    match root::<VerboseError<&str>>(input) {
+        Ok((_, json)) => Ok(json),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            Err(Error(convert_error(input, err)))
+        }
+        Err(err) => Err(Error(err.to_string())),
+    }
 }
 
-/// the root element of a JSON parser is either an object or an array
+/// the root element of a JSON document is any value, surrounded by optional
+/// whitespace and nothing else
 pub fn root<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, JsonValue, E> {
-    delimited(
-        sp,
-        alt((
-            map(hash, JsonValue::Object),
-            map(array, JsonValue::Array),
-            map(null, |_| JsonValue::Null),
-        )),
-        opt(sp),
-    )(i)
+    all_consuming(delimited(sp, |i| json_value(i, 0), sp))(i)
 }
 
-/// here, we apply the space parser before trying to parse a value
+/// `depth` counts the arrays and objects enclosing this value
 fn json_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
+    depth: usize,
 ) -> IResult<&'a str, JsonValue, E> {
-    preceded(
-        sp,
-        alt((
-            map(hash, JsonValue::Object),
-            map(array, JsonValue::Array),
-            map(string, |s| JsonValue::Str(String::from(s))),
-            map(double, JsonValue::Num),
-            map(boolean, JsonValue::Boolean),
-            map(null, |_| JsonValue::Null),
-        )),
-    )(i)
+    alt((
+        map(|i| hash(i, depth + 1), JsonValue::Object),
+        map(|i| array(i, depth + 1), JsonValue::Array),
+        map(string, JsonValue::String),
+        map(number, JsonValue::Number),
+        map(boolean, JsonValue::Bool),
+        map(null, |_| JsonValue::Null),
+    ))(i)
+}
+
+/// Fails outright once `depth` passes [`MAX_DEPTH`], before recursing any further.
+fn check_depth<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+    depth: usize,
+) -> IResult<&'a str, (), E> {
+    if depth > MAX_DEPTH {
+        let err = E::from_error_kind(i, ErrorKind::TooLarge);
+        return Err(nom::Err::Failure(E::add_context(
+            i,
+            "nesting too deep",
+            err,
+        )));
+    }
+    Ok((i, ()))
 }
 
 fn hash<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, HashMap<String, JsonValue>, E> {
+    depth: usize,
+) -> IResult<&'a str, Vec<(String, JsonValue)>, E> {
     context(
         "map",
         preceded(
             char('{'),
-            cut(terminated(
-                map(
-                    separated_list0(preceded(sp, char(',')), key_value),
-                    |tuple_vec| {
-                        tuple_vec
-                            .into_iter()
-                            .map(|(k, v)| (String::from(k), v))
-                            .collect()
-                    },
+            cut(preceded(
+                |i| check_depth(i, depth),
+                terminated(
+                    separated_list0(char(','), |i| key_value(i, depth)),
+                    preceded(sp, char('}')),
                 ),
-                preceded(sp, char('}')),
             )),
         ),
     )(i)
@@ -79,11 +96,12 @@ fn hash<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 
 fn key_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, (&'a str, JsonValue), E> {
+    depth: usize,
+) -> IResult<&'a str, (String, JsonValue), E> {
     separated_pair(
-        preceded(sp, string),
-        cut(preceded(sp, char(':'))),
-        json_value,
+        delimited(sp, string, sp),
+        cut(char(':')),
+        cut(delimited(sp, |i| json_value(i, depth), sp)),
     )(i)
 }
 
@@ -93,14 +111,18 @@ fn key_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 /// combinator (cf `examples/iterator.rs`)
 fn array<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
+    depth: usize,
 ) -> IResult<&'a str, Vec<JsonValue>, E> {
     context(
         "array",
         preceded(
             char('['),
-            cut(terminated(
-                separated_list0(preceded(sp, char(',')), json_value),
-                preceded(sp, char(']')),
+            cut(preceded(
+                |i| check_depth(i, depth),
+                terminated(
+                    separated_list0(char(','), delimited(sp, |i| json_value(i, depth), sp)),
+                    preceded(sp, char(']')),
+                ),
             )),
         ),
     )(i)
@@ -132,23 +154,101 @@ fn null<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
     value((), tag("null"))(input)
 }
 
-/// A nom parser has the following signature:
-/// `Input -> IResult<Input, Output, Error>`, with `IResult` defined as:
-/// `type IResult<I, O, E = (I, ErrorKind)> = Result<(I, O), Err<E>>;`
-///
-/// most of the times you can ignore the error type and use the default (but this
-/// examples shows custom error types later on!)
+/// JSON's number grammar is stricter than `nom::number::complete::double`: no
+/// leading `+`, no leading zeros, and digits on both sides of the `.`.
 ///
-/// Here we use `&str` as input type, but nom parsers can be generic over
-/// the input type, and work directly with `&[u8]` or any other type that
-/// implements the required traits.
+/// Numbers too large for an `f64` are an error rather than infinity; underflow
+/// only loses precision and is accepted.
+fn number<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, f64, E> {
+    let (rest, literal) = recognize(tuple((
+        opt(char('-')),
+        alt((tag("0"), recognize(pair(one_of("123456789"), digit0)))),
+        opt(pair(char('.'), cut(digit1))),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), cut(digit1)))),
+    )))(i)?;
+    let n: f64 = literal.parse().expect("JSON numbers are valid Rust floats");
+    if !n.is_finite() {
+        let err = E::from_error_kind(i, ErrorKind::Float);
+        return Err(nom::Err::Failure(E::add_context(
+            i,
+            "number out of range",
+            err,
+        )));
+    }
+    Ok((rest, n))
+}
+
+/// Four hex digits, as found after `\u`.
+fn hex4<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, u32, E> {
+    map(
+        take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+        |hex| u32::from_str_radix(hex, 16).expect("four hex digits"),
+    )(i)
+}
+
+/// The `XXXX` of a `\uXXXX` escape, pairing up UTF-16 surrogates.
 ///
-/// Finally, we can see here that the input and output type are both `&str`
-/// with the same lifetime tag. This means that the produced value is a subslice
-/// of the input data. and there is no allocation needed. This is the main idea
-/// behind nom's performance.
-fn parse_str<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
-    escaped(alphanumeric, '\\', one_of("\"n\\"))(i)
+/// A lone surrogate has no `char` to decode to, so it is an error.
+fn unicode_escape<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, char, E> {
+    let invalid = |i| {
+        let err = E::from_error_kind(i, ErrorKind::Char);
+        nom::Err::Failure(E::add_context(i, "unpaired surrogate", err))
+    };
+    let (rest, unit) = hex4(i)?;
+    let code = match unit {
+        0xD800..=0xDBFF => {
+            let (rest, low) =
+                preceded(tag("\\u"), hex4)(rest).map_err(|_: nom::Err<E>| invalid(i))?;
+            if !(0xDC00..=0xDFFF).contains(&low) {
+                return Err(invalid(i));
+            }
+            return Ok((
+                rest,
+                char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+                    .expect("surrogate pairs decode to a char"),
+            ));
+        }
+        0xDC00..=0xDFFF => return Err(invalid(i)),
+        code => code,
+    };
+    Ok((
+        rest,
+        char::from_u32(code).expect("non-surrogates are chars"),
+    ))
+}
+
+fn escape<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, char, E> {
+    preceded(
+        char('\\'),
+        cut(alt((
+            preceded(char('u'), unicode_escape),
+            value('"', char('"')),
+            value('\\', char('\\')),
+            value('/', char('/')),
+            value('\u{08}', char('b')),
+            value('\u{0C}', char('f')),
+            value('\n', char('n')),
+            value('\r', char('r')),
+            value('\t', char('t')),
+        ))),
+    )(i)
+}
+
+/// A run of characters that stand for themselves: anything but the closing
+/// quote, the start of an escape, or a control character.
+fn literal<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    take_while1(|c: char| c != '"' && c != '\\' && c >= ' ')(i)
+}
+
+enum Fragment<'a> {
+    Literal(&'a str),
+    Escaped(char),
 }
 
 /// parser combinators are constructed from the bottom up:
@@ -162,22 +262,57 @@ fn sp<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
     take_while(move |c| chars.contains(c))(i)
 }
 
-/// this parser combines the previous `parse_str` parser, that recognizes the
-/// interior of a string, with a parse to recognize the double quote character,
-/// before the string (using `preceded`) and after the string (using `terminated`).
+/// this parser folds string fragments, literal runs and escapes, into one
+/// `String`, between the opening and closing double quotes.
 ///
 /// `context` and `cut` are related to error management:
 /// - `cut` transforms an `Err::Error(e)` in `Err::Failure(e)`, signaling to
-/// combinators like  `alt` that they should not try other parsers. We were in the
-/// right branch (since we found the `"` character) but encountered an error when
-/// parsing the string
+///   combinators like  `alt` that they should not try other parsers. We were in the
+///   right branch (since we found the `"` character) but encountered an error when
+///   parsing the string
 /// - `context` lets you add a static string to provide more information in the
-/// error chain (to indicate which parser had an error)
+///   error chain (to indicate which parser had an error)
 fn string<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, &'a str, E> {
+) -> IResult<&'a str, String, E> {
+    let body = fold_many0(
+        alt((
+            map(literal, Fragment::Literal),
+            map(escape, Fragment::Escaped),
+        )),
+        String::new,
+        |mut s, fragment| {
+            match fragment {
+                Fragment::Literal(l) => s.push_str(l),
+                Fragment::Escaped(c) => s.push(c),
+            }
+            s
+        },
+    );
     context(
         "string",
-        preceded(char('\"'), cut(terminated(parse_str, char('\"')))),
+        preceded(char('\"'), cut(terminated(body, char('\"')))),
     )(i)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn max_depth() {
+        let err = corpus::check_max_depth(parse);
+        assert!(err.to_string().contains("nesting too deep"), "{err}");
+        assert!(parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use parol_runtime::{ParolError, ParserError};
+use std::{env, fs};
+
+fn main() {
+    let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
+        .expect("Failed to read file");
+
+    match parol_app::parse(&src) {
+        Ok(json) => {
+            #[cfg(debug_assertions)]
+            {
+                println!("{:#?}", json);
+            }
+            #[cfg(not(debug_assertions))]
+            {
+                std::hint::black_box(json);
+            }
+        }
+        Err(ParolError::ParserError(ParserError::SyntaxErrors { entries })) => {
+            for entry in entries {
+                let location = &entry.error_location;
+                eprintln!(
+                    "syntax error at line {} column {}, expected {}",
+                    location.start_line, location.start_column, entry.expected_tokens
+                );
+            }
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+}
@@ -0,0 +1,33 @@
+use parol::build::Builder;
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    Builder::with_cargo_script_output()
+        .grammar_file("json.par")
+        .parser_output_file("parser.rs")
+        .actions_output_file("grammar_trait.rs")
+        .user_type_name("JsonGrammar")
+        .user_trait_module_name("grammar")
+        .trim_parse_tree()
+        .disable_recovery()
+        // The syntax tree is built, converted and dropped recursively, which
+        // overflows the main thread's stack in a debug build at around 6000
+        // nested arrays. Each level opens three productions, so this stops
+        // just short of 4000.
+        .max_parsing_depth(12_000)
+        .generate_parser()
+        .unwrap();
+
+    // The generated actions open with `#![allow(...)]` lines, which `include!`
+    // can't take, so they are dropped here and put on the module instead.
+    let path: PathBuf = [env::var("OUT_DIR").unwrap().as_str(), "grammar_trait.rs"]
+        .iter()
+        .collect();
+    let source = fs::read_to_string(&path).unwrap();
+    let source: String = source
+        .lines()
+        .filter(|line| !line.starts_with("#!["))
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    fs::write(&path, source).unwrap();
+}
@@ -0,0 +1,179 @@
+//! JSON as an LL(k) grammar, `json.par`, which parol turns into a parser and
+//! a typed syntax tree in the build script.
+//!
+//! parol's scanner would otherwise skip any Unicode whitespace between tokens,
+//! so the grammar switches that off and places JSON's four whitespace
+//! characters itself, wherever a value or punctuation may be followed by them.
+
+use parol_runtime::ParolError;
+
+pub use ast::JsonValue;
+
+use grammar_trait::{ArraySuffix, Json, JsonGrammarTrait, Member, ObjectSuffix, Value};
+
+#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
+mod grammar_trait {
+    include!(concat!(env!("OUT_DIR"), "/grammar_trait.rs"));
+}
+
+mod json_parser {
+    include!(concat!(env!("OUT_DIR"), "/parser.rs"));
+}
+
+/// Parses a complete JSON document. Syntax errors are parol's own, while
+/// numbers out of range and unpaired surrogates come back as user errors.
+pub fn parse(input: &str) -> Result<JsonValue, ParolError> {
+    let mut grammar = grammar::JsonGrammar::default();
+    json_parser::parse(input, "input", &mut grammar)?;
+    Ok(grammar
+        .value
+        .expect("parol calls the start symbol's action on success"))
+}
+
+mod grammar {
+    use super::*;
+
+    /// The user actions: parol calls [`JsonGrammarTrait::json`] once the whole
+    /// document has been parsed, with its syntax tree.
+    #[derive(Default)]
+    pub struct JsonGrammar<'t> {
+        pub value: Option<JsonValue>,
+        _input: std::marker::PhantomData<&'t str>,
+    }
+
+    impl<'t> JsonGrammarTrait<'t> for JsonGrammar<'t> {
+        fn json(&mut self, arg: &Json<'t>) -> parol_runtime::Result<()> {
+            self.value = Some(value(&arg.element.value).map_err(ParolError::UserError)?);
+            Ok(())
+        }
+    }
+}
+
+fn value(value: &Value) -> anyhow::Result<JsonValue> {
+    Ok(match value {
+        Value::Object(object) => {
+            let mut members = vec![];
+            if let ObjectSuffix::MemberObjectSuffixListRBrace(suffix) =
+                &*object.object.object_suffix
+            {
+                members.push(member(&suffix.member)?);
+                for item in &suffix.object_suffix_list {
+                    members.push(member(&item.member)?);
+                }
+            }
+            JsonValue::Object(members)
+        }
+        Value::Array(array) => {
+            let mut items = vec![];
+            if let ArraySuffix::ValueArraySuffixOptArraySuffixListRBracket(suffix) =
+                &*array.array.array_suffix
+            {
+                items.push(self::value(&suffix.value)?);
+                for item in &suffix.array_suffix_list {
+                    items.push(self::value(&item.element.value)?);
+                }
+            }
+            JsonValue::Array(items)
+        }
+        Value::String(s) => JsonValue::String(string(s.string.string.text())?),
+        Value::Number(n) => number(n.number.number.text())?,
+        Value::True(_) => JsonValue::Bool(true),
+        Value::False(_) => JsonValue::Bool(false),
+        Value::Null(_) => JsonValue::Null,
+    })
+}
+
+fn member(member: &Member) -> anyhow::Result<(String, JsonValue)> {
+    Ok((
+        string(member.string.string.text())?,
+        value(&member.element.value)?,
+    ))
+}
+
+// The scanner's patterns are the rust-sitter example's, so only overflow is
+// left to check.
+fn number(s: &str) -> anyhow::Result<JsonValue> {
+    let n = s.parse::<f64>().unwrap();
+    anyhow::ensure!(n.is_finite(), "number out of range");
+    Ok(JsonValue::Number(n))
+}
+
+fn string(s: &str) -> anyhow::Result<String> {
+    unescape(&s[1..s.len() - 1]).map_err(anyhow::Error::msg)
+}
+/// Decodes the escapes in the body of a string token, pairing up the
+/// surrogates from `\u` escapes as rust-sitter's `unescape` does.
+///
+/// The token's pattern has already checked that every escape is well-formed.
+fn unescape(body: &str) -> Result<String, &'static str> {
+    if !body.contains('\\') {
+        return Ok(body.to_owned());
+    }
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match chars.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let rest = chars.as_str();
+                chars = rest[4..].chars();
+                u16::from_str_radix(&rest[..4], 16).unwrap()
+            }
+            c => c as u16,
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| "unpaired surrogate in string")
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        // parol's scanner builds its character classes with an exclusive upper
+        // bound of `char::MAX`, so no pattern, not even `.`, matches U+10FFFF.
+        corpus::check_json_test_suite(
+            |input| parse(input).is_ok(),
+            &["y_string_nonCharacterInUTF-8_U+10FFFF"],
+        );
+    }
+
+    #[test]
+    fn errors() {
+        use parol_runtime::ParserError;
+        match parse("[1, tru]") {
+            Err(ParolError::ParserError(ParserError::SyntaxErrors { entries })) => {
+                assert_eq!(entries.len(), 1, "{entries:?}");
+                assert_eq!(entries[0].error_location.start_line, 1);
+                assert_eq!(entries[0].error_location.start_column, 5);
+            }
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(
+            parse(r#"["\uD800"]"#).unwrap_err().to_string(),
+            "unpaired surrogate in string"
+        );
+        assert_eq!(
+            parse(&"[".repeat(100_000)).unwrap_err().to_string(),
+            "Max parsing depth exceeded: 12001"
+        );
+        assert_eq!(
+            parse("[1e400]").unwrap_err().to_string(),
+            "number out of range"
+        );
+    }
+}
@@ -1,12 +1,10 @@
-mod parser;
-
 use std::{env, fs};
 
 fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match parser::parser::json(&src) {
+    match peg_app::parse(&src) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
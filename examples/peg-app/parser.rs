@@ -1,48 +1,50 @@
-use std::{borrow::Cow, collections::HashMap, str::FromStr};
-
-#[derive(Debug, PartialEq, Clone)]
-pub enum JsonValue {
-    Null,
-    Boolean(bool),
-    Str(String),
-    Num(f64),
-    Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+use std::{borrow::Cow, str::FromStr};
+
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, peg::error::ParseError<peg::str::LineCol>> {
+    parser::json(input)
 }
 
 peg::parser!(pub grammar parser() for str {
 
 pub rule json() -> JsonValue
-    = _ value:value() _ { value }
+    = _ value:value(0) _ { value }
 
 rule _() = [' ' | '\t' | '\r' | '\n']*
 rule value_separator() = _ "," _
 
-rule value() -> JsonValue
-    = boolean() / null() / object() / array() / number() / string()
+/// `depth` counts the arrays and objects enclosing this value
+rule value(depth: usize) -> JsonValue
+    = boolean() / null() / object(depth + 1) / array(depth + 1) / number() / string()
 
 rule null() -> JsonValue
     = "null" { JsonValue::Null }
 
 rule boolean() -> JsonValue
-    = "true" { JsonValue::Boolean(true) }
-    / "false" { JsonValue::Boolean(false) }
+    = "true" { JsonValue::Bool(true) }
+    / "false" { JsonValue::Bool(false) }
 
-rule object() -> JsonValue
-    = "{" _ elements:(member() ** value_separator()) _ "}" {
-        JsonValue::Object(elements.into_iter().collect())
+/// Fails past `MAX_DEPTH`; the message has to be a literal, so it repeats it.
+rule depth_limit(depth: usize)
+    = {? if depth > MAX_DEPTH { Err("at most 128 levels of arrays and objects") } else { Ok(()) } }
+
+rule object(depth: usize) -> JsonValue
+    = "{" depth_limit(depth) _ elements:(member(depth) ** value_separator()) _ "}" {
+        JsonValue::Object(elements)
     }
 
-rule member() -> (String, JsonValue)
-    = key:raw_string() _ ":" _ value:value() { (key, value) }
+rule member(depth: usize) -> (String, JsonValue)
+    = key:raw_string() _ ":" _ value:value(depth) { (key, value) }
 
-rule array() -> JsonValue
-    = "[" _ elements:(value() ** value_separator()) _ "]" {
+rule array(depth: usize) -> JsonValue
+    = "[" depth_limit(depth) _ elements:(value(depth) ** value_separator()) _ "]" {
         JsonValue::Array(elements)
     }
 
 rule string() -> JsonValue
-    = value:raw_string() { JsonValue::Str(value) }
+    = value:raw_string() { JsonValue::String(value) }
 
 rule raw_string() -> String
     = "\"" slices:string_slice()* "\"" { slices.concat() }
@@ -50,37 +52,38 @@ rule raw_string() -> String
 /// A substring of same-kind (escaped or unescaped) characters
 rule string_slice() -> Cow<'input, str>
     = value:string_characters() { Cow::Borrowed(value) }
-    / value:string_escapes() { Cow::Owned(value.into_iter().collect()) }
+    / value:string_escapes() { Cow::Owned(value) }
 
 /// A substring of unescaped characters
 rule string_characters() -> &'input str
-    = $([^ '\"' | '\\']+)
+    = $([^ '\"' | '\\' | '\x00'..='\x1F']+)
 
-/// A substring of escaped characters
-rule string_escapes() -> Vec<char>
-    = ("\\" value:string_escape_char() { value })+
+/// A substring of escaped characters, decoded as UTF-16 as rust-sitter's
+/// `unescape` does, so that the two halves of a surrogate pair come together
+rule string_escapes() -> String
+    = units:("\\" value:string_escape_unit() { value })+ {?
+        String::from_utf16(&units).map_err(|_| "paired surrogates")
+    }
 
 /// Handles a single escape
-rule string_escape_char() -> char
-    = "\"" { '"' }
-    / "\\" { '\\' }
-    / "/"  { '/' }
-    / "b" { '\x08' }
-    / "f" { '\x0C' }
-    / "n" { '\n' }
-    / "r" { '\r' }
-    / "t" { '\t' }
-    / "u" digits:$(hex_digit()*<4>) { ?
-        let value = u16::from_str_radix(digits, 16).unwrap();
-        char::from_u32(value.into()).ok_or("invalid unicode escape")
-    }
+rule string_escape_unit() -> u16
+    = "\"" { '"' as u16 }
+    / "\\" { '\\' as u16 }
+    / "/"  { '/' as u16 }
+    / "b" { 0x08 }
+    / "f" { 0x0C }
+    / "n" { '\n' as u16 }
+    / "r" { '\r' as u16 }
+    / "t" { '\t' as u16 }
+    / "u" digits:$(hex_digit()*<4>) { u16::from_str_radix(digits, 16).unwrap() }
 
 rule hex_digit()
     = ['0'..='9' | 'a'..='f' | 'A'..='F']
 
 rule number() -> JsonValue
-    = "-"? value:$(int() frac()? exp()?) { ?
-        Ok(JsonValue::Num(f64::from_str(value).map_err(|_| "invalid number")?))
+    = value:$("-"? int() frac()? exp()?) {?
+        let n = f64::from_str(value).unwrap();
+        if n.is_finite() { Ok(JsonValue::Number(n)) } else { Err("a number in range") }
     }
 
 rule int()
@@ -92,3 +95,30 @@ rule exp()
 rule frac()
     = "." ['0'..='9']*<1,>
 });
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn max_depth() {
+        let err = corpus::check_max_depth(parse);
+        assert_eq!(err.location.offset, MAX_DEPTH + 1);
+        assert!(
+            err.expected
+                .tokens()
+                .any(|t| t == "at most 128 levels of arrays and objects"),
+            "{err}"
+        );
+    }
+}
@@ -7,17 +7,13 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-extern crate pest;
-
-mod parser;
-
 use std::{env, fs};
 
 fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match parser::Json::parse(&src) {
+    match pest_app::parse(&src) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -7,291 +7,152 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::HashMap;
+//! The grammar lives in `json.pest`; this file only turns the `Pairs` tree it
+//! produces into a [`JsonValue`].
 
-use pest::error::Error;
-use pest::iterators::{Pair, Pairs};
-use pest::{state, ParseResult, Parser, ParserState, Span};
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::{Parser, Position, Span};
+use pest_derive::Parser;
 
-#[derive(Debug, PartialEq)]
-pub enum Json<'i> {
-    Null,
-    Bool(bool),
-    Number(f64),
-    String(Span<'i>),
-    Array(Vec<Json<'i>>),
-    Object(HashMap<Span<'i>, Json<'i>>),
-}
-
-impl<'i> Json<'i> {
-    pub fn parse(input: &'i str) -> Result<Self, Error<Rule>> {
-        let json = Self::consume(JsonParser::parse(Rule::json, input)?.next().unwrap());
-        Ok(json)
-    }
-
-    fn consume(pair: Pair<'i, Rule>) -> Self {
-        let pair = pair.into_inner().next().unwrap();
+pub use ast::{JsonValue, MAX_DEPTH};
 
-        match pair.as_rule() {
-            Rule::null => Self::Null,
-            Rule::bool => match pair.as_str() {
-                "false" => Self::Bool(false),
-                "true" => Self::Bool(true),
-                _ => unreachable!(),
-            },
-            Rule::number => Self::Number(pair.as_str().parse().unwrap()),
-            Rule::string => Self::String(pair.as_span()),
-            Rule::array => Self::Array(pair.into_inner().map(Self::consume).collect()),
-            Rule::object => {
-                let pairs = pair.into_inner().map(|pos| {
-                    let mut pair = pos.into_inner();
+#[derive(Parser)]
+#[grammar = "json.pest"]
+struct JsonParser;
 
-                    let key = pair.next().unwrap().as_span();
-                    let value = Self::consume(pair.next().unwrap());
+/// Parses a complete JSON document.
+///
+/// pest's errors carry the rendered source line, so they are boxed to keep the
+/// `Result` small.
+pub fn parse(input: &str) -> Result<JsonValue, Box<Error<Rule>>> {
+    check_depth(input)?;
+    let pair = JsonParser::parse(Rule::json, input)?.next().unwrap();
+    consume(pair)
+}
 
-                    (key, value)
-                });
+/// Builds the value for one `value` pair. The grammar has already checked
+/// the syntax; what is left are the checks a PEG can't express, on the
+/// decoded contents of strings and numbers.
+fn consume(pair: Pair<'_, Rule>) -> Result<JsonValue, Box<Error<Rule>>> {
+    let json = match pair.as_rule() {
+        Rule::null => JsonValue::Null,
+        Rule::boolean => JsonValue::Bool(pair.as_str() == "true"),
+        Rule::number => {
+            let n: f64 = pair.as_str().parse().unwrap();
+            if !n.is_finite() {
+                return Err(custom_error(pair.as_span(), "number out of range"));
+            }
+            JsonValue::Number(n)
+        }
+        Rule::string => JsonValue::String(string(pair)?),
+        Rule::array => JsonValue::Array(pair.into_inner().map(consume).collect::<Result<_, _>>()?),
+        Rule::object => JsonValue::Object(
+            pair.into_inner()
+                .map(|pair| {
+                    let mut pair = pair.into_inner();
+                    let key = string(pair.next().unwrap())?;
+                    let value = consume(pair.next().unwrap())?;
+                    Ok((key, value))
+                })
+                .collect::<Result<_, Box<Error<Rule>>>>()?,
+        ),
+        _ => unreachable!(),
+    };
+    Ok(json)
+}
 
-                Self::Object(pairs.collect())
+/// Decodes a `string` pair, pairing up the surrogates from `\u` escapes as
+/// rust-sitter's `unescape` does.
+fn string(pair: Pair<'_, Rule>) -> Result<String, Box<Error<Rule>>> {
+    let span = pair.as_span();
+    let chars = pair.into_inner().next().unwrap().as_str();
+    let mut units: Vec<u16> = Vec::with_capacity(chars.len());
+    let mut rest = chars.chars();
+    while let Some(c) = rest.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            continue;
+        }
+        let unit = match rest.next().unwrap() {
+            'b' => 0x08,
+            'f' => 0x0C,
+            'n' => '\n' as u16,
+            'r' => '\r' as u16,
+            't' => '\t' as u16,
+            'u' => {
+                let hex = rest.as_str().get(..4).unwrap();
+                rest = rest.as_str()[4..].chars();
+                u16::from_str_radix(hex, 16).unwrap()
             }
-            _ => unreachable!(),
-        }
+            c => c as u16,
+        };
+        units.push(unit);
     }
+    String::from_utf16(&units).map_err(|_| custom_error(span, "unpaired surrogate"))
 }
 
-#[allow(dead_code, non_camel_case_types)]
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum Rule {
-    json,
-    object,
-    pair,
-    array,
-    value,
-    string,
-    escape,
-    unicode,
-    hex,
-    number,
-    int,
-    exp,
-    bool,
-    null,
+fn custom_error(span: Span<'_>, message: &str) -> Box<Error<Rule>> {
+    Box::new(Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: message.to_string(),
+        },
+        span,
+    ))
 }
 
-struct JsonParser;
-
-impl Parser<Rule> for JsonParser {
-    fn parse(rule: Rule, input: &str) -> Result<Pairs<Rule>, Error<Rule>> {
-        fn json(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            value(state)
-        }
-
-        fn object(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::object, |s| {
-                s.sequence(|s| {
-                    s.match_string("{")
-                        .and_then(|s| skip(s))
-                        .and_then(|s| pair(s))
-                        .and_then(|s| skip(s))
-                        .and_then(|s| {
-                            s.repeat(|s| {
-                                s.sequence(|s| {
-                                    s.match_string(",")
-                                        .and_then(|s| skip(s))
-                                        .and_then(|s| pair(s))
-                                        .and_then(|s| skip(s))
-                                })
-                            })
-                        })
-                        .and_then(|s| s.match_string("}"))
-                })
-                .or_else(|s| {
-                    s.sequence(|s| {
-                        s.match_string("{")
-                            .and_then(|s| skip(s))
-                            .and_then(|s| s.match_string("}"))
-                    })
-                })
-            })
-        }
-
-        fn pair(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::pair, |s| {
-                s.sequence(|s| {
-                    string(s)
-                        .and_then(|s| skip(s))
-                        .and_then(|s| s.match_string(":"))
-                        .and_then(|s| skip(s))
-                        .and_then(|s| value(s))
-                })
-            })
-        }
-
-        fn array(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::array, |s| {
-                s.sequence(|s| {
-                    s.match_string("[")
-                        .and_then(|s| skip(s))
-                        .and_then(|s| value(s))
-                        .and_then(|s| skip(s))
-                        .and_then(|s| {
-                            s.repeat(|s| {
-                                s.sequence(|s| {
-                                    s.match_string(",")
-                                        .and_then(|s| skip(s))
-                                        .and_then(|s| value(s))
-                                        .and_then(|s| skip(s))
-                                })
-                            })
-                        })
-                        .and_then(|s| s.match_string("]"))
-                })
-                .or_else(|s| {
-                    s.sequence(|s| {
-                        s.match_string("[")
-                            .and_then(|s| skip(s))
-                            .and_then(|s| s.match_string("]"))
-                    })
-                })
-            })
-        }
-
-        fn value(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::value, |s| {
-                string(s)
-                    .or_else(|s| number(s))
-                    .or_else(|s| object(s))
-                    .or_else(|s| array(s))
-                    .or_else(|s| bool(s))
-                    .or_else(|s| null(s))
-            })
-        }
-
-        fn string(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::string, |s| {
-                s.match_string("\"")
-                    .and_then(|s| {
-                        s.repeat(|s| {
-                            escape(s).or_else(|s| {
-                                s.sequence(|s| {
-                                    s.lookahead(false, |s| {
-                                        s.match_string("\"").or_else(|s| s.match_string("\\"))
-                                    })
-                                    .and_then(|s| s.skip(1))
-                                })
-                            })
-                        })
-                    })
-                    .and_then(|pos| pos.match_string("\""))
-            })
-        }
-
-        fn escape(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.sequence(|s| {
-                s.match_string("\\").and_then(|s| {
-                    s.match_string("\"")
-                        .or_else(|s| s.match_string("\\"))
-                        .or_else(|s| s.match_string("/"))
-                        .or_else(|s| s.match_string("b"))
-                        .or_else(|s| s.match_string("f"))
-                        .or_else(|s| s.match_string("n"))
-                        .or_else(|s| s.match_string("r"))
-                        .or_else(|s| s.match_string("t"))
-                        .or_else(|s| unicode(s))
-                })
-            })
-        }
-
-        fn unicode(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.sequence(|s| {
-                s.match_string("u")
-                    .and_then(|s| hex(s))
-                    .and_then(|s| hex(s))
-                    .and_then(|s| hex(s))
-            })
-        }
-
-        fn hex(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state
-                .match_range('0'..'9')
-                .or_else(|s| s.match_range('a'..'f'))
-                .or_else(|s| s.match_range('A'..'F'))
-        }
-
-        fn number(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::number, |s| {
-                s.sequence(|s| {
-                    s.optional(|s| s.match_string("-"))
-                        .and_then(|s| int(s))
-                        .and_then(|s| {
-                            s.optional(|s| {
-                                s.sequence(|s| {
-                                    s.match_string(".")
-                                        .and_then(|s| s.match_range('0'..'9'))
-                                        .and_then(|s| s.repeat(|s| s.match_range('0'..'9')))
-                                        .and_then(|s| s.optional(|s| exp(s)))
-                                        .or_else(|s| exp(s))
-                                })
-                            })
-                        })
-                })
-            })
-        }
-
-        fn int(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.match_string("0").or_else(|s| {
-                s.sequence(|s| {
-                    s.match_range('1'..'9')
-                        .and_then(|s| s.repeat(|s| s.match_range('0'..'9')))
-                })
-            })
-        }
-
-        fn exp(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.sequence(|s| {
-                s.match_string("E")
-                    .or_else(|s| s.match_string("e"))
-                    .and_then(|s| {
-                        s.optional(|s| s.match_string("+").or_else(|s| s.match_string("-")))
-                    })
-                    .and_then(|s| int(s))
-            })
+/// pest has no way to bound recursion from inside a grammar, so deep input is
+/// turned away before it gets to parse.
+fn check_depth(input: &str) -> Result<(), Box<Error<Rule>>> {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (i, c) in input.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => {
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err(Box::new(Error::new_from_pos(
+                        ErrorVariant::CustomError {
+                            message: format!("more than {MAX_DEPTH} levels of arrays and objects"),
+                        },
+                        Position::new(input, i).unwrap(),
+                    )));
+                }
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
         }
+    }
+    Ok(())
+}
 
-        fn bool(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::bool, |s| {
-                s.match_string("true").or_else(|s| s.match_string("false"))
-            })
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        fn null(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.rule(Rule::null, |s| s.match_string("null"))
-        }
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
 
-        fn skip(state: Box<ParserState<Rule>>) -> ParseResult<Box<ParserState<Rule>>> {
-            state.repeat(|s| {
-                s.match_string(" ")
-                    .or_else(|s| s.match_string("\t"))
-                    .or_else(|s| s.match_string("\r"))
-                    .or_else(|s| s.match_string("\n"))
-            })
-        }
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
 
-        state(input, |state| match rule {
-            Rule::json => json(state),
-            Rule::object => object(state),
-            Rule::pair => pair(state),
-            Rule::array => array(state),
-            Rule::value => value(state),
-            Rule::string => string(state),
-            Rule::escape => escape(state),
-            Rule::unicode => unicode(state),
-            Rule::hex => hex(state),
-            Rule::number => number(state),
-            Rule::int => int(state),
-            Rule::exp => exp(state),
-            Rule::bool => bool(state),
-            Rule::null => null(state),
-        })
+    #[test]
+    fn max_depth() {
+        corpus::check_max_depth(parse);
+        // Brackets inside strings don't count.
+        assert!(parse(&format!("\"{}\"", "[".repeat(1000))).is_ok());
     }
 }
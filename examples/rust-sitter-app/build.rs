@@ -2,5 +2,5 @@ use std::path::PathBuf;
 
 fn main() {
     println!("cargo:rerun-if-changed=src");
-    rust_sitter_tool::build_parsers(&PathBuf::from("src/app.rs"));
+    rust_sitter_tool::build_parsers(&PathBuf::from("src/lib.rs"));
 }
@@ -0,0 +1,55 @@
+//! Checks that `parse` agrees with serde_json on every input.
+//!
+//! ```bash
+//! $ cargo +nightly fuzz run differential
+//! ```
+//!
+//! Both parsers must accept or reject the same documents, and when both accept,
+//! the values must be equal once ours is converted to a `serde_json::Value`.
+//!
+//! Intended differences that are not reported:
+//! - Nesting limits: both parsers cap recursion near 128 levels but count them
+//!   slightly differently, so rejections for depth are skipped.
+//! - Duplicate keys: both keep the last value, which the conversion mirrors, so
+//!   these compare equal without special handling.
+//! - A leading U+FEFF byte order mark: we skip it, while serde_json rejects it,
+//!   so serde_json is given the input without it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_sitter_app::parser::{parse, ParseErrorReason};
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let ours = parse(input);
+    let theirs = serde_json::from_str::<Value>(input.strip_prefix('\u{FEFF}').unwrap_or(input));
+
+    let ours_too_deep = ours.as_ref().is_err_and(|errs| {
+        errs.iter()
+            .any(|e| matches!(e.reason, ParseErrorReason::DepthLimitExceeded(_)))
+    });
+    let theirs_too_deep = theirs
+        .as_ref()
+        .is_err_and(|e| e.to_string().starts_with("recursion limit exceeded"));
+    if ours_too_deep || theirs_too_deep {
+        return;
+    }
+
+    match (ours, theirs) {
+        (Ok(ours), Ok(theirs)) => {
+            assert_eq!(Value::from(ours), theirs, "values differ for {input:?}")
+        }
+        (Err(_), Err(_)) => {}
+        (Ok(ours), Err(err)) => {
+            panic!("only serde_json rejected {input:?} ({err}); we parsed {ours}")
+        }
+        (Err(errs), Ok(theirs)) => {
+            panic!("only we rejected {input:?} ({errs:?}); serde_json parsed {theirs}")
+        }
+    }
+});
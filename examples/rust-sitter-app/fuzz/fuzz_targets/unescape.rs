@@ -0,0 +1,18 @@
+//! Checks that `unescape` never panics, whatever string body it is given.
+//!
+//! ```bash
+//! $ cargo +nightly fuzz run unescape
+//! ```
+//!
+//! Whatever it decodes must also survive a round trip through `escape`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_sitter_app::parser::{escape, unescape};
+
+fuzz_target!(|body: &str| {
+    if let Ok(decoded) = unescape(body) {
+        assert_eq!(unescape(&escape(&decoded)).unwrap(), decoded, "for {body:?}");
+    }
+});
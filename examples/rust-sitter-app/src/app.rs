@@ -1,86 +1,1083 @@
-use std::{env, fs};
+use std::{
+    collections::HashSet,
+    env, fs,
+    io::{self, BufRead, BufReader, IsTerminal, Read},
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "generate")]
+use bench_inputs::gen;
 use codemap::CodeMap;
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, SpanStyle};
-use rust_sitter::errors::{ParseError, ParseErrorReason};
+use rust_sitter_app::parser::{
+    self, grammar::JsonValue, write_escaped, ParseError, ParseErrorReason, ParseOptions, Production,
+};
 
-mod parser;
+const USAGE: &str = "usage: rust-sitter-app [--format=human|json] [--color=auto|always|never] [--reporter=codemap|miette] [--max-errors N] [--depth-limit N] [--max-string-length N] [--max-bytes N] [--explain] [--warn-precision] [--quiet] [--ndjson] [--count] [--bench N] [--generate SHAPE:N]... [--stats] [--tree] [--sort-keys] [<file> | -]...
 
-fn convert_parse_error_to_diagnostics(
-    file_span: &codemap::Span,
-    error: &ParseError,
-    diagnostics: &mut Vec<Diagnostic>,
-) {
+Without --depth-limit, arrays and objects may nest 128 deep, the parser's default, rather than without limit; --depth-limit can raise that to at most 1000.";
+
+/// The highest `--depth-limit` accepted. Extracting a value recurses once per
+/// level of nesting, and a debug build runs out of stack somewhere past 2000
+/// levels of objects.
+const MAX_DEPTH_LIMIT: usize = 1000;
+
+/// How many diagnostics `--max-errors` lets through per input by default.
+const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// How many characters of each string and number `--tree` shows.
+const TREE_VALUE_LEN: usize = 40;
+
+/// The name diagnostics use for input piped in on stdin.
+const STDIN_NAME: &str = "<stdin>";
+
+/// How parse errors are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// Colored diagnostics on stderr, underlining the source.
+    Human,
+    /// A JSON array of diagnostics on stdout, for editors and CI.
+    Json,
+}
+
+/// What renders human diagnostics for parse errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reporter {
+    /// codemap-diagnostic, with a diagnostic of its own for each error.
+    Codemap,
+    /// miette, with every error labeled in one report, as the library's
+    /// `Diagnostic` impl describes them. Only with the `miette` feature.
+    Miette,
+}
+
+/// A document to parse.
+#[derive(Debug, PartialEq, Eq)]
+enum Input {
+    /// Whatever is piped in on stdin, which is what `-` means.
+    Stdin,
+    File(String),
+    /// A document from `bench_inputs::gen`, named like `deep:100` after the
+    /// `--generate` argument that asked for it. Only with the `generate`
+    /// feature.
+    #[cfg(feature = "generate")]
+    Generated {
+        name: String,
+        shape: Shape,
+        size: usize,
+    },
+}
+
+impl Input {
+    fn name(&self) -> &str {
+        match self {
+            Input::Stdin => STDIN_NAME,
+            Input::File(file) => file,
+            #[cfg(feature = "generate")]
+            Input::Generated { name, .. } => name,
+        }
+    }
+
+    /// Reads the whole document, failing without reading any more of it than
+    /// that once it turns out to be longer than `max_bytes`, or with the offset
+    /// of the first byte that isn't UTF-8.
+    fn read(&self, max_bytes: Option<u64>) -> io::Result<String> {
+        let too_large = |len: Option<u64>, max: u64| {
+            let len = len.map_or("the input".to_string(), |len| format!("{len} bytes"));
+            io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("{len} is over the --max-bytes limit of {max}"),
+            )
+        };
+        let mut bytes = vec![];
+        match self {
+            Input::Stdin => {
+                io::stdin()
+                    .take(max_bytes.map_or(u64::MAX, |max| max + 1))
+                    .read_to_end(&mut bytes)?;
+            }
+            Input::File(file) => {
+                let file = fs::File::open(file)?;
+                // Checked up front so that a huge file is never read at all.
+                let len = file.metadata()?.len();
+                if let Some(max) = max_bytes.filter(|&max| len > max) {
+                    return Err(too_large(Some(len), max));
+                }
+                // The file may have grown since.
+                file.take(max_bytes.map_or(u64::MAX, |max| max + 1))
+                    .read_to_end(&mut bytes)?;
+            }
+            #[cfg(feature = "generate")]
+            Input::Generated { shape, size, .. } => bytes = shape.generate(*size).into_bytes(),
+        }
+        if let Some(max) = max_bytes.filter(|&max| bytes.len() as u64 > max) {
+            return Err(too_large(None, max));
+        }
+        String::from_utf8(bytes).map_err(|err| {
+            let what = match self {
+                Input::File(_) => "file",
+                _ => "input",
+            };
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{what} is not valid UTF-8 at byte {}",
+                    err.utf8_error().valid_up_to()
+                ),
+            )
+        })
+    }
+}
+
+impl Input {
+    /// Opens the document to read a line at a time, for `--count`.
+    fn lines(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(match self {
+            Input::Stdin => Box::new(io::stdin().lock()),
+            Input::File(file) => Box::new(BufReader::new(fs::File::open(file)?)),
+            #[cfg(feature = "generate")]
+            Input::Generated { shape, size, .. } => {
+                Box::new(io::Cursor::new(shape.generate(*size)))
+            }
+        })
+    }
+}
+
+/// The kinds of document `--generate` can make.
+#[cfg(feature = "generate")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shape {
+    Deep,
+    WideArray,
+    StringHeavy,
+    Numbers,
+}
+
+#[cfg(feature = "generate")]
+impl Shape {
+    fn parse(name: &str) -> Option<Shape> {
+        match name {
+            "deep" => Some(Shape::Deep),
+            "wide_array" => Some(Shape::WideArray),
+            "string_heavy" => Some(Shape::StringHeavy),
+            "numbers" => Some(Shape::Numbers),
+            _ => None,
+        }
+    }
+
+    fn generate(self, size: usize) -> String {
+        match self {
+            Shape::Deep => gen::deep(size),
+            Shape::WideArray => gen::wide_array(size),
+            Shape::StringHeavy => gen::string_heavy(size),
+            Shape::Numbers => gen::numbers(size),
+        }
+    }
+}
+
+/// Whether human diagnostics are colored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    /// Only when stderr is a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    fn config(self) -> ColorConfig {
+        match self {
+            Color::Auto if io::stderr().is_terminal() => ColorConfig::Always,
+            Color::Auto => ColorConfig::Never,
+            Color::Always => ColorConfig::Always,
+            Color::Never => ColorConfig::Never,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    format: Format,
+    color: Color,
+    /// `--max-errors`, `--explain` and the enclosing spans only apply to the
+    /// codemap reporter.
+    #[cfg_attr(not(feature = "miette"), allow(dead_code))]
+    reporter: Reporter,
+    /// How many human diagnostics to show for each input, or `None` for all of
+    /// them.
+    max_errors: Option<usize>,
+    /// How deeply arrays and objects may nest, at most [`MAX_DEPTH_LIMIT`].
+    /// Unlike the other limits, `None` doesn't mean no limit but the parser's
+    /// default of 128 levels.
+    depth_limit: Option<usize>,
+    /// The longest string to accept, in bytes, or `None` for no limit.
+    max_string_length: Option<usize>,
+    /// The largest input to read, in bytes, or `None` for no limit.
+    max_bytes: Option<u64>,
+    /// Say which part of the grammar was being parsed where each error occurred.
+    explain: bool,
+    /// Warn about numbers that change when read as an `f64`.
+    warn_precision: bool,
+    /// Print nothing, leaving the exit code to say whether every input parsed.
+    quiet: bool,
+    /// Treat each line of an input as a document of its own.
+    ndjson: bool,
+    /// Check each line of an input as a document of its own, reading a line at
+    /// a time, and print only the lines that fail and how many of each there
+    /// were. `--max-bytes` doesn't apply, since no input is held whole.
+    count: bool,
+    /// How many times to parse each input to time it, instead of printing the
+    /// tree.
+    bench: Option<usize>,
+    /// Print how many values of each kind every document holds, instead of the
+    /// tree.
+    stats: bool,
+    /// Print an indented outline of every document's structure, instead of the
+    /// tree.
+    tree: bool,
+    /// Print every document in canonical form, with sorted keys and uniform
+    /// whitespace, instead of the tree.
+    sort_keys: bool,
+    inputs: Vec<Input>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut format = Format::Human;
+    let mut color = Color::Auto;
+    let mut reporter = Reporter::Codemap;
+    let mut max_errors = Some(DEFAULT_MAX_ERRORS);
+    let mut depth_limit = None;
+    let mut max_string_length = None;
+    let mut max_bytes = None;
+    let mut explain = false;
+    let mut warn_precision = false;
+    let mut quiet = false;
+    let mut ndjson = false;
+    let mut count = false;
+    let mut bench = None;
+    let mut stats = false;
+    let mut tree = false;
+    let mut sort_keys = false;
+    let mut inputs = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--format=") {
+            format = match value {
+                "human" => Format::Human,
+                "json" => Format::Json,
+                _ => return Err(format!("unknown format {value:?}")),
+            };
+        } else if let Some(value) = arg.strip_prefix("--color=") {
+            color = match value {
+                "auto" => Color::Auto,
+                "always" => Color::Always,
+                "never" => Color::Never,
+                _ => return Err(format!("unknown color setting {value:?}")),
+            };
+        } else if let Some(value) = arg.strip_prefix("--reporter=") {
+            reporter = match value {
+                "codemap" => Reporter::Codemap,
+                "miette" if cfg!(feature = "miette") => Reporter::Miette,
+                "miette" => return Err("--reporter=miette needs the miette feature".to_string()),
+                _ => return Err(format!("unknown reporter {value:?}")),
+            };
+        } else if arg == "--max-errors" || arg.starts_with("--max-errors=") {
+            let value = match arg.strip_prefix("--max-errors=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--max-errors needs a value")?,
+            };
+            // As with compilers' error limits, 0 means no limit at all.
+            max_errors = match value.parse() {
+                Ok(0) => None,
+                Ok(n) => Some(n),
+                Err(_) => return Err(format!("invalid error limit {value:?}")),
+            };
+        } else if arg == "--depth-limit" || arg.starts_with("--depth-limit=") {
+            let value = match arg.strip_prefix("--depth-limit=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--depth-limit needs a value")?,
+            };
+            let limit = value
+                .parse()
+                .map_err(|_| format!("invalid depth limit {value:?}"))?;
+            if limit > MAX_DEPTH_LIMIT {
+                return Err(format!(
+                    "depth limit {limit} is over the maximum of {MAX_DEPTH_LIMIT}"
+                ));
+            }
+            depth_limit = Some(limit);
+        } else if arg == "--max-string-length" || arg.starts_with("--max-string-length=") {
+            let value = match arg.strip_prefix("--max-string-length=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--max-string-length needs a value")?,
+            };
+            max_string_length = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid string length limit {value:?}"))?,
+            );
+        } else if arg == "--max-bytes" || arg.starts_with("--max-bytes=") {
+            let value = match arg.strip_prefix("--max-bytes=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--max-bytes needs a value")?,
+            };
+            max_bytes = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid size limit {value:?}"))?,
+            );
+        } else if arg == "--explain" {
+            explain = true;
+        } else if arg == "--warn-precision" {
+            warn_precision = true;
+        } else if arg == "--quiet" {
+            quiet = true;
+        } else if arg == "--ndjson" {
+            ndjson = true;
+        } else if arg == "--count" {
+            count = true;
+        } else if arg == "--bench" || arg.starts_with("--bench=") {
+            let value = match arg.strip_prefix("--bench=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--bench needs a value")?,
+            };
+            bench = match value.parse() {
+                Ok(0) | Err(_) => return Err(format!("invalid iteration count {value:?}")),
+                Ok(n) => Some(n),
+            };
+        } else if arg == "--generate" || arg.starts_with("--generate=") {
+            let name = match arg.strip_prefix("--generate=") {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or("--generate needs a value")?,
+            };
+            #[cfg(not(feature = "generate"))]
+            return Err(format!("--generate={name} needs the generate feature"));
+            #[cfg(feature = "generate")]
+            {
+                let generated = name
+                    .split_once(':')
+                    .and_then(|(shape, size)| Some((Shape::parse(shape)?, size.parse().ok()?)));
+                let Some((shape, size)) = generated else {
+                    return Err(format!("invalid document to generate {name:?}"));
+                };
+                inputs.push(Input::Generated { name, shape, size });
+            }
+        } else if arg == "--stats" {
+            stats = true;
+        } else if arg == "--tree" {
+            tree = true;
+        } else if arg == "--sort-keys" {
+            sort_keys = true;
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option {arg:?}"));
+        } else if arg == "-" {
+            if inputs.contains(&Input::Stdin) {
+                return Err("stdin can only be read once".to_string());
+            }
+            inputs.push(Input::Stdin);
+        } else {
+            inputs.push(Input::File(arg));
+        }
+    }
+    if inputs.is_empty() {
+        if io::stdin().is_terminal() {
+            return Err("Expected file argument".to_string());
+        }
+        inputs.push(Input::Stdin);
+    }
+    Ok(Args {
+        format,
+        color,
+        reporter,
+        max_errors,
+        depth_limit,
+        max_string_length,
+        max_bytes,
+        explain,
+        warn_precision,
+        quiet,
+        ndjson,
+        count,
+        bench,
+        stats,
+        tree,
+        sort_keys,
+        inputs,
+    })
+}
+
+/// Parses each line of `src` as a document of its own, skipping blank lines.
+///
+/// Errors from every line are collected, with offsets into the whole of `src` so
+/// that diagnostics point at the line they came from.
+fn parse_ndjson(src: &str, options: &ParseOptions) -> Result<Vec<JsonValue>, Vec<ParseError>> {
+    let mut values = vec![];
+    let mut errors = vec![];
+    let mut start = 0;
+    for line in src.split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            match parser::parse_with_options(line.strip_suffix('\n').unwrap_or(line), options) {
+                Ok(value) => values.push(value),
+                Err(errs) => errors.extend(errs.into_iter().map(|mut error| {
+                    error.shift(start);
+                    error
+                })),
+            }
+        }
+        start += line.len();
+    }
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
+}
+
+/// How many of an input's lines were valid documents, for `--count`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Count {
+    valid: usize,
+    invalid: usize,
+}
+
+/// Parses each line of `reader` as a document of its own, skipping blank lines
+/// as `--ndjson` does, but drops each value as soon as it is checked, so only
+/// one line is held at a time.
+///
+/// `on_invalid` is called with the 1-based line and column of the first error
+/// in each line that fails, and what it is.
+fn count_lines(
+    reader: impl BufRead,
+    options: &ParseOptions,
+    mut on_invalid: impl FnMut(usize, usize, &str),
+) -> io::Result<Count> {
+    let mut count = Count::default();
+    for (i, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                count.invalid += 1;
+                on_invalid(i + 1, 1, "Invalid UTF-8");
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parser::parse_with_options(&line, options) {
+            Ok(_) => count.valid += 1,
+            Err(errs) => {
+                count.invalid += 1;
+                let first = distinct_leaf_errors(&errs)[0];
+                let column = line[..first.start].chars().count() + 1;
+                on_invalid(i + 1, column, &describe(first).0);
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Parses `src` as one document, or as one per line with `--ndjson`.
+fn parse_input(
+    src: &str,
+    ndjson: bool,
+    options: &ParseOptions,
+) -> Result<Vec<JsonValue>, Vec<ParseError>> {
+    if ndjson {
+        parse_ndjson(src, options)
+    } else {
+        parser::parse_with_options(src, options).map(|json| vec![json])
+    }
+}
+
+/// Parses `src` `iterations` times and returns the median time a parse took.
+fn bench(src: &str, ndjson: bool, options: &ParseOptions, iterations: usize) -> Duration {
+    let mut times: Vec<_> = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            let _ = std::hint::black_box(parse_input(std::hint::black_box(src), ndjson, options));
+            start.elapsed()
+        })
+        .collect();
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// The production that was being parsed where `error` occurred, for
+/// `--explain`.
+///
+/// With `--ndjson`, only the line the error is on is parsed again, after as many
+/// spaces as there are bytes before it, so that the error's offsets still fit.
+fn explain(src: &str, ndjson: bool, error: &ParseError) -> Option<Production> {
+    if !ndjson {
+        return parser::production(src, error);
+    }
+    let start = src[..error.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+    parser::production(&format!("{:start$}{}", "", &src[start..end]), error)
+}
+
+/// The span of the `FailedNode` that `leaf` is directly inside, if it covers
+/// more than `leaf` does, to show what was being parsed around the error.
+fn enclosing_span(errors: &[ParseError], leaf: &ParseError) -> Option<(usize, usize)> {
+    errors.iter().find_map(|error| {
+        let ParseErrorReason::FailedNode(inner) = &error.reason else {
+            return None;
+        };
+        if !inner.iter().any(|child| std::ptr::eq(child, leaf)) {
+            return enclosing_span(inner, leaf);
+        }
+        let covers = error.start <= leaf.start && leaf.end <= error.end;
+        (covers && (error.start, error.end) != (leaf.start, leaf.end))
+            .then_some((error.start, error.end))
+    })
+}
+
+/// The leaf errors, keeping only the first of any that would make the same
+/// diagnostic, with the same message at the same span, since one error can be
+/// reached through more than one `FailedNode`.
+fn distinct_leaf_errors(errors: &[ParseError]) -> Vec<&ParseError> {
+    let mut leaves = parser::leaf_errors(errors);
+    let mut seen = HashSet::new();
+    leaves.retain(|error| seen.insert((error.start, error.end, describe(error))));
+    leaves
+}
+
+/// The name of the production, as reported in JSON diagnostics.
+fn production_name(production: Production) -> &'static str {
+    match production {
+        Production::Value => "Value",
+        Production::Array => "Array",
+        Production::Object => "Object",
+        Production::Property => "Property",
+        Production::String => "String",
+        Production::Number => "Number",
+    }
+}
+
+/// The one-line summary of a leaf error and the label for its span.
+fn describe(error: &ParseError) -> (String, String) {
     match &error.reason {
-        ParseErrorReason::MissingToken(tok) => diagnostics.push(Diagnostic {
-            level: Level::Error,
-            message: format!("Missing token: \"{tok}\""),
-            code: Some("S000".to_string()),
-            spans: vec![SpanLabel {
-                span: file_span.subspan(error.start as u64, error.end as u64),
-                style: SpanStyle::Primary,
-                label: Some(format!("missing \"{tok}\"")),
-            }],
-        }),
-
-        ParseErrorReason::UnexpectedToken(tok) => diagnostics.push(Diagnostic {
-            level: Level::Error,
-            message: format!("Unexpected token: \"{tok}\""),
-            code: Some("S000".to_string()),
-            spans: vec![SpanLabel {
-                span: file_span.subspan(error.start as u64, error.end as u64),
-                style: SpanStyle::Primary,
-                label: Some(format!("unexpected \"{tok}\"")),
-            }],
-        }),
-
-        ParseErrorReason::FailedNode(errors) => {
-            if errors.is_empty() {
-                diagnostics.push(Diagnostic {
-                    level: Level::Error,
-                    message: "Failed to parse node".to_string(),
-                    code: Some("S000".to_string()),
-                    spans: vec![SpanLabel {
-                        span: file_span.subspan(error.start as u64, error.end as u64),
-                        style: SpanStyle::Primary,
-                        label: Some("failed".to_string()),
-                    }],
-                })
+        ParseErrorReason::EmptyInput => (
+            "Unexpected end of input: expected a JSON value".to_string(),
+            "expected a JSON value".to_string(),
+        ),
+        ParseErrorReason::MissingToken(tok) => (
+            format!("Missing token: \"{tok}\""),
+            format!("missing \"{tok}\""),
+        ),
+        ParseErrorReason::UnexpectedToken(tok) => {
+            let shown = escape_control(tok);
+            (
+                format!("Unexpected token: \"{shown}\""),
+                match suggest_keyword(tok) {
+                    Some(keyword) => {
+                        format!("unexpected \"{shown}\", did you mean `{keyword}`?")
+                    }
+                    None => format!("unexpected \"{shown}\""),
+                },
+            )
+        }
+        ParseErrorReason::InvalidEscape(err) => (
+            format!("Invalid string: {err}"),
+            "invalid escape".to_string(),
+        ),
+        ParseErrorReason::NumberOutOfRange(raw) => (
+            format!("Number out of range: {raw}"),
+            "does not fit in an f64".to_string(),
+        ),
+        ParseErrorReason::NonFiniteNumber(raw) => (
+            format!("Not a JSON number: {raw}"),
+            "not allowed in strict JSON".to_string(),
+        ),
+        ParseErrorReason::DepthLimitExceeded(max) => (
+            format!("Nesting too deep: more than {max} levels of arrays and objects"),
+            "exceeds the depth limit".to_string(),
+        ),
+        ParseErrorReason::StringTooLong(max) => (
+            format!("String too long: more than {max} bytes"),
+            "exceeds the string length limit".to_string(),
+        ),
+        ParseErrorReason::DuplicateKey(key) => (
+            format!("Duplicate key: {key:?}"),
+            "repeats an earlier key".to_string(),
+        ),
+        ParseErrorReason::InvalidUtf8 => ("Invalid UTF-8".to_string(), "not UTF-8".to_string()),
+        ParseErrorReason::FailedNode(_) => {
+            ("Failed to parse node".to_string(), "failed".to_string())
+        }
+    }
+}
+
+/// `text` with its control characters, like a NUL byte, written as escapes,
+/// so that a diagnostic quoting it prints as it reads.
+fn escape_control(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
             } else {
-                for error in errors {
-                    convert_parse_error_to_diagnostics(file_span, error, diagnostics);
-                }
+                c.to_string()
             }
+        })
+        .collect()
+}
+
+/// The keyword a word is most likely a typo of, if it is within two edits of
+/// one.
+fn suggest_keyword(word: &str) -> Option<&'static str> {
+    if !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    ["false", "null", "true"]
+        .into_iter()
+        .map(|keyword| (edit_distance(word, keyword), keyword))
+        .filter(|&(distance, _)| distance <= 2)
+        .min()
+        .map(|(_, keyword)| keyword)
+}
+
+/// The Levenshtein distance between `a` and `b`: how many characters have to be
+/// inserted, removed or replaced to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // The distances from the part of `a` seen so far to each prefix of `b`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != cb))
+                .min(row[j] + 1)
+                .min(above + 1);
+            diagonal = above;
         }
     }
+    row[b.len()]
+}
+
+/// Builds a diagnostic for each error, with the 1-based `line:column` where it
+/// starts appended to the message, counting columns in characters.
+///
+/// `productions` holds what [`explain`] found for each error, if anything, which
+/// is named in the message too, and `contexts` the [`enclosing_span`] of each,
+/// which is underlined as well.
+fn convert_parse_error_to_diagnostics(
+    codemap: &CodeMap,
+    file_span: &codemap::Span,
+    errors: &[&ParseError],
+    productions: &[Option<Production>],
+    contexts: &[Option<(usize, usize)>],
+) -> Vec<Diagnostic> {
+    errors
+        .iter()
+        .zip(productions)
+        .zip(contexts)
+        .map(|((error, production), context)| {
+            let (mut message, label) = describe(error);
+            if let Some(production) = production {
+                message.push_str(&format!(" while parsing {production}"));
+            }
+            let span = file_span.subspan(error.start as u64, error.end as u64);
+            let position = codemap.look_up_pos(span.low()).position;
+            let mut spans = vec![SpanLabel {
+                span,
+                style: SpanStyle::Primary,
+                label: Some(label),
+            }];
+            if let Some((start, end)) = *context {
+                spans.push(SpanLabel {
+                    span: file_span.subspan(start as u64, end as u64),
+                    style: SpanStyle::Secondary,
+                    label: Some("while parsing this".to_string()),
+                });
+            }
+            Diagnostic {
+                level: Level::Error,
+                message: format!("{message} at {}:{}", position.line + 1, position.column + 1),
+                code: Some("S000".to_string()),
+                spans,
+            }
+        })
+        .collect()
+}
+
+/// Renders `errors` in `src` as one miette report, for `--reporter=miette`.
+#[cfg(feature = "miette")]
+fn render_miette(name: &str, src: String, errors: parser::ParseErrors, color: Color) -> String {
+    use miette::{GraphicalReportHandler, GraphicalTheme, NamedSource, Report};
+
+    let theme = match color.config() {
+        ColorConfig::Always => GraphicalTheme::unicode(),
+        _ => GraphicalTheme::unicode_nocolor(),
+    };
+    let report = Report::new(errors).with_source_code(NamedSource::new(name, src));
+    let mut out = String::new();
+    GraphicalReportHandler::new_themed(theme)
+        .render_report(&mut out, report.as_ref())
+        .expect("writing to a `String` cannot fail");
+    out
+}
+
+/// The number literals in `src` that change when read as an `f64`, as found by
+/// [`parser::imprecise_numbers`], looking at each line on its own with
+/// `--ndjson`.
+fn imprecise_numbers(src: &str, ndjson: bool) -> Vec<(usize, usize, f64)> {
+    if !ndjson {
+        return parser::imprecise_numbers(src);
+    }
+    let mut found = vec![];
+    let mut start = 0;
+    for line in src.split_inclusive('\n') {
+        found.extend(
+            parser::imprecise_numbers(line)
+                .into_iter()
+                .map(|(s, e, f)| (start + s, start + e, f)),
+        );
+        start += line.len();
+    }
+    found
+}
+
+/// Builds a warning for each number [`imprecise_numbers`] found, with its
+/// position appended to the message as for errors.
+fn convert_imprecise_numbers_to_diagnostics(
+    codemap: &CodeMap,
+    file_span: &codemap::Span,
+    src: &str,
+    found: &[(usize, usize, f64)],
+) -> Vec<Diagnostic> {
+    found
+        .iter()
+        .map(|&(start, end, f)| {
+            let span = file_span.subspan(start as u64, end as u64);
+            let position = codemap.look_up_pos(span.low()).position;
+            Diagnostic {
+                level: Level::Warning,
+                message: format!(
+                    "Number loses precision: {} is read as {f} at {}:{}",
+                    &src[start..end],
+                    position.line + 1,
+                    position.column + 1
+                ),
+                code: Some("W000".to_string()),
+                spans: vec![SpanLabel {
+                    span,
+                    style: SpanStyle::Primary,
+                    label: Some("not exact as an f64".to_string()),
+                }],
+            }
+        })
+        .collect()
+}
+
+/// Renders each error as a `{"file", "reason", "start", "end", "message"}` JSON
+/// object, with byte offsets into the source, and a `"production"` too where
+/// `productions` has one for it.
+fn convert_parse_errors_to_json(
+    file: &str,
+    errors: &[&ParseError],
+    productions: &[Option<Production>],
+) -> Vec<String> {
+    errors
+        .iter()
+        .zip(productions)
+        .map(|(error, production)| {
+            let mut out = String::from(r#"{"file":""#);
+            write_escaped(&mut out, file).expect("writing to a `String` cannot fail");
+            let (message, _) = describe(error);
+            out.push_str(&format!(
+                r#"","reason":"{}","start":{},"end":{},"message":""#,
+                error.reason.name(),
+                error.start,
+                error.end
+            ));
+            write_escaped(&mut out, &message).expect("writing to a `String` cannot fail");
+            out.push('"');
+            if let Some(production) = production {
+                out.push_str(&format!(
+                    r#","production":"{}""#,
+                    production_name(*production)
+                ));
+            }
+            out.push('}');
+            out
+        })
+        .collect()
 }
 
 fn main() {
-    let file = env::args().nth(1).expect("Expected file argument");
-    let src = fs::read_to_string(&file).expect("Failed to read file");
+    let args = parse_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let mut options = ParseOptions::default();
+    if let Some(depth_limit) = args.depth_limit {
+        options.max_depth = depth_limit;
+    }
+    options.max_string_length = args.max_string_length;
+    // With more than one input, each one's diagnostics get a header naming it.
+    let headers = args.inputs.len() > 1;
+    let mut failed = false;
+    let mut json_diagnostics = vec![];
 
-    match parser::grammar::parse(src.as_str()) {
-        Ok(json) => {
-            #[cfg(debug_assertions)]
-            {
-                println!("{:#?}", json);
+    for input in &args.inputs {
+        let name = input.name();
+        if args.count {
+            let counted = input.lines().and_then(|reader| {
+                count_lines(reader, &options, |line, column, message| {
+                    if !args.quiet {
+                        println!("{name}:{line}:{column}: {message}");
+                    }
+                })
+            });
+            match counted {
+                Ok(count) => {
+                    failed |= count.invalid > 0;
+                    if !args.quiet {
+                        println!("{name}: {} valid, {} invalid", count.valid, count.invalid);
+                    }
+                }
+                Err(err) => {
+                    failed = true;
+                    if !args.quiet {
+                        eprintln!("{name}: failed to read: {err}");
+                    }
+                }
             }
-            #[cfg(not(debug_assertions))]
-            {
-                std::hint::black_box(json);
+            continue;
+        }
+        let src = match input.read(args.max_bytes) {
+            Ok(src) => src,
+            Err(err) => {
+                if !args.quiet {
+                    match err.kind() {
+                        io::ErrorKind::FileTooLarge => {
+                            eprintln!("{name}: refusing to parse: {err}")
+                        }
+                        io::ErrorKind::InvalidData => eprintln!("{name}: {err}"),
+                        _ => eprintln!("{name}: failed to read: {err}"),
+                    }
+                }
+                failed = true;
+                continue;
+            }
+        };
+
+        if let Some(iterations) = args.bench {
+            let median = bench(&src, args.ndjson, &options, iterations);
+            if !args.quiet {
+                let seconds = median.as_secs_f64();
+                eprintln!(
+                    "{name}: median {median:?} over {iterations} parses, {:.2} MB/s, {:.1} parses/s",
+                    src.len() as f64 / 1e6 / seconds,
+                    1.0 / seconds
+                );
             }
         }
-        Err(errs) => {
-            let mut codemap = CodeMap::new();
-            let file_span = codemap.add_file(file, src.to_string());
-            let mut diagnostics = vec![];
-            for error in errs {
-                convert_parse_error_to_diagnostics(&file_span.span, &error, &mut diagnostics);
+
+        match parse_input(&src, args.ndjson, &options) {
+            Ok(values) => {
+                if args.quiet || args.bench.is_some() {
+                    continue;
+                }
+                if headers && args.format == Format::Human {
+                    eprintln!("{name}: ok");
+                }
+                if args.warn_precision && args.format == Format::Human {
+                    let found = imprecise_numbers(&src, args.ndjson);
+                    if !found.is_empty() {
+                        let mut codemap = CodeMap::new();
+                        let file_span = codemap.add_file(name.to_string(), src.clone());
+                        let diagnostics = convert_imprecise_numbers_to_diagnostics(
+                            &codemap,
+                            &file_span.span,
+                            &src,
+                            &found,
+                        );
+                        let mut emitter = Emitter::stderr(args.color.config(), Some(&codemap));
+                        emitter.emit(&diagnostics);
+                    }
+                }
+                if args.format == Format::Json {
+                    continue;
+                }
+                if args.sort_keys {
+                    for json in &values {
+                        println!("{}", parser::canonicalize(json).to_string_pretty(2));
+                    }
+                    continue;
+                }
+                if args.stats {
+                    for json in &values {
+                        let stats = parser::stats(json);
+                        println!(
+                            "{name}: {} objects, {} arrays, {} strings, {} numbers, {} booleans, {} nulls, depth {}",
+                            stats.objects,
+                            stats.arrays,
+                            stats.strings,
+                            stats.numbers,
+                            stats.booleans,
+                            stats.nulls,
+                            stats.max_depth
+                        );
+                    }
+                    continue;
+                }
+                if args.tree {
+                    for json in &values {
+                        print!("{}", parser::outline(json, TREE_VALUE_LEN));
+                    }
+                    continue;
+                }
+                #[cfg(debug_assertions)]
+                for json in values {
+                    println!("{:#?}", json);
+                }
+                #[cfg(not(debug_assertions))]
+                {
+                    std::hint::black_box(values);
+                }
             }
+            Err(errs) => {
+                failed = true;
+                if args.quiet {
+                    continue;
+                }
+                let leaves = distinct_leaf_errors(&errs);
+                let shown = match args.format {
+                    Format::Human => args.max_errors.unwrap_or(usize::MAX).min(leaves.len()),
+                    Format::Json => leaves.len(),
+                };
+                let productions: Vec<_> = leaves[..shown]
+                    .iter()
+                    .map(|error| {
+                        args.explain
+                            .then(|| explain(&src, args.ndjson, error))
+                            .flatten()
+                    })
+                    .collect();
+                match args.format {
+                    Format::Human => {
+                        if headers {
+                            let count = leaves.len();
+                            let plural = if count == 1 { "" } else { "s" };
+                            eprintln!("{name}: {count} error{plural}");
+                        }
+                        #[cfg(feature = "miette")]
+                        if args.reporter == Reporter::Miette {
+                            let errors = parser::ParseErrors(errs);
+                            eprint!("{}", render_miette(name, src, errors, args.color));
+                            continue;
+                        }
+                        let mut codemap = CodeMap::new();
+                        let file_span = codemap.add_file(name.to_string(), src);
+                        let contexts: Vec<_> = leaves[..shown]
+                            .iter()
+                            .map(|leaf| enclosing_span(&errs, leaf))
+                            .collect();
+                        let diagnostics = convert_parse_error_to_diagnostics(
+                            &codemap,
+                            &file_span.span,
+                            &leaves[..shown],
+                            &productions,
+                            &contexts,
+                        );
 
-            let mut emitter = Emitter::stderr(ColorConfig::Always, Some(&codemap));
-            emitter.emit(&diagnostics);
-            std::process::exit(1);
+                        let mut emitter = Emitter::stderr(args.color.config(), Some(&codemap));
+                        emitter.emit(&diagnostics);
+                        let hidden = leaves.len() - shown;
+                        if hidden > 0 {
+                            let plural = if hidden == 1 { "" } else { "s" };
+                            eprintln!("... and {hidden} more error{plural}");
+                        }
+                    }
+                    Format::Json => json_diagnostics.extend(convert_parse_errors_to_json(
+                        name,
+                        &leaves,
+                        &productions,
+                    )),
+                }
+            }
         }
     }
+
+    if args.format == Format::Json && !args.quiet {
+        println!("[{}]", json_diagnostics.join(","));
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(reason: ParseErrorReason, start: usize, end: usize) -> ParseError {
+        ParseError { reason, start, end }
+    }
+
+    fn missing(tok: &str, at: usize) -> ParseError {
+        error(ParseErrorReason::MissingToken(tok.to_string()), at, at)
+    }
+
+    #[test]
+    fn repeated_errors_make_one_diagnostic() {
+        let errors = vec![
+            error(
+                ParseErrorReason::FailedNode(vec![
+                    missing("]", 5),
+                    error(ParseErrorReason::UnexpectedToken("x".to_string()), 1, 2),
+                ]),
+                0,
+                6,
+            ),
+            // The same missing token again, through another parent.
+            error(ParseErrorReason::FailedNode(vec![missing("]", 5)]), 3, 6),
+            // The same span with a different message is kept.
+            missing("}", 5),
+        ];
+        let leaves = distinct_leaf_errors(&errors);
+        let messages: Vec<_> = leaves.iter().map(|e| describe(e).0).collect();
+        assert_eq!(
+            messages,
+            [
+                r#"Missing token: "]""#,
+                r#"Unexpected token: "x""#,
+                r#"Missing token: "}""#
+            ]
+        );
+
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_file("test".to_string(), "[x, [1".to_string());
+        let diagnostics = convert_parse_error_to_diagnostics(
+            &codemap, &file.span, &leaves, &[None; 3], &[None; 3],
+        );
+        assert_eq!(diagnostics.len(), 3);
+    }
+
+    #[test]
+    fn enclosing_span_is_the_parent_failed_node() {
+        let errors = vec![
+            error(
+                ParseErrorReason::FailedNode(vec![
+                    missing("]", 5),
+                    // No wider than the error in it, so it adds nothing.
+                    error(ParseErrorReason::FailedNode(vec![missing("}", 4)]), 4, 4),
+                ]),
+                0,
+                6,
+            ),
+            // Outside any `FailedNode`.
+            missing("]", 7),
+        ];
+        let leaves = distinct_leaf_errors(&errors);
+        let spans: Vec<_> = leaves
+            .iter()
+            .map(|leaf| enclosing_span(&errors, leaf))
+            .collect();
+        assert_eq!(spans, [Some((0, 6)), None, None]);
+    }
 }
@@ -0,0 +1,95 @@
+//! Numbers as [`BigDecimal`], for when `f64` would lose digits.
+//!
+//! Numbers keep the text they were written as, so parsing still only produces
+//! an `f64` or `i64` per number, and the exact value is read from that text on
+//! request.
+
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+
+use crate::parser::{grammar::JsonNumber, BorrowedNumber};
+
+impl JsonNumber {
+    /// The number exactly as written, however many digits it has, or `None`
+    /// for `NaN` and `Infinity`.
+    pub fn as_big_decimal(&self) -> Option<BigDecimal> {
+        big_decimal(self.raw())
+    }
+}
+
+impl BorrowedNumber<'_> {
+    /// The number exactly as written, like [`JsonNumber::as_big_decimal`].
+    pub fn as_big_decimal(&self) -> Option<BigDecimal> {
+        big_decimal(self.raw())
+    }
+}
+
+fn big_decimal(raw: &str) -> Option<BigDecimal> {
+    // Every JSON number literal is also a `BigDecimal` literal; only the
+    // non-finite tokens `ParseOptions::allow_non_finite` lets through are not.
+    BigDecimal::from_str(raw).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use bigdecimal::num_bigint::BigInt;
+
+    use super::*;
+    use crate::parser::{
+        grammar::JsonValue, parse, parse_borrowed, parse_with_options, BorrowedValue, ParseOptions,
+    };
+
+    fn number(value: &JsonValue) -> &JsonNumber {
+        let JsonValue::Number(n) = value else {
+            panic!("expected a number, got {value:?}");
+        };
+        n
+    }
+
+    fn exact(input: &str) -> BigDecimal {
+        number(&parse(input).unwrap()).as_big_decimal().unwrap()
+    }
+
+    #[test]
+    fn big_decimal_is_exact() {
+        let big = "123456789012345678901234567890";
+        assert_eq!(exact(big).to_string(), big);
+        assert_eq!(exact(big), BigDecimal::from(BigInt::from_str(big).unwrap()));
+        // `f64` can't tell it from its neighbour.
+        assert_eq!(
+            parse(big).unwrap(),
+            parse("123456789012345678901234567891").unwrap()
+        );
+        assert_ne!(exact(big), exact("123456789012345678901234567891"));
+
+        assert_eq!(exact("0.1"), BigDecimal::new(1.into(), 1));
+        assert_eq!(exact("0.1").to_string(), "0.1");
+        assert_eq!(exact("-2.50e-3"), BigDecimal::new((-25).into(), 4));
+        assert_eq!(exact("-0"), BigDecimal::from(0));
+
+        let borrowed = parse_borrowed("[3.14159265358979323846264338327950288]").unwrap();
+        let BorrowedValue::Array(values) = borrowed else {
+            panic!("expected an array");
+        };
+        let BorrowedValue::Number(pi) = &values[0] else {
+            panic!("expected a number");
+        };
+        assert_eq!(
+            pi.as_big_decimal().unwrap().to_string(),
+            "3.14159265358979323846264338327950288"
+        );
+    }
+
+    #[test]
+    fn non_finite_is_none() {
+        let options = ParseOptions {
+            allow_non_finite: true,
+            ..ParseOptions::default()
+        };
+        let value = parse_with_options("[NaN, -Infinity]", &options).unwrap();
+        for n in value.values() {
+            assert_eq!(number(n).as_big_decimal(), None);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Conversions between [`JsonValue`] and [`serde_json::Value`].
+
+use serde_json::{Map, Number, Value};
+
+use crate::parser::grammar::{JsonNumber, JsonString, JsonValue, Property};
+
+/// Integers that fit in an `i64` or `u64` stay exact, like they do in serde_json;
+/// everything else goes through `f64`.
+///
+/// If an object repeats a key, the last property with that key wins, as it does
+/// when serde_json parses the same text.
+impl From<JsonValue> for Value {
+    fn from(value: JsonValue) -> Self {
+        match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::True => Value::Bool(true),
+            JsonValue::False => Value::Bool(false),
+            JsonValue::Number(n) => match (n.as_i64(), n.raw().parse::<u64>()) {
+                (Some(i), _) => Value::from(i),
+                (None, Ok(u)) => Value::from(u),
+                (None, Err(_)) => Value::from(n.as_f64()),
+            },
+            JsonValue::Str(s) => Value::String(s.0),
+            JsonValue::Array(_, values, _) => {
+                Value::Array(values.into_iter().map(Value::from).collect())
+            }
+            JsonValue::Object(_, properties, _) => Value::Object(
+                properties
+                    .into_iter()
+                    .map(|p| (p.key().to_owned(), Value::from(p.value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+/// Every [`serde_json::Value`] has a [`JsonValue`] equivalent, so this never fails
+/// and `TryFrom` comes from the blanket impl.
+///
+/// Numbers keep the text serde_json would print for them.
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => JsonValue::Null,
+            Value::Bool(true) => JsonValue::True,
+            Value::Bool(false) => JsonValue::False,
+            Value::Number(n) => JsonValue::Number(number(&n)),
+            Value::String(s) => JsonValue::Str(JsonString(s)),
+            Value::Array(values) => {
+                JsonValue::Array((), values.into_iter().map(JsonValue::from).collect(), ())
+            }
+            Value::Object(map) => JsonValue::Object(
+                (),
+                map.into_iter()
+                    .map(|(k, v)| Property::new(k, JsonValue::from(v)))
+                    .collect(),
+                (),
+            ),
+        }
+    }
+}
+
+fn number(n: &Number) -> JsonNumber {
+    // serde_json only holds finite numbers and prints them as valid JSON.
+    JsonNumber::from_literal(&n.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use corpus::WHITESPACE;
+
+    fn round_trip(input: &str) {
+        let expected: Value = serde_json::from_str(input).unwrap();
+        let parsed = parse(input).unwrap();
+
+        let forward = Value::from(parsed);
+        assert_eq!(forward, expected, "{input}");
+
+        let back = JsonValue::from(forward.clone());
+        assert_eq!(Value::from(back), forward, "{input}");
+    }
+
+    #[test]
+    fn values_round_trip() {
+        round_trip(WHITESPACE);
+        round_trip("null");
+        round_trip("[true, false, -0.5, 1e3, 9007199254740993]");
+        round_trip(r#""\"\\\/\b\f\n\r\t\u0001😐""#);
+        round_trip(r#"{"nested": {"deeper": [[], {}]}}"#);
+    }
+
+    #[test]
+    fn integers_stay_exact() {
+        let value = Value::from(parse("9007199254740993").unwrap());
+        assert_eq!(value.as_i64(), Some(9007199254740993));
+
+        let JsonValue::Number(n) = JsonValue::from(value) else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.as_i64(), Some(9007199254740993));
+
+        let value = Value::from(parse("18446744073709551615").unwrap());
+        assert_eq!(value.as_u64(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_last() {
+        let value = Value::from(parse(r#"{"a": 1, "a": 2}"#).unwrap());
+        assert_eq!(value, serde_json::json!({ "a": 2 }));
+    }
+}
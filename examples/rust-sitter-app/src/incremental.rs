@@ -0,0 +1,141 @@
+//! Parsing a document again after an edit, reusing the parts of tree-sitter's
+//! syntax tree that the edit did not touch.
+//!
+//! Only the syntax tree is incremental. Checking it and extracting a
+//! [`JsonValue`](crate::parser::grammar::JsonValue) still walk all of it, so
+//! this is for tools that keep the tree around, like an editor's outline.
+
+use std::ops::Range;
+
+use rust_sitter::tree_sitter::{InputEdit, Point, Tree};
+
+use crate::parser::{blank_bom, syntax_tree};
+
+/// A change to a document, as tree-sitter needs to hear of it before the
+/// document is parsed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit(InputEdit);
+
+impl Edit {
+    /// The edit that replaces the bytes in `range` of `old_src` with `text`.
+    pub fn replace(old_src: &str, range: Range<usize>, text: &str) -> Self {
+        let start_position = point(&old_src[..range.start], Point::new(0, 0));
+        Edit(InputEdit {
+            start_byte: range.start,
+            old_end_byte: range.end,
+            new_end_byte: range.start + text.len(),
+            start_position,
+            old_end_position: point(&old_src[range.clone()], start_position),
+            new_end_position: point(text, start_position),
+        })
+    }
+}
+
+/// Where `text` ends, in tree-sitter's rows and byte columns, if it starts at
+/// `start`.
+fn point(text: &str, start: Point) -> Point {
+    match text.rfind('\n') {
+        Some(newline) => Point::new(
+            start.row + text.matches('\n').count(),
+            text.len() - newline - 1,
+        ),
+        None => Point::new(start.row, start.column + text.len()),
+    }
+}
+
+/// Parses `input` into tree-sitter's syntax tree, for [`reparse`] to start
+/// from after an edit.
+pub fn parse_tree(input: &str) -> Tree {
+    syntax_tree(&blank_bom(input), None)
+}
+
+/// Parses `new_src`, which is the source of `old_tree` with `edit` made to it,
+/// parsing again only what the edit could have changed.
+///
+/// The tree that comes back is the one [`parse_tree`] would give for `new_src`.
+pub fn reparse(old_tree: &Tree, edit: &Edit, new_src: &str) -> Tree {
+    // Cloning a tree only takes a reference to its nodes, and the edit copies
+    // those it changes, so `old_tree` stays as it was.
+    let mut tree = old_tree.clone();
+    tree.edit(&edit.0);
+    syntax_tree(&blank_bom(new_src), Some(&tree))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Every node's kind and where it is, in document order.
+    fn nodes(tree: &Tree) -> Vec<(&'static str, Range<usize>, Point, Point)> {
+        let mut nodes = vec![];
+        let mut cursor = tree.walk();
+        'walk: loop {
+            let node = cursor.node();
+            nodes.push((
+                node.kind(),
+                node.byte_range(),
+                node.start_position(),
+                node.end_position(),
+            ));
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            while cursor.goto_parent() {
+                if cursor.goto_next_sibling() {
+                    continue 'walk;
+                }
+            }
+            return nodes;
+        }
+    }
+
+    #[test]
+    fn edit_positions() {
+        let Edit(edit) = Edit::replace("[1,\n 22]", 2..6, "\n\n3");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (2, 6, 5)
+        );
+        assert_eq!(edit.start_position, Point::new(0, 2));
+        assert_eq!(edit.old_end_position, Point::new(1, 2));
+        assert_eq!(edit.new_end_position, Point::new(2, 1));
+        let Edit(edit) = Edit::replace("[1,\n 22]", 6..7, "345");
+        assert_eq!(edit.start_position, Point::new(1, 2));
+        assert_eq!(edit.old_end_position, Point::new(1, 3));
+        assert_eq!(edit.new_end_position, Point::new(1, 5));
+    }
+
+    #[test]
+    fn reparse_matches_a_full_parse() {
+        let old_src = "{\n  \"a\": [1, 22, 3],\n  \"b\": {\"c\": null}\n}\n";
+        let at = |pattern: &str| {
+            let start = old_src.find(pattern).unwrap();
+            start..start + pattern.len()
+        };
+        let old_tree = parse_tree(old_src);
+        let old_nodes = nodes(&old_tree);
+        for (range, text) in [
+            // One digit, as in the benchmark.
+            (at("22"), "23"),
+            // A number becomes a string, and spans lines.
+            (at("22"), "\"x\ny\""),
+            // A line goes, so everything after it moves up.
+            (at("\n  \"b\": {\"c\": null}"), ""),
+            (at("\n}"), ",\n  \"d\": [true,\n false]\n}"),
+            (0..0, "\u{feff}"),
+            // An unbalanced quote, which tree-sitter has to recover from.
+            (at("\"b\""), "\"b"),
+        ] {
+            let new_src = format!("{}{text}{}", &old_src[..range.start], &old_src[range.end..]);
+            let tree = reparse(&old_tree, &Edit::replace(old_src, range, text), &new_src);
+            let full = parse_tree(&new_src);
+            assert_eq!(
+                tree.root_node().to_sexp(),
+                full.root_node().to_sexp(),
+                "{new_src:?}"
+            );
+            assert_eq!(nodes(&tree), nodes(&full), "{new_src:?}");
+        }
+        assert_eq!(nodes(&old_tree), old_nodes);
+    }
+}
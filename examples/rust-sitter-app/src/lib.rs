@@ -0,0 +1,17 @@
+pub mod incremental;
+pub mod parser;
+pub mod reader;
+pub mod ser;
+mod value;
+pub mod visit;
+
+#[cfg(feature = "bignum")]
+mod bignum;
+#[cfg(feature = "serde")]
+mod convert;
+#[cfg(feature = "miette")]
+mod miette_impls;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,104 @@
+//! [`Diagnostic`] for [`ParseErrors`], so that miette can render them with the
+//! source underlined.
+//!
+//! The errors don't hold on to the source, so attach it to the report:
+//!
+//! ```
+//! use rust_sitter_app::parser::grammar::JsonValue;
+//!
+//! let errors = "[1, tru]".parse::<JsonValue>().unwrap_err();
+//! let report = miette::Report::new(errors).with_source_code("[1, tru]");
+//! assert_eq!(report.labels().unwrap().count(), 1);
+//! ```
+
+use std::collections::HashSet;
+
+use miette::{Diagnostic, LabeledSpan};
+
+use crate::parser::{leaf_errors, ParseErrorReason, ParseErrors};
+
+/// Every error gets a label, though one reached through more than one failed
+/// node is only labeled once.
+impl Diagnostic for ParseErrors {
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let mut seen = HashSet::new();
+        let labels = leaf_errors(&self.0)
+            .into_iter()
+            .map(|error| {
+                LabeledSpan::new(
+                    Some(label(&error.reason)),
+                    error.start,
+                    error.end - error.start,
+                )
+            })
+            .filter(move |label| {
+                seen.insert((
+                    label.offset(),
+                    label.len(),
+                    label.label().map(str::to_owned),
+                ))
+            });
+        Some(Box::new(labels))
+    }
+}
+
+/// What is wrong at an error's span, in a few words.
+fn label(reason: &ParseErrorReason) -> String {
+    match reason {
+        ParseErrorReason::EmptyInput => "expected a JSON value".to_string(),
+        ParseErrorReason::UnexpectedToken(tok) => format!("unexpected \"{tok}\""),
+        ParseErrorReason::FailedNode(_) => "failed to parse".to_string(),
+        ParseErrorReason::MissingToken(tok) => format!("missing \"{tok}\""),
+        ParseErrorReason::InvalidEscape(err) => err.to_string(),
+        ParseErrorReason::NumberOutOfRange(_) => "does not fit in an f64".to_string(),
+        ParseErrorReason::NonFiniteNumber(_) => "not allowed in strict JSON".to_string(),
+        ParseErrorReason::DepthLimitExceeded(max) => format!("nested more than {max} deep"),
+        ParseErrorReason::StringTooLong(max) => format!("longer than {max} bytes"),
+        ParseErrorReason::DuplicateKey(_) => "repeats an earlier key".to_string(),
+        ParseErrorReason::InvalidUtf8 => "not UTF-8".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use miette::{GraphicalReportHandler, GraphicalTheme, NamedSource, Report};
+
+    use crate::parser::grammar::JsonValue;
+
+    fn render(name: &str, src: &str) -> String {
+        let errors = src.parse::<JsonValue>().unwrap_err();
+        let report = Report::new(errors).with_source_code(NamedSource::new(name, src.to_owned()));
+        let mut out = String::new();
+        GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+            .with_width(80)
+            .render_report(&mut out, report.as_ref())
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn renders_a_report() {
+        assert_eq!(
+            render("list.json", "[1,\n tru]"),
+            r#"  × invalid JSON: UnexpectedToken at bytes 5..8
+   ╭─[list.json:2:2]
+ 1 │ [1,
+ 2 │  tru]
+   ·  ─┬─
+   ·   ╰── unexpected "tru"
+   ╰────
+"#
+        );
+        assert_eq!(
+            render("list.json", r#"[1e400, "\uD800"]"#),
+            r#"  × invalid JSON: NumberOutOfRange at bytes 1..6, and 1 more error
+   ╭─[list.json:1:2]
+ 1 │ [1e400, "\uD800"]
+   ·  ──┬──   ───┬──
+   ·    │        ╰── high surrogate \uD800 is not followed by a low surrogate
+   ·    ╰── does not fit in an f64
+   ╰────
+"#
+        );
+    }
+}
@@ -1,284 +1,3588 @@
-use core::str;
+use core::{cmp::Ordering, fmt, hash, str};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+#[cfg(feature = "arena")]
+use bumpalo::Bump;
+use rust_sitter::tree_sitter;
+
+use self::grammar::{JsonNumber, JsonValue, Property};
+use crate::visit::{self, Visitor};
+
+/// Why [`unescape`] failed, and where.
+///
+/// Each `offset` is the byte offset into the string body of the backslash that
+/// starts the offending escape.
 #[derive(Debug)]
-enum EscapeError {
-    UnfinishedEscapeSequence(String),
-    UnicodeError, // (Vec<u16>),
-}
-
-fn unescape(s: &str) -> Result<String, EscapeError> {
-    let mut t: Vec<u16> = vec![];
-    let mut unicode = false;
-    let mut encoded: u16 = 0;
-    let mut escape = 0; // The number of characters to escape.
-    for i in s.chars() {
-        if escape > 0 {
-            let ch = match i {
-                'b' => 8,
-                'u' => {
-                    escape = 4;
-                    unicode = true;
-                    encoded = 0;
+pub enum EscapeError {
+    /// The body ends partway through an escape.
+    UnfinishedEscapeSequence { offset: usize },
+    /// The character after a backslash, or in a `\u` escape's hex digits, is
+    /// not allowed there.
+    InvalidEscape { offset: usize, ch: char },
+    /// A `\u` escape encodes half of a surrogate pair, `unit`, without the other
+    /// half next to it.
+    UnpairedSurrogate { offset: usize, unit: u16 },
+}
+
+impl EscapeError {
+    /// The byte offset into the string body where the offending escape starts.
+    pub fn offset(&self) -> usize {
+        match *self {
+            EscapeError::UnfinishedEscapeSequence { offset }
+            | EscapeError::InvalidEscape { offset, .. }
+            | EscapeError::UnpairedSurrogate { offset, .. } => offset,
+        }
+    }
+
+    /// The byte range of `body`, the input to [`unescape`], that the error covers.
+    fn span(&self, body: &str) -> (usize, usize) {
+        match *self {
+            EscapeError::UnfinishedEscapeSequence { offset } => (offset, body.len()),
+            // Up to and including the offending character, which follows the
+            // backslash directly or comes after a `u` and some hex digits.
+            EscapeError::InvalidEscape { offset, ch } => {
+                let hex = body[offset + 1..].strip_prefix('u').map_or(0, |rest| {
+                    1 + rest.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(0)
+                });
+                (offset, offset + 1 + hex + ch.len_utf8())
+            }
+            EscapeError::UnpairedSurrogate { offset, .. } => (offset, offset + "\\uXXXX".len()),
+        }
+    }
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapeError::UnfinishedEscapeSequence { .. } => {
+                write!(f, "unfinished escape sequence")
+            }
+            EscapeError::InvalidEscape { ch, .. } => write!(f, "invalid escape character {ch:?}"),
+            EscapeError::UnpairedSurrogate {
+                unit: unit @ 0xD800..=0xDBFF,
+                ..
+            } => {
+                write!(
+                    f,
+                    "high surrogate \\u{unit:04X} is not followed by a low surrogate"
+                )
+            }
+            EscapeError::UnpairedSurrogate { unit, .. } => {
+                write!(
+                    f,
+                    "low surrogate \\u{unit:04X} is not preceded by a high surrogate"
+                )
+            }
+        }
+    }
+}
+
+/// Decodes the escapes in the body of a JSON string literal with the default
+/// [`ParseOptions`], the inverse of [`write_escaped`].
+///
+/// Most strings have no escapes at all, so those are borrowed rather than copied.
+pub fn unescape(s: &str) -> Result<Cow<'_, str>, EscapeError> {
+    unescape_with_options(s, &ParseOptions::default())
+}
+
+/// Decodes the escapes in the body of a JSON string literal, following the
+/// parts of `options` that concern strings.
+pub fn unescape_with_options<'a>(
+    s: &'a str,
+    options: &ParseOptions,
+) -> Result<Cow<'a, str>, EscapeError> {
+    if !s.contains('\\') {
+        return Ok(Cow::Borrowed(s));
+    }
+    let unpaired = |out: &mut String, offset, unit| {
+        if options.lossy_surrogates {
+            out.push(char::REPLACEMENT_CHARACTER);
+            Ok(())
+        } else {
+            Err(EscapeError::UnpairedSurrogate { offset, unit })
+        }
+    };
+    let mut out = String::with_capacity(s.len());
+    // A high surrogate still waiting for its low half, and where its escape started.
+    let mut high_surrogate: Option<(u16, usize)> = None;
+    let mut chars = s.char_indices();
+    while let Some((offset, ch)) = chars.next() {
+        let decoded = match ch {
+            '\\' => match chars.next() {
+                Some((_, 'u')) => {
+                    let mut unit = 0;
+                    for _ in 0..4 {
+                        let (_, digit) = chars
+                            .next()
+                            .ok_or(EscapeError::UnfinishedEscapeSequence { offset })?;
+                        let value = digit
+                            .to_digit(16)
+                            .ok_or(EscapeError::InvalidEscape { offset, ch: digit })?;
+                        unit = unit * 16 + value as u16;
+                    }
+                    match (unit, high_surrogate.take()) {
+                        (0xDC00..=0xDFFF, Some((high, _))) => {
+                            let scalar = 0x10000
+                                + ((u32::from(high) - 0xD800) << 10)
+                                + (u32::from(unit) - 0xDC00);
+                            out.push(char::from_u32(scalar).expect("a surrogate pair is a scalar"));
+                        }
+                        (_, pending) => {
+                            if let Some((high, offset)) = pending {
+                                unpaired(&mut out, offset, high)?;
+                            }
+                            match unit {
+                                0xD800..=0xDBFF => high_surrogate = Some((unit, offset)),
+                                0xDC00..=0xDFFF => unpaired(&mut out, offset, unit)?,
+                                _ => {
+                                    out.push(char::from_u32(unit.into()).expect("not a surrogate"))
+                                }
+                            }
+                        }
+                    }
                     continue;
                 }
-                'r' => '\r' as u16,
-                'n' => '\n' as u16,
-                't' => '\t' as u16,
-                'f' => 12,
-                '\'' => '\'' as u16,
-                '"' => '"' as u16,
-                '\\' => '\\' as u16,
-                '/' => '/' as u16,
-                '0'..'9' if unicode => (i as u16)-('0' as u16),
-                'A'..'F' if unicode => 10+(i as u16)-('A' as u16),
-                _ => panic!("CH >> {i:?}"),
-            };
-            escape -= 1;
-            if !unicode {
-                println!(">>> {ch}");
-                t.push(ch);
+                Some((_, 'b')) => '\x08',
+                Some((_, 'f')) => '\x0C',
+                Some((_, 'n')) => '\n',
+                Some((_, 'r')) => '\r',
+                Some((_, 't')) => '\t',
+                Some((_, escaped @ ('"' | '\\' | '/'))) => escaped,
+                Some((_, escaped)) => {
+                    return Err(EscapeError::InvalidEscape {
+                        offset,
+                        ch: escaped,
+                    })
+                }
+                None => return Err(EscapeError::UnfinishedEscapeSequence { offset }),
+            },
+            _ => ch,
+        };
+        if let Some((unit, offset)) = high_surrogate.take() {
+            unpaired(&mut out, offset, unit)?;
+        }
+        out.push(decoded);
+    }
+    if let Some((unit, offset)) = high_surrogate {
+        unpaired(&mut out, offset, unit)?;
+    }
+    Ok(Cow::Owned(out))
+}
+
+/// Decodes the body of a string literal that `check` has accepted, under
+/// whichever options it was checked with. An unpaired surrogate is the only
+/// failure any options let through, so decoding those lossily covers them all.
+pub(crate) fn unescape_checked(s: &str) -> Result<Cow<'_, str>, EscapeError> {
+    let options = ParseOptions {
+        lossy_surrogates: true,
+        ..ParseOptions::default()
+    };
+    unescape_with_options(s, &options)
+}
+
+/// Choices that change how [`escape_with_options`] writes a string.
+#[derive(Clone, Debug, Default)]
+pub struct EscapeOptions {
+    /// Write characters outside the Basic Multilingual Plane as a surrogate pair
+    /// of `\u` escapes rather than as UTF-8, for consumers that only handle
+    /// UTF-16 code units.
+    pub astral_as_surrogates: bool,
+}
+
+/// Escapes `s` into the body of a JSON string literal with the default
+/// [`EscapeOptions`], so that [`unescape`] gives back `s`.
+pub fn escape(s: &str) -> String {
+    escape_with_options(s, &EscapeOptions::default())
+}
+
+/// Escapes `s` into the body of a JSON string literal, following `options`.
+pub fn escape_with_options(s: &str, options: &EscapeOptions) -> String {
+    let mut out = String::with_capacity(s.len());
+    write_escaped_with_options(&mut out, s, options).expect("writing to a `String` cannot fail");
+    out
+}
+
+/// Writes `s` as the body of a JSON string literal, escaping it so that `unescape`
+/// gives back `s`.
+pub fn write_escaped<W: fmt::Write>(out: &mut W, s: &str) -> fmt::Result {
+    write_escaped_with_options(out, s, &EscapeOptions::default())
+}
+
+fn write_escaped_with_options<W: fmt::Write>(
+    out: &mut W,
+    s: &str,
+    options: &EscapeOptions,
+) -> fmt::Result {
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        let escaped = match ch {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\x08' => "\\b",
+            '\x0C' => "\\f",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\x00'..='\x1F' => {
+                out.write_str(&s[start..i])?;
+                write!(out, "\\u{:04x}", ch as u32)?;
+                start = i + 1;
+                continue;
+            }
+            '\u{10000}'.. if options.astral_as_surrogates => {
+                out.write_str(&s[start..i])?;
+                for unit in ch.encode_utf16(&mut [0; 2]) {
+                    write!(out, "\\u{unit:04x}")?;
+                }
+                start = i + ch.len_utf8();
                 continue;
             }
-            // Handle the byte as hex encoded unicode.
-            // TODO: This isn't correct in cases
-            encoded *= 16;
-            encoded += ch;
-            println!(">> {ch} ({encoded})");
-            if escape == 0 {
-                t.push(encoded);
-                unicode = false;
-            }
-        } else if i == '\\' {
-            escape = 1;
+            _ => continue,
+        };
+        out.write_str(&s[start..i])?;
+        out.write_str(escaped)?;
+        start = i + 1;
+    }
+    out.write_str(&s[start..])
+}
+
+/// The tree-sitter node kind rust-sitter generates for the `JsonString` leaf.
+const STRING_LEAF: &str = "JsonString_0";
+/// The tree-sitter node kind rust-sitter generates for the `JsonNumber` leaf.
+const NUMBER_LEAF: &str = "JsonNumber_literal";
+/// The number tokens that only [`ParseOptions::allow_non_finite`] accepts.
+const NON_FINITE: [&str; 3] = ["NaN", "Infinity", "-Infinity"];
+/// The tree-sitter node kinds rust-sitter generates for the nesting `JsonValue` variants.
+const CONTAINERS: [&str; 2] = ["JsonValue_Array", "JsonValue_Object"];
+/// The tree-sitter node kinds rust-sitter generates for the `[`, `]`, `{` and `}`
+/// tokens.
+const BRACKETS: [&str; 4] = [
+    "JsonValue_Array_0",
+    "JsonValue_Array_2",
+    "JsonValue_Object_0",
+    "JsonValue_Object_2",
+];
+/// The tree-sitter node kind rust-sitter generates for `JsonValue::Object`.
+const OBJECT: &str = CONTAINERS[1];
+/// The tree-sitter node kind rust-sitter generates for the `Comment` extra.
+const COMMENT: &str = "Comment";
+/// The tree-sitter node kind rust-sitter generates for the `Whitespace` extra.
+const WHITESPACE: &str = "Whitespace";
+
+/// Limits that change what [`parse_with_options`] accepts.
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// The deepest nesting of arrays and objects allowed before parsing fails.
+    ///
+    /// Building the tree recurses once per level, so this is what keeps hostile
+    /// input from overflowing the stack.
+    pub max_depth: usize,
+    /// What to do with an object that has the same key more than once.
+    pub on_duplicate_key: OnDuplicateKey,
+    /// Accept a comma after the last element of a non-empty array or object, as
+    /// JSON5 does, instead of rejecting it like strict JSON.
+    pub allow_trailing_commas: bool,
+    /// Accept `//` line comments and `/* */` block comments wherever whitespace
+    /// is allowed, as JSONC does, instead of rejecting them like strict JSON.
+    pub allow_comments: bool,
+    /// Accept `NaN`, `Infinity` and `-Infinity` as numbers, as JavaScript and
+    /// Python's `json` module write them, instead of rejecting them like strict
+    /// JSON.
+    pub allow_non_finite: bool,
+    /// Decode a `\u` escape for half of a surrogate pair, without the other half
+    /// next to it, as U+FFFD REPLACEMENT CHARACTER instead of rejecting it. A
+    /// Rust string cannot hold a lone surrogate, so this is the only way to keep
+    /// the rest of such a string.
+    pub lossy_surrogates: bool,
+    /// Accept any Unicode whitespace between tokens, like vertical tab, form
+    /// feed and no-break space, instead of only the space, tab, carriage return
+    /// and line feed that JSON allows.
+    pub allow_any_whitespace: bool,
+    /// The most bytes a string may take up between its quotes, as written, or
+    /// `None` for no limit. A longer string is reported at its opening quote,
+    /// without decoding it.
+    pub max_string_length: Option<usize>,
+    /// Share one allocation between every object key with the same text, for
+    /// documents that repeat a few keys many times over, like arrays of
+    /// records. Each distinct key is allocated once per parse.
+    pub intern_keys: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            on_duplicate_key: OnDuplicateKey::default(),
+            allow_trailing_commas: false,
+            allow_comments: false,
+            allow_non_finite: false,
+            lossy_surrogates: false,
+            allow_any_whitespace: false,
+            max_string_length: None,
+            intern_keys: false,
+        }
+    }
+}
+
+/// How [`parse_with_options`] treats an object with a repeated key, compared
+/// after unescaping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDuplicateKey {
+    /// Keep every property, in document order.
+    #[default]
+    Keep,
+    /// Keep only the last property with each key, as most JSON parsers do.
+    UseLast,
+    /// Fail, pointing at each repeat of a key.
+    Error,
+}
+
+/// An error that occurred during [`parse`].
+///
+/// Mirrors [`rust_sitter::errors::ParseError`], with extra reasons for the checks
+/// that tree-sitter cannot express in the grammar itself.
+#[derive(Debug)]
+pub struct ParseError {
+    pub reason: ParseErrorReason,
+    /// Inclusive start of the error.
+    pub start: usize,
+    /// Exclusive end of the error.
+    pub end: usize,
+}
+
+impl ParseError {
+    /// Moves this error, and every error nested in it, `by` bytes further along,
+    /// for input that was parsed out of a larger document.
+    pub fn shift(&mut self, by: usize) {
+        self.start += by;
+        self.end += by;
+        if let ParseErrorReason::FailedNode(errors) = &mut self.reason {
+            errors.iter_mut().for_each(|error| error.shift(by));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseErrorReason {
+    /// The input ends before there is any value in it, because it is empty or
+    /// only whitespace.
+    EmptyInput,
+    /// The parser did not expect to see some token.
+    UnexpectedToken(String),
+    /// Tree Sitter failed to parse a specific intermediate node.
+    /// The underlying failures are in the vector.
+    FailedNode(Vec<ParseError>),
+    /// The parser expected a specific token, but it was not found.
+    MissingToken(String),
+    /// A string literal is well-formed but its escapes do not decode.
+    InvalidEscape(EscapeError),
+    /// A number literal is too large to be represented as an `f64`.
+    NumberOutOfRange(String),
+    /// `NaN`, `Infinity` or `-Infinity`, and [`ParseOptions::allow_non_finite`]
+    /// is not set.
+    NonFiniteNumber(String),
+    /// Arrays and objects are nested more deeply than [`ParseOptions::max_depth`].
+    DepthLimitExceeded(usize),
+    /// A string is longer than [`ParseOptions::max_string_length`].
+    StringTooLong(usize),
+    /// An object repeats this key, and [`ParseOptions::on_duplicate_key`] is
+    /// [`OnDuplicateKey::Error`].
+    DuplicateKey(String),
+    /// The input to [`parse_bytes`] is not UTF-8.
+    InvalidUtf8,
+}
+
+impl ParseErrorReason {
+    /// The variant's name, as reported in JSON diagnostics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParseErrorReason::EmptyInput => "EmptyInput",
+            ParseErrorReason::UnexpectedToken(_) => "UnexpectedToken",
+            ParseErrorReason::FailedNode(_) => "FailedNode",
+            ParseErrorReason::MissingToken(_) => "MissingToken",
+            ParseErrorReason::InvalidEscape(_) => "InvalidEscape",
+            ParseErrorReason::NumberOutOfRange(_) => "NumberOutOfRange",
+            ParseErrorReason::NonFiniteNumber(_) => "NonFiniteNumber",
+            ParseErrorReason::DepthLimitExceeded(_) => "DepthLimitExceeded",
+            ParseErrorReason::StringTooLong(_) => "StringTooLong",
+            ParseErrorReason::DuplicateKey(_) => "DuplicateKey",
+            ParseErrorReason::InvalidUtf8 => "InvalidUtf8",
+        }
+    }
+}
+
+impl From<rust_sitter::errors::ParseError> for ParseError {
+    fn from(error: rust_sitter::errors::ParseError) -> Self {
+        use rust_sitter::errors::ParseErrorReason as Reason;
+
+        let reason = match error.reason {
+            Reason::UnexpectedToken(tok) => ParseErrorReason::UnexpectedToken(tok),
+            Reason::FailedNode(errors) => {
+                ParseErrorReason::FailedNode(errors.into_iter().map(Self::from).collect())
+            }
+            Reason::MissingToken(tok) => ParseErrorReason::MissingToken(tok),
+        };
+        Self {
+            reason,
+            start: error.start,
+            end: error.end,
+        }
+    }
+}
+
+/// Every error from parsing one document, as a single [`std::error::Error`], for
+/// callers that want to `?` a parse failure along with their other errors.
+/// [`JsonValue`]'s `FromStr` fails with one, and any `Vec` of errors from the
+/// other parse functions converts into one. The errors themselves are still
+/// there to look through, since this derefs to them.
+///
+/// ```
+/// use rust_sitter_app::parser::{grammar::JsonValue, ParseErrors};
+///
+/// fn first(input: &str) -> Result<JsonValue, ParseErrors> {
+///     let value: JsonValue = input.parse()?;
+///     Ok(value[0].clone())
+/// }
+/// assert_eq!(first("[1, 2]").unwrap(), "1".parse::<JsonValue>().unwrap());
+/// assert_eq!(
+///     first("[1, tru]").unwrap_err().to_string(),
+///     "invalid JSON: UnexpectedToken at bytes 4..7"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    /// The errors, as the parse functions return them.
+    pub fn into_vec(self) -> Vec<ParseError> {
+        self.0
+    }
+}
+
+impl From<Vec<ParseError>> for ParseErrors {
+    fn from(errors: Vec<ParseError>) -> Self {
+        Self(errors)
+    }
+}
+
+impl From<ParseErrors> for Vec<ParseError> {
+    fn from(errors: ParseErrors) -> Self {
+        errors.0
+    }
+}
+
+impl core::ops::Deref for ParseErrors {
+    type Target = [ParseError];
+
+    fn deref(&self) -> &[ParseError] {
+        &self.0
+    }
+}
+
+/// Names the first error and where it is, and counts the rest.
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let leaves = leaf_errors(&self.0);
+        let Some(first) = leaves.first() else {
+            return f.write_str("invalid JSON");
+        };
+        write!(
+            f,
+            "invalid JSON: {} at bytes {}..{}",
+            first.reason.name(),
+            first.start,
+            first.end
+        )?;
+        match leaves.len() - 1 {
+            0 => Ok(()),
+            1 => f.write_str(", and 1 more error"),
+            more => write!(f, ", and {more} more errors"),
+        }
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+/// The errors in `errors` that say what went wrong, looking through the
+/// `FailedNode`s that only group other errors, in the order they occur.
+pub fn leaf_errors(errors: &[ParseError]) -> Vec<&ParseError> {
+    errors
+        .iter()
+        .flat_map(|error| match &error.reason {
+            ParseErrorReason::FailedNode(inner) if !inner.is_empty() => leaf_errors(inner),
+            _ => vec![error],
+        })
+        .collect()
+}
+
+/// Parses with [`parse`], so with the default [`ParseOptions`].
+impl str::FromStr for JsonValue {
+    type Err = ParseErrors;
+
+    fn from_str(input: &str) -> Result<Self, ParseErrors> {
+        parse(input).map_err(ParseErrors)
+    }
+}
+
+/// The part of the grammar that was being parsed where a [`ParseError`]
+/// occurred, as found by [`production`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Production {
+    /// Any value, before it is clear which kind.
+    Value,
+    Array,
+    Object,
+    /// A `"key": value` member of an object.
+    Property,
+    String,
+    Number,
+}
+
+/// Written with an article, to follow "while parsing".
+impl fmt::Display for Production {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Production::Value => "a value",
+            Production::Array => "an array",
+            Production::Object => "an object",
+            Production::Property => "an object property",
+            Production::String => "a string",
+            Production::Number => "a number",
+        })
+    }
+}
+
+/// Names the production that was being parsed where `error` occurred, for
+/// errors that [`parse_with_options`] returned for `input`, or `None` for
+/// errors about the input as a whole.
+///
+/// Tree-sitter's errors only carry a span, so this parses `input` again and
+/// looks at what surrounds the span in the tree. Only the errors themselves
+/// are looked at, not any nested in a [`ParseErrorReason::FailedNode`].
+pub fn production(input: &str, error: &ParseError) -> Option<Production> {
+    match &error.reason {
+        ParseErrorReason::EmptyInput => return Some(Production::Value),
+        ParseErrorReason::InvalidEscape(_) | ParseErrorReason::StringTooLong(_) => {
+            return Some(Production::String)
+        }
+        ParseErrorReason::NumberOutOfRange(_) | ParseErrorReason::NonFiniteNumber(_) => {
+            return Some(Production::Number)
+        }
+        ParseErrorReason::DuplicateKey(_) => return Some(Production::Property),
+        // The error points at the opening bracket.
+        ParseErrorReason::DepthLimitExceeded(_) => {
+            return match input.as_bytes().get(error.start) {
+                Some(b'[') => Some(Production::Array),
+                _ => Some(Production::Object),
+            }
+        }
+        ParseErrorReason::InvalidUtf8 => return None,
+        ParseErrorReason::UnexpectedToken(_)
+        | ParseErrorReason::MissingToken(_)
+        | ParseErrorReason::FailedNode(_) => {}
+    }
+    let tree = syntax_tree(&blank_bom(input), None);
+    let root = tree.root_node();
+    // The node the error was collected from, or failing that, whatever covers
+    // its span, as for the content after the root value.
+    let mut found = None;
+    walk(root, |node, visit| {
+        if visit == Visit::Enter
+            && found.is_none()
+            && (node.is_error() || node.is_missing())
+            && (node.start_byte(), node.end_byte()) == (error.start, error.end)
+        {
+            found = Some(node);
+        }
+    });
+    let node = found
+        .or_else(|| root.descendant_for_byte_range(error.start, error.end))
+        .unwrap_or(root);
+    // A missing token is named after what it belongs to, and anything else is
+    // inside it. An error that tree-sitter could not fit into the tree at all
+    // may still start with a bracket of the array or object it was in.
+    let mut ancestor = Some(node);
+    while let Some(node) = ancestor {
+        let kind = if node.is_error() {
+            let mut cursor = node.walk();
+            let first = node.children(&mut cursor).find(|child| !child.is_extra());
+            first
+                .map(|child| child.kind())
+                .filter(|kind| BRACKETS.contains(kind))
+        } else {
+            Some(node.kind())
+        };
+        if let Some(production) = kind.and_then(production_of_kind) {
+            return Some(production);
+        }
+        ancestor = node.parent();
+    }
+    Some(Production::Value)
+}
+
+/// The production a tree-sitter node kind belongs to, for the kinds rust-sitter
+/// generates from [`grammar`].
+fn production_of_kind(kind: &str) -> Option<Production> {
+    let kind = kind.strip_prefix("JsonValue_").unwrap_or(kind);
+    match kind.split('_').next()? {
+        "Array" => Some(Production::Array),
+        "Object" => Some(Production::Object),
+        "Property" => Some(Production::Property),
+        "JsonString" | "Str" => Some(Production::String),
+        "JsonNumber" | "Number" => Some(Production::Number),
+        "JsonValue" | "Null" | "True" | "False" | "source" => Some(Production::Value),
+        _ => None,
+    }
+}
+
+/// Parses a JSON document with the default [`ParseOptions`].
+///
+/// Unlike the generated `grammar::parse`, this validates every string and number
+/// literal before building the tree, so a bad escape or an out of range number is
+/// reported as a [`ParseError`] instead of being silently replaced.
+pub fn parse(input: &str) -> Result<JsonValue, Vec<ParseError>> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Parses a JSON document from bytes, like [`parse`], without the caller having
+/// to check that they are UTF-8 first.
+///
+/// The input is still validated as UTF-8 in a pass of its own before parsing,
+/// and the first byte that is not part of a character is reported as
+/// [`ParseErrorReason::InvalidUtf8`].
+pub fn parse_bytes(input: &[u8]) -> Result<JsonValue, Vec<ParseError>> {
+    match str::from_utf8(input) {
+        Ok(input) => parse(input),
+        Err(err) => {
+            let bad = err.valid_up_to();
+            Err(vec![ParseError {
+                reason: ParseErrorReason::InvalidUtf8,
+                start: bad,
+                end: bad + err.error_len().unwrap_or(input.len() - bad),
+            }])
+        }
+    }
+}
+
+/// Parses a JSON document, enforcing the limits in `options`.
+pub fn parse_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<JsonValue, Vec<ParseError>> {
+    let tree = check(input, options)?;
+    let mut value = if options.intern_keys {
+        let mut interner = Interner {
+            input,
+            keys: HashSet::new(),
+        };
+        interner.value(tree.root_node())
+    } else {
+        <JsonValue as rust_sitter::Extract<_>>::extract(
+            Some(tree.root_node()),
+            input.as_bytes(),
+            0,
+            None,
+        )
+    };
+    if options.on_duplicate_key == OnDuplicateKey::UseLast {
+        keep_last_duplicates(&mut value);
+    }
+    Ok(value)
+}
+
+/// Parses a JSON document with the default [`ParseOptions`], keeping as much of
+/// it as parsed cleanly when there are errors, for editors that want to work
+/// with a document that is still being typed.
+///
+/// The value leaves out every element and property with an error anywhere in
+/// it, except that arrays and objects keep whatever is intact inside them. It
+/// is `None` when not even the outermost value could be made out. The errors are
+/// the same as [`parse`] returns.
+pub fn parse_partial(input: &str) -> (Option<JsonValue>, Vec<ParseError>) {
+    let errors = match parse(input) {
+        Ok(value) => return (Some(value), vec![]),
+        Err(errors) => errors,
+    };
+    let text = blank_bom(input);
+    let tree = syntax_tree(&text, None);
+    let mut spans = vec![];
+    error_spans(&errors, &mut spans);
+    let partial = Partial {
+        input: &text,
+        spans,
+        max_depth: ParseOptions::default().max_depth,
+    };
+    // The root stands for the outermost `JsonValue`, unless tree-sitter could
+    // not make one out at all.
+    let root = tree.root_node();
+    let value = if root.is_error() {
+        None
+    } else {
+        partial.value(root, 0)
+    };
+    (value, errors)
+}
+
+/// The spans of `errors` and of every error nested in them.
+fn error_spans(errors: &[ParseError], spans: &mut Vec<(usize, usize)>) {
+    for error in errors {
+        spans.push((error.start, error.end));
+        if let ParseErrorReason::FailedNode(errors) = &error.reason {
+            error_spans(errors, spans);
+        }
+    }
+}
+
+/// Extracts what it can from a tree with errors in it, for [`parse_partial`].
+struct Partial<'a> {
+    input: &'a str,
+    /// Where the errors are, including those `check` found in well-formed
+    /// syntax.
+    spans: Vec<(usize, usize)>,
+    max_depth: usize,
+}
+
+impl Partial<'_> {
+    /// Whether `node` is as [`check`] would have it, with no errors in it.
+    ///
+    /// An empty error, like a missing token, is in the node if the byte it is
+    /// before is, so one at the very end of an element belongs after it.
+    fn is_clean(&self, node: tree_sitter::Node<'_>) -> bool {
+        !node.has_error()
+            && !self.spans.iter().any(|&(start, end)| {
+                start < node.end_byte() && node.start_byte() < end.max(start + 1)
+            })
+    }
+
+    /// The value a `JsonValue` node holds, without whatever has errors in it.
+    ///
+    /// This recurses once per level of nesting, but only as deep as the depth
+    /// limit: a clean node is no deeper than that, since `check` would otherwise
+    /// have reported it, so the generated extraction takes over from there.
+    fn value(&self, node: tree_sitter::Node<'_>, depth: usize) -> Option<JsonValue> {
+        if self.is_clean(node) {
+            return Some(<JsonValue as rust_sitter::Extract<_>>::extract(
+                Some(node),
+                self.input.as_bytes(),
+                0,
+                None,
+            ));
+        }
+        if depth == self.max_depth {
+            return None;
+        }
+        let mut cursor = node.walk();
+        let inner = node
+            .named_children(&mut cursor)
+            .find(|child| !child.is_extra())?;
+        match inner.kind() {
+            "JsonValue_Array" => {
+                let elements = match inner.child_by_field_name("1") {
+                    Some(elements) => elements
+                        .children_by_field_name("JsonValue_Array_1_vec_element", &mut cursor)
+                        .filter_map(|element| self.value(element, depth + 1))
+                        .collect(),
+                    None => vec![],
+                };
+                Some(JsonValue::Array((), elements, ()))
+            }
+            "JsonValue_Object" => {
+                let properties = match inner.child_by_field_name("1") {
+                    Some(properties) => properties
+                        .children_by_field_name("JsonValue_Object_1_vec_element", &mut cursor)
+                        .filter_map(|property| self.property(property, depth + 1))
+                        .collect(),
+                    None => vec![],
+                };
+                Some(JsonValue::Object((), properties, ()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The property a `Property` node holds, if its key is intact and its value
+    /// has anything left.
+    fn property(&self, node: tree_sitter::Node<'_>, depth: usize) -> Option<Property> {
+        let name = key_leaf(node).filter(|name| self.is_clean(*name))?;
+        let key = unescape(&self.input[name.start_byte() + 1..name.end_byte() - 1]).ok()?;
+        let value = self.value(node.child_by_field_name("value")?, depth)?;
+        Some(Property::new(key, value))
+    }
+}
+
+/// Extracts a tree that [`check`] has accepted, as the generated extraction
+/// does, but with the keys shared for [`ParseOptions::intern_keys`].
+struct Interner<'a> {
+    input: &'a str,
+    /// One of each key seen so far.
+    keys: HashSet<Arc<str>>,
+}
+
+impl Interner<'_> {
+    /// The value a `JsonValue` node holds.
+    ///
+    /// This recurses once per level of nesting, which `check` has already limited.
+    /// Scalars are left to the generated extraction.
+    fn value(&mut self, node: tree_sitter::Node<'_>) -> JsonValue {
+        let mut cursor = node.walk();
+        let inner = node
+            .named_children(&mut cursor)
+            .find(|child| !child.is_extra());
+        let Some(inner) = inner.filter(|inner| CONTAINERS.contains(&inner.kind())) else {
+            return <JsonValue as rust_sitter::Extract<_>>::extract(
+                Some(node),
+                self.input.as_bytes(),
+                0,
+                None,
+            );
+        };
+        let children = inner.child_by_field_name("1");
+        if inner.kind() == OBJECT {
+            let properties = match children {
+                Some(properties) => properties
+                    .children_by_field_name("JsonValue_Object_1_vec_element", &mut cursor)
+                    .map(|property| self.property(property))
+                    .collect(),
+                None => vec![],
+            };
+            JsonValue::Object((), properties, ())
         } else {
-            t.push(i as u16);
+            let elements = match children {
+                Some(elements) => elements
+                    .children_by_field_name("JsonValue_Array_1_vec_element", &mut cursor)
+                    .map(|element| self.value(element))
+                    .collect(),
+                None => vec![],
+            };
+            JsonValue::Array((), elements, ())
+        }
+    }
+
+    /// The property a `Property` node holds, with its key decoded straight from
+    /// the input, so that only a key not seen before is allocated.
+    fn property(&mut self, node: tree_sitter::Node<'_>) -> Property {
+        let name = key_leaf(node).expect("`check` accepted every property");
+        // The same decoding, and the same default, as the `JsonString` leaf.
+        let key = unescape_checked(&self.input[name.start_byte() + 1..name.end_byte() - 1])
+            .unwrap_or_default();
+        let key = match self.keys.get(&*key) {
+            Some(key) => Arc::clone(key),
+            None => {
+                let key = Arc::<str>::from(key);
+                self.keys.insert(Arc::clone(&key));
+                key
+            }
+        };
+        let value = node
+            .child_by_field_name("value")
+            .expect("`check` accepted every property");
+        Property::new(key, self.value(value))
+    }
+}
+
+/// Drops every property that a later property with the same key overrides.
+///
+/// This recurses once per level of nesting, which `check` has already limited.
+fn keep_last_duplicates(value: &mut JsonValue) {
+    match value {
+        JsonValue::Array(_, values, _) => values.iter_mut().for_each(keep_last_duplicates),
+        JsonValue::Object(_, properties, _) => {
+            let last: HashMap<&str, usize> = properties
+                .iter()
+                .enumerate()
+                .map(|(i, property)| (property.key(), i))
+                .collect();
+            if last.len() < properties.len() {
+                let keep: Vec<bool> = properties
+                    .iter()
+                    .enumerate()
+                    .map(|(i, property)| last[property.key()] == i)
+                    .collect();
+                let mut keep = keep.into_iter();
+                properties.retain(|_| keep.next().expect("one flag per property"));
+            }
+            properties
+                .iter_mut()
+                .for_each(|property| keep_last_duplicates(&mut property.value));
+        }
+        _ => {}
+    }
+}
+
+/// How many values of each kind a [`JsonValue`] holds, itself included.
+///
+/// Object keys are not counted as strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    /// How deeply arrays and objects nest, counted as [`ParseOptions::max_depth`]
+    /// counts it: 0 for a lone scalar, 1 for an array of scalars.
+    pub max_depth: usize,
+}
+
+/// Counts the values in `value`.
+///
+/// This does not recurse, so it copes with however deep a tree parsing allowed.
+pub fn stats(value: &JsonValue) -> Stats {
+    let mut counter = StatsVisitor::default();
+    visit::walk(value, &mut counter);
+    counter.stats
+}
+
+#[derive(Default)]
+struct StatsVisitor {
+    stats: Stats,
+    /// How many arrays and objects the walk is inside of.
+    depth: usize,
+}
+
+impl StatsVisitor {
+    fn enter(&mut self) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+}
+
+impl Visitor for StatsVisitor {
+    fn on_null(&mut self) {
+        self.stats.nulls += 1;
+    }
+    fn on_bool(&mut self, _value: bool) {
+        self.stats.booleans += 1;
+    }
+    fn on_number(&mut self, _value: &JsonNumber) {
+        self.stats.numbers += 1;
+    }
+    fn on_string(&mut self, _value: &str) {
+        self.stats.strings += 1;
+    }
+    fn on_array_start(&mut self, _len: usize) {
+        self.stats.arrays += 1;
+        self.enter();
+    }
+    fn on_array_end(&mut self) {
+        self.depth -= 1;
+    }
+    fn on_object_start(&mut self, _len: usize) {
+        self.stats.objects += 1;
+        self.enter();
+    }
+    fn on_object_end(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+/// An indented outline of `value`'s structure, one line per value: each
+/// property's key, the length of every array and object, and scalars with
+/// string and number text cut off after `max_len` characters.
+///
+/// ```
+/// use rust_sitter_app::parser::{outline, parse};
+///
+/// let value = parse(r#"{"a": [1, "a long string"], "b": null}"#).unwrap();
+/// assert_eq!(
+///     outline(&value, 6),
+///     "object (2)\n  a: array (2)\n    1\n    \"a long…\"\n  b: null\n"
+/// );
+/// ```
+pub fn outline(value: &JsonValue, max_len: usize) -> String {
+    let mut outliner = Outliner {
+        out: String::new(),
+        depth: 0,
+        keyed: false,
+        max_len,
+    };
+    visit::walk(value, &mut outliner);
+    outliner.out
+}
+
+struct Outliner {
+    out: String,
+    depth: usize,
+    /// Whether the current line already holds the indent and a property's key.
+    keyed: bool,
+    max_len: usize,
+}
+
+impl Outliner {
+    fn line(&mut self, args: fmt::Arguments<'_>) {
+        use fmt::Write;
+        if !std::mem::take(&mut self.keyed) {
+            self.indent();
+        }
+        let _ = writeln!(self.out, "{args}");
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    /// `text` cut off after `max_len` characters, with an ellipsis if it was.
+    fn truncate<'t>(&self, text: &'t str) -> (&'t str, &'static str) {
+        match text.char_indices().nth(self.max_len) {
+            Some((end, _)) => (&text[..end], "…"),
+            None => (text, ""),
+        }
+    }
+}
+
+impl Visitor for Outliner {
+    fn on_null(&mut self) {
+        self.line(format_args!("null"));
+    }
+    fn on_bool(&mut self, value: bool) {
+        self.line(format_args!("{value}"));
+    }
+    fn on_number(&mut self, value: &JsonNumber) {
+        let (raw, more) = self.truncate(value.raw());
+        self.line(format_args!("{raw}{more}"));
+    }
+    fn on_string(&mut self, value: &str) {
+        let (text, more) = self.truncate(value);
+        self.line(format_args!("\"{}{more}\"", escape(text)));
+    }
+    fn on_array_start(&mut self, len: usize) {
+        self.line(format_args!("array ({len})"));
+        self.depth += 1;
+    }
+    fn on_array_end(&mut self) {
+        self.depth -= 1;
+    }
+    fn on_object_start(&mut self, len: usize) {
+        self.line(format_args!("object ({len})"));
+        self.depth += 1;
+    }
+    fn on_object_end(&mut self) {
+        self.depth -= 1;
+    }
+    fn on_key(&mut self, key: &str) {
+        self.indent();
+        self.out.push_str(&escape(key));
+        self.out.push_str(": ");
+        self.keyed = true;
+    }
+}
+
+/// A copy of `value` in canonical form, with the properties of every object
+/// sorted by key, so that documents that only differ in property order compare
+/// equal and print the same.
+///
+/// Where an object repeats a key, only the last of its values is kept, as
+/// [`OnDuplicateKey::UseLast`] does, since that is the one a reader of the
+/// object would see.
+pub fn canonicalize(value: &JsonValue) -> JsonValue {
+    let mut value = value.clone();
+    keep_last_duplicates(&mut value);
+    sort_keys(&mut value);
+    value
+}
+
+/// Sorts the properties of every object in `value` by key, comparing the keys
+/// by code point.
+///
+/// This recurses once per level of nesting, which `check` has already limited.
+fn sort_keys(value: &mut JsonValue) {
+    match value {
+        JsonValue::Array(_, values, _) => values.iter_mut().for_each(sort_keys),
+        JsonValue::Object(_, properties, _) => {
+            properties.sort_by(|a, b| a.key().cmp(b.key()));
+            properties
+                .iter_mut()
+                .for_each(|property| sort_keys(&mut property.value));
+        }
+        _ => {}
+    }
+}
+
+/// The span of every number literal in `input` that does not survive a round
+/// trip through `f64`, such as `9007199254740993` or `0.1000000000000000000001`,
+/// along with the value it is read as, for warning about numbers that silently
+/// change.
+///
+/// A literal survives if the shortest text that reads back as the same `f64`
+/// denotes the same decimal value, so `1.0` and `1e0` do however they are
+/// written, and so does `0.1`, although no `f64` is exactly a tenth.
+/// Non-finite and out of range numbers are left to [`parse`] to reject. Only
+/// meant for input that parses; anything in a part of the tree that failed is
+/// skipped.
+pub fn imprecise_numbers(input: &str) -> Vec<(usize, usize, f64)> {
+    let tree = syntax_tree(&blank_bom(input), None);
+    let mut found = vec![];
+    walk(tree.root_node(), |node, visit| {
+        if visit == Visit::Enter && node.kind() == NUMBER_LEAF {
+            let text = &input[node.start_byte()..node.end_byte()];
+            if let Ok(f) = text.parse::<f64>() {
+                if f.is_finite() && decimal(text) != decimal(&format!("{f:e}")) {
+                    found.push((node.start_byte(), node.end_byte(), f));
+                }
+            }
+        }
+    });
+    found
+}
+
+/// The decimal number `literal` denotes, as whether it is negative, its
+/// significant digits and the power of ten they are scaled by, so that any two
+/// ways of writing one number come out the same. Zero has no digits and no
+/// sign. `None` when the exponent does not even fit in an `i64`.
+fn decimal(literal: &str) -> Option<(bool, String, i64)> {
+    let (negative, literal) = match literal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, literal),
+    };
+    let (mantissa, exponent) = literal.split_once(['e', 'E']).unwrap_or((literal, "0"));
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int}{frac}");
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    let exponent = exponent.parse::<i64>().ok()?;
+    let shift = (digits.len() - significant.len()) as i64 - frac.len() as i64;
+    Some((
+        negative,
+        significant.to_string(),
+        exponent.checked_add(shift)?,
+    ))
+}
+
+/// Parses a JSON document into a [`BorrowedValue`] with the default
+/// [`ParseOptions`], accepting exactly what [`parse`] does.
+///
+/// Strings without escapes borrow from `input` rather than being copied, which
+/// saves an allocation for almost every string in a typical document.
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>, Vec<ParseError>> {
+    let tree = check(input, &ParseOptions::default())?;
+    Ok(BorrowedValue::extract(tree.root_node(), input))
+}
+
+/// Parses a JSON document into an [`ArenaValue`] allocated in `arena`, with the
+/// default [`ParseOptions`], accepting exactly what [`parse`] does.
+///
+/// Arrays, objects and strings all live in `arena` instead of each getting a
+/// heap allocation of their own, and are freed together when it is reset.
+#[cfg(feature = "arena")]
+pub fn parse_in_arena<'a>(
+    arena: &'a Bump,
+    input: &str,
+) -> Result<&'a ArenaValue<'a>, Vec<ParseError>> {
+    let tree = check(input, &ParseOptions::default())?;
+    Ok(arena.alloc(ArenaValue::extract(tree.root_node(), input, arena)))
+}
+
+/// `input` with a leading byte order mark turned into spaces.
+///
+/// A byte order mark is only allowed at the very start, where it is skipped
+/// like whitespace. Blanking it out rather than slicing it off keeps every
+/// offset in the tree pointing into `input`, and the only nodes it covers are
+/// whitespace, which extraction never looks at.
+pub(crate) fn blank_bom(input: &str) -> Cow<'_, str> {
+    match input.strip_prefix('\u{FEFF}') {
+        // As many spaces as the mark has bytes.
+        Some(rest) => Cow::Owned(format!("   {rest}")),
+        None => Cow::Borrowed(input),
+    }
+}
+
+/// Parses `input` with the generated grammar, errors and all, reusing whatever
+/// an edit left untouched of `old_tree`, if there is one.
+pub(crate) fn syntax_tree(input: &str, old_tree: Option<&tree_sitter::Tree>) -> tree_sitter::Tree {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&grammar::language())
+        .expect("generated grammar matches the tree-sitter runtime");
+    parser
+        .parse(input, old_tree)
+        .expect("parsing without a timeout always produces a tree")
+}
+
+/// Parses `input` and runs every check that [`parse_with_options`] promises, so
+/// the tree that comes back can be extracted without anything failing.
+fn check(input: &str, options: &ParseOptions) -> Result<tree_sitter::Tree, Vec<ParseError>> {
+    let text = blank_bom(input);
+    let input = &*text;
+    let source = input.as_bytes();
+    // Otherwise tree-sitter reports this as a failed node with nothing in it.
+    // `trim` strips the same whitespace as the `Whitespace` extra, and the part
+    // of it that JSON doesn't allow is as unexpected here as anywhere else.
+    if input.trim().is_empty() {
+        let error = match other_whitespace(input).filter(|_| !options.allow_any_whitespace) {
+            Some((start, end)) => ParseError {
+                reason: ParseErrorReason::UnexpectedToken(input[start..end].to_string()),
+                start,
+                end,
+            },
+            None => ParseError {
+                reason: ParseErrorReason::EmptyInput,
+                start: 0,
+                end: 0,
+            },
+        };
+        return Err(vec![error]);
+    }
+    let tree = syntax_tree(input, None);
+    let root = tree.root_node();
+    let syntax_ok = !root.has_error();
+    // Tree-sitter recovers from anything after the root value by marking the
+    // first value as the error, so that case is spotted and reported here.
+    if !syntax_ok {
+        if let Some(error) = trailing_content(input, options) {
+            return Err(vec![error]);
+        }
+    }
+
+    let mut errors = vec![];
+    let mut depth = 0;
+    walk(root, |node, visit| {
+        if CONTAINERS.contains(&node.kind()) {
+            match visit {
+                Visit::Enter => depth += 1,
+                Visit::Leave => depth -= 1,
+            }
+            // Only the outermost container past the limit is worth reporting.
+            if visit == Visit::Enter && depth == options.max_depth + 1 {
+                errors.push(ParseError {
+                    reason: ParseErrorReason::DepthLimitExceeded(options.max_depth),
+                    start: node.start_byte(),
+                    end: node.start_byte() + 1,
+                });
+            }
+            if visit == Visit::Enter && syntax_ok {
+                check_closing_token(node, input, options, &mut errors);
+            }
+            if visit == Visit::Enter
+                && syntax_ok
+                && node.kind() == OBJECT
+                && options.on_duplicate_key == OnDuplicateKey::Error
+            {
+                check_duplicate_keys(node, input, options, &mut errors);
+            }
+            return;
+        }
+        if visit == Visit::Leave || !syntax_ok {
+            return;
+        }
+        let text = &input[node.start_byte()..node.end_byte()];
+        match node.kind() {
+            COMMENT if !options.allow_comments => errors.push(ParseError {
+                reason: ParseErrorReason::UnexpectedToken(text[..2].to_string()),
+                start: node.start_byte(),
+                end: node.end_byte(),
+            }),
+            WHITESPACE if !options.allow_any_whitespace && !is_json_whitespace(text) => errors
+                .push(ParseError {
+                    reason: ParseErrorReason::UnexpectedToken(text.to_string()),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                }),
+            STRING_LEAF => {
+                let body = &text[1..text.len() - 1];
+                if let Some(max) = options.max_string_length.filter(|&max| body.len() > max) {
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::StringTooLong(max),
+                        start: node.start_byte(),
+                        end: node.start_byte() + 1,
+                    });
+                } else if let Err(err) = unescape_with_options(body, options) {
+                    // Point at the escape itself rather than the whole string.
+                    let (start, end) = err.span(body);
+                    let body_start = node.start_byte() + 1;
+                    errors.push(ParseError {
+                        reason: ParseErrorReason::InvalidEscape(err),
+                        start: body_start + start,
+                        end: body_start + end,
+                    });
+                }
+            }
+            NUMBER_LEAF if NON_FINITE.contains(&text) && !options.allow_non_finite => {
+                errors.push(ParseError {
+                    reason: ParseErrorReason::NonFiniteNumber(text.to_string()),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                })
+            }
+            NUMBER_LEAF if !NON_FINITE.contains(&text) && !Num::from_literal(text).is_finite() => {
+                errors.push(ParseError {
+                    reason: ParseErrorReason::NumberOutOfRange(text.to_string()),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                })
+            }
+            _ => {}
         }
+    });
+
+    // Collecting syntax errors recurses too, so it has to wait until the depth is
+    // known to be safe.
+    let too_deep = errors
+        .iter()
+        .any(|e| matches!(e.reason, ParseErrorReason::DepthLimitExceeded(_)));
+    if !syntax_ok && !too_deep {
+        let mut syntax_errors = vec![];
+        rust_sitter::errors::collect_parsing_errors(&root, source, &mut syntax_errors);
+        let mut syntax_errors = syntax_errors.into_iter().map(ParseError::from).collect();
+        narrow_bad_escapes(&mut syntax_errors, input, options);
+        return Err(syntax_errors);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(tree)
+}
+
+/// Points each error for a string literal that the lexer gave up on at the bad
+/// escape in it, as an [`ParseErrorReason::InvalidEscape`].
+///
+/// The string leaf only matches valid escapes, so tree-sitter reports a string
+/// with any other as an unexpected token running from its opening quote, and
+/// often reports more unexpected tokens starting in the rest of the literal.
+/// Those follow from the same escape, so they are dropped.
+fn narrow_bad_escapes(errors: &mut Vec<ParseError>, input: &str, options: &ParseOptions) {
+    narrow_bad_escapes_after(errors, input, options, &mut None);
+}
+
+/// [`narrow_bad_escapes`], given the literal behind the last error narrowed
+/// down, which may have been in another `FailedNode`.
+fn narrow_bad_escapes_after(
+    errors: &mut Vec<ParseError>,
+    input: &str,
+    options: &ParseOptions,
+    literal: &mut Option<(usize, usize)>,
+) {
+    errors.retain_mut(|error| {
+        if literal.is_some_and(|(start, end)| (start..end).contains(&error.start)) {
+            return false;
+        }
+        match &mut error.reason {
+            ParseErrorReason::FailedNode(errors) => {
+                narrow_bad_escapes_after(errors, input, options, literal)
+            }
+            ParseErrorReason::UnexpectedToken(_) if input[error.start..].starts_with('"') => {
+                let start = error.start;
+                let (body, end) = string_literal_body(&input[start + 1..]);
+                if let Err(err) = unescape_with_options(body, options) {
+                    let (escape_start, escape_end) = err.span(body);
+                    *error = ParseError {
+                        reason: ParseErrorReason::InvalidEscape(err),
+                        start: start + 1 + escape_start,
+                        end: start + 1 + escape_end,
+                    };
+                    *literal = Some((start, start + 1 + end));
+                }
+            }
+            _ => {}
+        }
+        true
+    });
+}
+
+/// The body of the string literal that `rest`, which follows its opening quote,
+/// starts with, and how far past the closing quote it ends.
+///
+/// The body runs up to the first quote that is not escaped, or failing that up
+/// to the end of the line.
+fn string_literal_body(rest: &str) -> (&str, usize) {
+    let mut escaped = false;
+    for (i, byte) in rest.bytes().enumerate() {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return (&rest[..i], i + 1),
+            b'\n' => return (&rest[..i], i),
+            _ => {}
+        }
+    }
+    (rest, rest.len())
+}
+
+/// Reports what the closing token of `container` takes in besides its bracket
+/// and whitespace and `options` do not allow: a comma, which also needs an
+/// element before it, comments, and whitespace other than JSON's.
+fn check_closing_token(
+    container: tree_sitter::Node<'_>,
+    input: &str,
+    options: &ParseOptions,
+    errors: &mut Vec<ParseError>,
+) {
+    let Some(close) = container.child_by_field_name("2") else {
+        return;
+    };
+    let text = &input[close.byte_range()];
+    let empty = container.child_by_field_name("1").is_none();
+    if text.starts_with(',') && (!options.allow_trailing_commas || empty) {
+        errors.push(ParseError {
+            reason: ParseErrorReason::UnexpectedToken(",".to_string()),
+            start: close.start_byte(),
+            end: close.start_byte() + 1,
+        });
+    }
+    // Anything else that can start with `/` is inside a comment.
+    if let Some(slash) = text.find('/').filter(|_| !options.allow_comments) {
+        let start = close.start_byte() + slash;
+        errors.push(ParseError {
+            reason: ParseErrorReason::UnexpectedToken(input[start..start + 2].to_string()),
+            start,
+            end: start + 2,
+        });
+    }
+    if let Some((start, end)) = other_whitespace(text).filter(|_| !options.allow_any_whitespace) {
+        errors.push(ParseError {
+            reason: ParseErrorReason::UnexpectedToken(text[start..end].to_string()),
+            start: close.start_byte() + start,
+            end: close.start_byte() + end,
+        });
+    }
+}
+
+fn is_json_whitespace(text: &str) -> bool {
+    text.bytes()
+        .all(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+}
+
+/// The span of the first run of whitespace in `text` that JSON does not allow,
+/// skipping over comments.
+fn other_whitespace(text: &str) -> Option<(usize, usize)> {
+    let is_other = |c: char| c.is_whitespace() && !matches!(c, ' ' | '\t' | '\r' | '\n');
+    let mut i = 0;
+    while let Some(ch) = text[i..].chars().next() {
+        let rest = &text[i..];
+        if let Some(comment) = rest.strip_prefix("//") {
+            i += 2 + comment.find('\n').unwrap_or(comment.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            i += 2 + comment.find("*/").map_or(comment.len(), |len| len + 2);
+        } else if is_other(ch) {
+            return Some((i, i + rest.find(|c| !is_other(c)).unwrap_or(rest.len())));
+        } else {
+            i += ch.len_utf8();
+        }
+    }
+    None
+}
+
+/// The `JsonString` leaf holding the key of a `Property` node, quotes and all.
+fn key_leaf(property: tree_sitter::Node<'_>) -> Option<tree_sitter::Node<'_>> {
+    property
+        .child_by_field_name("name")
+        .and_then(|name| name.child_by_field_name("0"))
+}
+
+/// Reports each key of `object` that an earlier property already used.
+fn check_duplicate_keys(
+    object: tree_sitter::Node<'_>,
+    input: &str,
+    options: &ParseOptions,
+    errors: &mut Vec<ParseError>,
+) {
+    let Some(properties) = object.child_by_field_name("1") else {
+        return;
+    };
+    let mut seen = HashSet::new();
+    let mut cursor = properties.walk();
+    for property in properties.children_by_field_name("JsonValue_Object_1_vec_element", &mut cursor)
+    {
+        let Some(name) = key_leaf(property) else {
+            continue;
+        };
+        // A key that fails to unescape is reported on its own already.
+        let Ok(key) =
+            unescape_with_options(&input[name.start_byte() + 1..name.end_byte() - 1], options)
+        else {
+            continue;
+        };
+        if seen.contains(&key) {
+            errors.push(ParseError {
+                reason: ParseErrorReason::DuplicateKey(key.into_owned()),
+                start: name.start_byte(),
+                end: name.end_byte(),
+            });
+        } else {
+            seen.insert(key);
+        }
+    }
+}
+
+/// An error for the first token after the first value in `input`, if that
+/// value is valid on its own and more than whitespace and comments follow it.
+fn trailing_content(input: &str, options: &ParseOptions) -> Option<ParseError> {
+    let end = first_value_end(input)?;
+    let start = skip_blank(input, end);
+    if start == input.len() || check(&input[..end], options).is_err() {
+        return None;
+    }
+    let token_end = token_end(input, start);
+    Some(ParseError {
+        reason: ParseErrorReason::UnexpectedToken(input[start..token_end].to_string()),
+        start,
+        end: token_end,
+    })
+}
+
+/// The offset just past the first value in `input`, going by brackets and
+/// quotes alone, or `None` if it never closes.
+///
+/// Brackets inside comments throw this off, but then the value it finds does
+/// not parse and [`trailing_content`] gives up.
+fn first_value_end(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let start = skip_blank(input, 0);
+    if !matches!(bytes.get(start)?, b'[' | b'{' | b'"') {
+        return Some(token_end(input, start));
+    }
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+        } else {
+            match byte {
+                b'"' => in_string = true,
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth == 0 && !in_string {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// The offset of the first thing after `from` that is not whitespace or a
+/// comment.
+fn skip_blank(input: &str, mut from: usize) -> usize {
+    loop {
+        let rest = &input[from..];
+        let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+        from += rest.len() - trimmed.len();
+        if let Some(comment) = trimmed.strip_prefix("//") {
+            from += 2 + comment.find('\n').unwrap_or(comment.len());
+        } else if let Some(comment) = trimmed.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(len) => from += 2 + len + 2,
+                None => return from,
+            }
+        } else {
+            return from;
+        }
+    }
+}
+
+/// The end of the token at `start`: one punctuation character, or everything
+/// up to the next whitespace or punctuation.
+fn token_end(input: &str, start: usize) -> usize {
+    let is_boundary = |byte: &u8| b"[]{},:\"/".contains(byte) || byte.is_ascii_whitespace();
+    let bytes = &input.as_bytes()[start..];
+    match bytes.first() {
+        Some(byte) if is_boundary(byte) => start + 1,
+        _ => start + bytes.iter().position(is_boundary).unwrap_or(bytes.len()),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    Enter,
+    Leave,
+}
+
+/// Visits `root` and all of its descendants in document order without recursing,
+/// calling `f` once on the way into each node and once on the way out.
+fn walk<'t>(root: tree_sitter::Node<'t>, mut f: impl FnMut(tree_sitter::Node<'t>, Visit)) {
+    let mut cursor = root.walk();
+    loop {
+        f(cursor.node(), Visit::Enter);
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            f(cursor.node(), Visit::Leave);
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
+/// The value of a [`grammar::JsonNumber`], keeping integer literals exact.
+#[derive(Clone, Copy, Debug)]
+enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+/// A number literal exactly as it appeared in the input, along with its value.
+#[derive(Clone, Debug)]
+struct Literal {
+    raw: String,
+    value: Num,
+}
+
+impl Num {
+    fn from_literal(v: &str) -> Self {
+        // `-0` is an integer literal, but only a float can keep its sign.
+        let integral = !v.contains(['.', 'e', 'E']) && v != "-0";
+        match v.parse() {
+            Ok(i) if integral => Num::Int(i),
+            // The pattern only matches text `f64::from_str` accepts, so this never
+            // falls back; `parse` separately rejects literals that overflow.
+            _ => Num::Float(v.parse().unwrap_or(f64::INFINITY)),
+        }
+    }
+
+    fn is_finite(self) -> bool {
+        match self {
+            Num::Int(_) => true,
+            Num::Float(f) => f.is_finite(),
+        }
+    }
+
+    fn as_i64(self) -> Option<i64> {
+        match self {
+            Num::Int(i) => Some(i),
+            Num::Float(_) => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Num::Int(i) => i as f64,
+            Num::Float(f) => f,
+        }
+    }
+
+    /// The integer this number equals, if any, so that `1` and `1.0` can be
+    /// hashed and ordered alike.
+    fn as_exact_i64(self) -> Option<i64> {
+        match self {
+            Num::Int(i) => Some(i),
+            // Saturating casts make this `i64::MAX` for 2^63, which `eq` agrees
+            // with.
+            Num::Float(f) if f as i64 as f64 == f => Some(f as i64),
+            Num::Float(_) => None,
+        }
+    }
+}
+
+/// Integers and floats compare by value, so `1 == 1.0`, but an integer only
+/// equals a float that converts back to exactly the same integer.
+///
+/// Unlike `f64`, NaN equals itself, so that this is a real equivalence and the
+/// `Eq` impls built on it hold. Parsing never produces NaN, but a number built
+/// some other way could hold one.
+impl PartialEq for Num {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (Num::Int(a), Num::Int(b)) => a == b,
+            (Num::Float(a), Num::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Num::Int(i), Num::Float(f)) | (Num::Float(f), Num::Int(i)) => {
+                f == i as f64 && f as i64 == i
+            }
+        }
+    }
+}
+impl Eq for Num {}
+
+/// Hashes whole numbers as integers, so that values `eq` says are equal hash
+/// alike: `1` like `1.0`, and `-0.0` like `0`. Every NaN hashes the same.
+impl hash::Hash for Num {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        match (self.as_exact_i64(), *self) {
+            (Some(i), _) => (0u8, i).hash(state),
+            (None, Num::Float(f)) if f.is_nan() => (1u8, f64::NAN.to_bits()).hash(state),
+            (None, n) => (1u8, n.as_f64().to_bits()).hash(state),
+        }
+    }
+}
+
+/// Orders by value, comparing integers exactly. NaN sorts after every other
+/// number.
+impl Ord for Num {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.as_exact_i64(), other.as_exact_i64()) {
+            return a.cmp(&b);
+        }
+        // At least one side is a fraction, out of `i64`'s range or not finite,
+        // so rounding the other to an `f64` cannot make them tie.
+        let (a, b) = (self.as_f64(), other.as_f64());
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a.partial_cmp(&b).expect("neither side is NaN"),
+        }
+    }
+}
+
+impl PartialOrd for Num {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The error from converting a number that is not a whole number in `i64`'s
+/// range to an `i64`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotAnI64;
+
+impl fmt::Display for NotAnI64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("number is not a whole number that fits in an i64")
+    }
+}
+
+impl Literal {
+    fn from_literal(v: &str) -> Self {
+        Self {
+            raw: v.to_string(),
+            value: Num::from_literal(v),
+        }
+    }
+}
+
+/// A JSON value whose strings borrow from the input they were parsed from,
+/// as returned by [`parse_borrowed`].
+///
+/// Strings are only copied when they contain escapes, since the decoded text
+/// then differs from the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BorrowedValue<'a> {
+    Null,
+    True,
+    False,
+    Number(BorrowedNumber<'a>),
+    Str(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    /// Members in document order, duplicates included.
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+/// The value of a number literal, along with the literal itself.
+#[derive(Clone, Copy, Debug)]
+pub struct BorrowedNumber<'a> {
+    raw: &'a str,
+    value: Num,
+}
+
+impl<'a> BorrowedNumber<'a> {
+    /// The number as an `i64`, if it was written as an integer that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.value.as_i64()
+    }
+
+    /// The number as an `f64`, rounding integers that `f64` cannot represent exactly.
+    pub fn as_f64(&self) -> f64 {
+        self.value.as_f64()
+    }
+
+    /// The number exactly as it was written in the input.
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+}
+
+/// Compares like [`grammar::JsonNumber`] does, by value and not by raw text.
+impl PartialEq for BorrowedNumber<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl Eq for BorrowedNumber<'_> {}
+
+impl<'a> BorrowedValue<'a> {
+    /// Builds the value `node` spans from a tree that has passed [`check`].
+    ///
+    /// This recurses once per level of nesting, like the generated extraction,
+    /// which is safe because `check` has already enforced the depth limit.
+    fn extract(node: tree_sitter::Node<'_>, input: &'a str) -> Self {
+        let mut cursor = node.walk();
+        match node.kind() {
+            "JsonValue_Null" => BorrowedValue::Null,
+            "JsonValue_True" => BorrowedValue::True,
+            "JsonValue_False" => BorrowedValue::False,
+            NUMBER_LEAF => {
+                let raw = &input[node.byte_range()];
+                BorrowedValue::Number(BorrowedNumber {
+                    raw,
+                    value: Num::from_literal(raw),
+                })
+            }
+            STRING_LEAF => BorrowedValue::Str(borrowed_string(node, input)),
+            "JsonValue_Array" => BorrowedValue::Array(match node.child_by_field_name("1") {
+                Some(elements) => elements
+                    .children_by_field_name("JsonValue_Array_1_vec_element", &mut cursor)
+                    .map(|element| Self::extract(element, input))
+                    .collect(),
+                None => vec![],
+            }),
+            "JsonValue_Object" => BorrowedValue::Object(match node.child_by_field_name("1") {
+                Some(properties) => properties
+                    .children_by_field_name("JsonValue_Object_1_vec_element", &mut cursor)
+                    .map(|property| {
+                        let name = property
+                            .child_by_field_name("name")
+                            .and_then(|name| name.child_by_field_name("0"))
+                            .expect("a checked property has a name");
+                        let value = property
+                            .child_by_field_name("value")
+                            .expect("a checked property has a value");
+                        (borrowed_string(name, input), Self::extract(value, input))
+                    })
+                    .collect(),
+                None => vec![],
+            }),
+            // The root and the nodes that only wrap a single value, like
+            // `JsonValue` itself and the `Number` and `Str` variants.
+            _ => {
+                let inner = node
+                    .named_children(&mut cursor)
+                    .find(|child| !child.is_extra())
+                    .expect("a checked tree has a value wherever one is expected");
+                Self::extract(inner, input)
+            }
+        }
+    }
+}
+
+/// The contents of a checked string literal, borrowed unless it has escapes.
+fn borrowed_string<'a>(leaf: tree_sitter::Node<'_>, input: &'a str) -> Cow<'a, str> {
+    unescape_checked(&input[leaf.start_byte() + 1..leaf.end_byte() - 1])
+        .expect("`check` rejects strings that fail to unescape")
+}
+
+/// A JSON value allocated in a [`Bump`] arena, as returned by
+/// [`parse_in_arena`].
+///
+/// Strings and number literals are copied into the arena too, so the value
+/// does not borrow from the input.
+#[cfg(feature = "arena")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArenaValue<'a> {
+    Null,
+    True,
+    False,
+    Number(BorrowedNumber<'a>),
+    Str(&'a str),
+    Array(&'a [ArenaValue<'a>]),
+    /// Members in document order, duplicates included.
+    Object(&'a [(&'a str, ArenaValue<'a>)]),
+}
+
+#[cfg(feature = "arena")]
+impl<'a> ArenaValue<'a> {
+    /// Builds the value `node` spans from a tree that has passed [`check`],
+    /// recursing like [`BorrowedValue::extract`] does.
+    fn extract(node: tree_sitter::Node<'_>, input: &str, arena: &'a Bump) -> Self {
+        let mut cursor = node.walk();
+        match node.kind() {
+            "JsonValue_Null" => ArenaValue::Null,
+            "JsonValue_True" => ArenaValue::True,
+            "JsonValue_False" => ArenaValue::False,
+            NUMBER_LEAF => {
+                let raw = &input[node.byte_range()];
+                ArenaValue::Number(BorrowedNumber {
+                    raw: arena.alloc_str(raw),
+                    value: Num::from_literal(raw),
+                })
+            }
+            STRING_LEAF => ArenaValue::Str(arena.alloc_str(&borrowed_string(node, input))),
+            "JsonValue_Array" => {
+                let mut values = bumpalo::collections::Vec::new_in(arena);
+                if let Some(elements) = node.child_by_field_name("1") {
+                    values.extend(
+                        elements
+                            .children_by_field_name("JsonValue_Array_1_vec_element", &mut cursor)
+                            .map(|element| Self::extract(element, input, arena)),
+                    );
+                }
+                ArenaValue::Array(values.into_bump_slice())
+            }
+            "JsonValue_Object" => {
+                let mut members = bumpalo::collections::Vec::new_in(arena);
+                if let Some(properties) = node.child_by_field_name("1") {
+                    members.extend(
+                        properties
+                            .children_by_field_name("JsonValue_Object_1_vec_element", &mut cursor)
+                            .map(|property| {
+                                let name = property
+                                    .child_by_field_name("name")
+                                    .and_then(|name| name.child_by_field_name("0"))
+                                    .expect("a checked property has a name");
+                                let value = property
+                                    .child_by_field_name("value")
+                                    .expect("a checked property has a value");
+                                let key: &str = arena.alloc_str(&borrowed_string(name, input));
+                                (key, Self::extract(value, input, arena))
+                            }),
+                    );
+                }
+                ArenaValue::Object(members.into_bump_slice())
+            }
+            // The root and the nodes that only wrap a single value.
+            _ => {
+                let inner = node
+                    .named_children(&mut cursor)
+                    .find(|child| !child.is_extra())
+                    .expect("a checked tree has a value wherever one is expected");
+                Self::extract(inner, input, arena)
+            }
+        }
+    }
+}
+
+#[rust_sitter::grammar("parser")]
+pub mod grammar {
+    use core::cmp::Ordering;
+    use core::hash::{Hash, Hasher};
+    use std::sync::Arc;
+
+    use super::Literal;
+    #[cfg(test)]
+    use super::Num;
+
+    /// Values are ordered by type first, `null` then `false` and `true`, numbers,
+    /// strings, arrays and objects, then within a type by value. Arrays compare
+    /// element by element, and objects property by property in document order.
+    #[rust_sitter::language]
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    pub enum JsonValue {
+        #[rust_sitter::leaf(text = "null")]
+        Null,
+        #[rust_sitter::leaf(text = "true")]
+        True,
+        #[rust_sitter::leaf(text = "false")]
+        False,
+        Number(JsonNumber),
+        Str(JsonString),
+        Array(
+            #[rust_sitter::leaf(text = "[")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<JsonValue>,
+            // A trailing comma belongs to the closing token, since an optional
+            // comma after the elements would conflict with the delimiter, along
+            // with any whitespace and comments after it. `parse` only lets the
+            // comma or comments through if `ParseOptions` allows them. Spelling
+            // out the newline that ends a line comment here keeps tree-sitter
+            // able to recover from a missing bracket at the end of the input.
+            #[rust_sitter::leaf(
+                pattern = r",?([ \t\r\n\x0B\x0C\u0085\u00A0\u1680\u2000-\u200A\u2028\u2029\u202F\u205F\u3000]|//[^\n]*\n|/\*([^*]|\*+[^*/])*\*+/)*\]"
+            )]
+            (),
+        ),
+        Object(
+            #[rust_sitter::leaf(text = "{")] (),
+            #[rust_sitter::delimited(
+                #[rust_sitter::leaf(text = ",")]
+                ()
+            )]
+            Vec<Property>,
+            #[rust_sitter::leaf(
+                pattern = r",?([ \t\r\n\x0B\x0C\u0085\u00A0\u1680\u2000-\u200A\u2028\u2029\u202F\u205F\u3000]|//[^\n]*\n|/\*([^*]|\*+[^*/])*\*+/)*\}"
+            )]
+            (),
+        ),
+    }
+
+    /// A string, unescaped. Values own theirs, while a `Property` holds its key
+    /// as a `JsonString<Arc<str>>`, so that equal keys can share one allocation
+    /// under `ParseOptions::intern_keys`.
+    #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+    pub struct JsonString<Text = String>(
+        // `parse` rejects strings that fail to unescape before extracting, so the
+        // default is only observable through the raw `grammar::parse`.
+        #[rust_sitter::leaf(pattern = r#""([^"\\\x00-\x1f]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*""#, transform = |v| crate::parser::unescape_checked(&v[1..v.len()-1]).map(std::borrow::Cow::into_owned).unwrap_or_default())]
+        pub Text,
+    );
+
+    // The generated extraction of `JsonString` isn't generic, so `Text` there
+    // names this rather than the parameter: it extracts a `JsonString<String>`.
+    type Text = String;
+
+    // Keys are the same symbol as string values in the grammar, so that both
+    // lex as the one token, and extract the same way before being shared.
+    impl rust_sitter::Extract<JsonString<Arc<str>>> for JsonString<Arc<str>> {
+        type LeafFn = ();
+
+        fn extract(
+            node: Option<rust_sitter::tree_sitter::Node>,
+            source: &[u8],
+            last_idx: usize,
+            _leaf_fn: Option<&()>,
+        ) -> Self {
+            let JsonString(text) = JsonString::<String>::extract(node, source, last_idx, None);
+            JsonString(text.into())
+        }
+    }
+
+    /// One `"key": value` member of an object. Properties order by key, then by
+    /// value.
+    ///
+    /// ```
+    /// use rust_sitter_app::parser::parse;
+    ///
+    /// let value = parse(r#"{"a": 1, "b": [true]}"#).unwrap();
+    /// for property in value.as_object().unwrap() {
+    ///     println!("{} = {}", property.key(), property.value());
+    /// }
+    /// let keys: Vec<_> = value.as_object().unwrap().iter().map(|p| p.key()).collect();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// ```
+    #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+    pub struct Property {
+        pub(crate) name: JsonString<Arc<str>>,
+        #[rust_sitter::leaf(text = ":")]
+        sep: (),
+        pub(crate) value: JsonValue,
+    }
+    impl Property {
+        /// The property's key, unescaped.
+        pub fn key(&self) -> &str {
+            &self.name.0
+        }
+
+        /// The property's value.
+        pub fn value(&self) -> &JsonValue {
+            &self.value
+        }
+
+        /// A property with `key`, given unescaped, and `value`.
+        pub fn new<S: Into<Arc<str>>>(key: S, value: JsonValue) -> Self {
+            Self {
+                name: JsonString(key.into()),
+                sep: (),
+                value,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct JsonNumber {
+        // The non-finite tokens belong to the same pattern so that they end up
+        // as numbers, but `parse` rejects them unless they are allowed.
+        #[rust_sitter::leaf(pattern = "-?(0|[1-9]\\d*)(\\.\\d+)?([eE][+-]?\\d+)?|NaN|-?Infinity", transform = crate::parser::Literal::from_literal)]
+        literal: Literal,
+    }
+    impl JsonNumber {
+        #[cfg(test)]
+        pub fn new(value: f64) -> Self {
+            Self {
+                literal: Literal {
+                    // `Display` writes large floats as integer literals, which
+                    // denote a different number once past 2^53; `Debug` always
+                    // writes a float literal that reads back as `value`.
+                    raw: format!("{value:?}"),
+                    value: Num::Float(value),
+                },
+            }
+        }
+
+        /// Builds a number from text that the `JsonNumber` pattern matches.
+        #[cfg(feature = "serde")]
+        pub(crate) fn from_literal(raw: &str) -> Self {
+            Self {
+                literal: Literal::from_literal(raw),
+            }
+        }
+
+        /// The number as an `i64`, if it was written as an integer that fits.
+        pub fn as_i64(&self) -> Option<i64> {
+            self.literal.value.as_i64()
+        }
+
+        /// The number as an `f64`, rounding integers that `f64` cannot represent exactly.
+        pub fn as_f64(&self) -> f64 {
+            self.literal.value.as_f64()
+        }
+
+        /// The number's value, the same as [`JsonNumber::as_f64`].
+        pub fn value(&self) -> f64 {
+            self.as_f64()
+        }
+
+        /// The number exactly as it was written in the input.
+        pub fn raw(&self) -> &str {
+            &self.literal.raw
+        }
+    }
+
+    impl From<JsonNumber> for f64 {
+        fn from(n: JsonNumber) -> f64 {
+            n.as_f64()
+        }
+    }
+
+    /// Succeeds for any whole number in range, however it was written, so `1.0`
+    /// and `1e2` convert as well as `1`.
+    impl TryFrom<JsonNumber> for i64 {
+        type Error = super::NotAnI64;
+
+        fn try_from(n: JsonNumber) -> Result<i64, super::NotAnI64> {
+            if let Some(i) = n.as_i64() {
+                return Ok(i);
+            }
+            let f = n.as_f64();
+            // `i64::MIN` is a power of two, so it and its negation are exact.
+            if f.fract() == 0.0 && f >= i64::MIN as f64 && f < -(i64::MIN as f64) {
+                Ok(f as i64)
+            } else {
+                Err(super::NotAnI64)
+            }
+        }
+    }
+
+    /// Integers and floats compare by value, so `1 == 1.0`, but an integer only
+    /// equals a float that converts back to exactly the same integer. The raw text
+    /// is not compared.
+    impl PartialEq for JsonNumber {
+        fn eq(&self, other: &Self) -> bool {
+            self.literal.value == other.literal.value
+        }
+    }
+    impl Eq for JsonNumber {}
+
+    /// Consistent with `eq`, so `1` and `1.0` hash alike.
+    impl Hash for JsonNumber {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.literal.value.hash(state);
+        }
+    }
+
+    /// Numbers order by value, with integers compared exactly.
+    impl Ord for JsonNumber {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.literal.value.cmp(&other.literal.value)
+        }
+    }
+
+    impl PartialOrd for JsonNumber {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for JsonValue {
+        fn cmp(&self, other: &Self) -> Ordering {
+            fn rank(value: &JsonValue) -> u8 {
+                match value {
+                    JsonValue::Null => 0,
+                    JsonValue::False => 1,
+                    JsonValue::True => 2,
+                    JsonValue::Number(_) => 3,
+                    JsonValue::Str(_) => 4,
+                    JsonValue::Array(..) => 5,
+                    JsonValue::Object(..) => 6,
+                }
+            }
+            match (self, other) {
+                (JsonValue::Number(a), JsonValue::Number(b)) => a.cmp(b),
+                (JsonValue::Str(a), JsonValue::Str(b)) => a.cmp(b),
+                (JsonValue::Array(_, a, _), JsonValue::Array(_, b, _)) => a.cmp(b),
+                (JsonValue::Object(_, a, _), JsonValue::Object(_, b, _)) => a.cmp(b),
+                _ => rank(self).cmp(&rank(other)),
+            }
+        }
+    }
+
+    impl PartialOrd for JsonValue {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    /// One character of the whitespace JSON allows between tokens, or a run of
+    /// the rest of Unicode's, which `parse` rejects unless
+    /// `ParseOptions::allow_any_whitespace` is set. The two share an extra
+    /// because tree-sitter recovers from a missing closing bracket less well
+    /// with a second one.
+    #[rust_sitter::extra]
+    #[allow(dead_code)] // only matched by tree-sitter, never extracted
+    struct Whitespace {
+        #[rust_sitter::leaf(
+            pattern = r"[ \t\r\n]|[\x0B\x0C\u0085\u00A0\u1680\u2000-\u200A\u2028\u2029\u202F\u205F\u3000]+"
+        )]
+        _whitespace: (),
+    }
+
+    /// Always part of the grammar, but `parse` rejects comments unless
+    /// `ParseOptions::allow_comments` is set.
+    #[rust_sitter::extra]
+    #[allow(dead_code)] // only matched by tree-sitter, never extracted
+    struct Comment {
+        #[rust_sitter::leaf(pattern = r"//[^\n]*|/\*([^*]|\*+[^*/])*\*+/")]
+        _comment: (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::grammar::{
+        JsonNumber, JsonString, JsonValue, JsonValue::False, JsonValue::Null, JsonValue::True,
+        Property,
+    };
+    #[allow(clippy::useless_attribute)]
+    #[allow(dead_code)] // its dead for benches
+    use super::*;
+
+    fn jobject(v: Vec<Property>) -> JsonValue {
+        JsonValue::Object((), v, ())
+    }
+
+    fn jarray(v: Vec<JsonValue>) -> JsonValue {
+        JsonValue::Array((), v, ())
+    }
+
+    fn jnum(f: f64) -> JsonValue {
+        JsonValue::Number(JsonNumber::new(f))
+    }
+
+    #[allow(clippy::useless_attribute)]
+    #[allow(dead_code)] // its dead for benches
+    type Error = Vec<ParseError>;
+
+    fn same_value(value: &JsonValue, expected: &corpus::Value) -> bool {
+        use corpus::Value;
+        match (value, expected) {
+            (Null, Value::Null) => true,
+            (True, Value::Bool(true)) | (False, Value::Bool(false)) => true,
+            (JsonValue::Number(n), Value::Number(f)) => n.as_f64() == *f,
+            (JsonValue::Str(JsonString(s)), Value::String(e)) => s == e,
+            (JsonValue::Array(_, items, _), Value::Array(e)) => {
+                items.len() == e.len() && items.iter().zip(*e).all(|(v, e)| same_value(v, e))
+            }
+            (JsonValue::Object(_, props, _), Value::Object(e)) => {
+                props.len() == e.len()
+                    && props
+                        .iter()
+                        .zip(*e)
+                        .all(|(p, (k, v))| p.key() == *k && same_value(&p.value, v))
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, same_value);
+    }
+
+    #[test]
+    fn canonical_cases_bytes() {
+        corpus::check_canonical_cases(|input| parse_bytes(input.as_bytes()), same_value);
+    }
+
+    #[test]
+    fn parse_bytes_invalid_utf8() -> Result<(), Error> {
+        assert_eq!(
+            parse_bytes("{\"é\": [\"\u{1F610}\", 1]}".as_bytes())?,
+            parse("{\"é\": [\"\u{1F610}\", 1]}")?
+        );
+
+        for (input, start, end) in [
+            // A stray continuation byte inside a string, with ASCII all around.
+            (&b"{\"a\": \"x\xBFy\", \"b\": [1, 2]}"[..], 8, 9),
+            // Right after a valid non-ASCII character.
+            (b"[\"\xC3\xA9\xFF\"]", 4, 5),
+            // A sequence cut short by the closing quote.
+            (b"[\"\xE2\x82\"]", 2, 4),
+            // An overlong encoding of `/`.
+            (b"\"\xC0\xAF\"", 1, 2),
+            // Outside a string.
+            (b"[1, \xFF]", 4, 5),
+        ] {
+            let errs = parse_bytes(input).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input:?}");
+            assert!(
+                matches!(errs[0].reason, ParseErrorReason::InvalidUtf8),
+                "{input:?}: {errs:?}"
+            );
+            assert_eq!((errs[0].start, errs[0].end), (start, end), "{input:?}");
+        }
+
+        // Valid UTF-8 that is not valid JSON is an ordinary syntax error.
+        let errs = parse_bytes("[é]".as_bytes()).unwrap_err();
+        assert!(!matches!(errs[0].reason, ParseErrorReason::InvalidUtf8));
+        Ok(())
+    }
+
+    fn same_borrowed_value(value: &BorrowedValue<'_>, expected: &corpus::Value) -> bool {
+        use corpus::Value;
+        match (value, expected) {
+            (BorrowedValue::Null, Value::Null) => true,
+            (BorrowedValue::True, Value::Bool(true))
+            | (BorrowedValue::False, Value::Bool(false)) => true,
+            (BorrowedValue::Number(n), Value::Number(f)) => n.as_f64() == *f,
+            (BorrowedValue::Str(s), Value::String(e)) => s == e,
+            (BorrowedValue::Array(items), Value::Array(e)) => {
+                items.len() == e.len()
+                    && items.iter().zip(*e).all(|(v, e)| same_borrowed_value(v, e))
+            }
+            (BorrowedValue::Object(props), Value::Object(e)) => {
+                props.len() == e.len()
+                    && props
+                        .iter()
+                        .zip(*e)
+                        .all(|((k, v), (ek, ev))| k == ek && same_borrowed_value(v, ev))
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn canonical_cases_borrowed() {
+        corpus::check_canonical_cases(parse_borrowed, same_borrowed_value);
+    }
+
+    #[cfg(feature = "arena")]
+    fn same_arena_value(value: &ArenaValue<'_>, expected: &corpus::Value) -> bool {
+        use corpus::Value;
+        match (value, expected) {
+            (ArenaValue::Null, Value::Null) => true,
+            (ArenaValue::True, Value::Bool(true)) | (ArenaValue::False, Value::Bool(false)) => true,
+            (ArenaValue::Number(n), Value::Number(f)) => n.as_f64() == *f,
+            (ArenaValue::Str(s), Value::String(e)) => s == e,
+            (ArenaValue::Array(items), Value::Array(e)) => {
+                items.len() == e.len() && items.iter().zip(*e).all(|(v, e)| same_arena_value(v, e))
+            }
+            (ArenaValue::Object(props), Value::Object(e)) => {
+                props.len() == e.len()
+                    && props
+                        .iter()
+                        .zip(*e)
+                        .all(|((k, v), (ek, ev))| k == ek && same_arena_value(v, ev))
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn canonical_cases_arena() {
+        let arena = Bump::new();
+        corpus::check_canonical_cases(
+            |input| parse_in_arena(&arena, input),
+            |value, expected| same_arena_value(value, expected),
+        );
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn parse_in_arena_reuses_the_arena() -> Result<(), Error> {
+        let mut arena = Bump::new();
+        let input = String::from(r#"{"a": [1.50, "x\ty"], "b": null}"#);
+        let value = parse_in_arena(&arena, &input)?;
+        // Nothing borrows from `input`, so it can go first.
+        drop(input);
+        let ArenaValue::Object([("a", ArenaValue::Array([ArenaValue::Number(n), s])), ("b", b)]) =
+            value
+        else {
+            panic!("unexpected shape: {value:?}");
+        };
+        assert_eq!(n.raw(), "1.50");
+        assert_eq!(*s, ArenaValue::Str("x\ty"));
+        assert_eq!(*b, ArenaValue::Null);
+
+        let used = arena.allocated_bytes();
+        arena.reset();
+        parse_in_arena(&arena, "[[true], {}]")?;
+        assert_eq!(arena.allocated_bytes(), used);
+        assert!(parse_in_arena(&arena, "[1,").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn production_names_what_failed() {
+        for (input, expected) in [
+            ("[1, 2", Production::Array),
+            ("[1, foo]", Production::Array),
+            (r#"{"a": [1, {"b": [2 3]}]}"#, Production::Array),
+            (r#"{"a": 1, "b"}"#, Production::Object),
+            (r#"{"a" 1}"#, Production::Object),
+            (r#"{"a": tru}"#, Production::Object),
+            ("{1: 2}", Production::Object),
+            (r#"{"a": }"#, Production::Value),
+            ("[1] 2", Production::Value),
+            ("", Production::Value),
+            (r#""\uD800""#, Production::String),
+            ("1e400", Production::Number),
+            ("\u{FEFF}[1,", Production::Array),
+        ] {
+            let errs = parse(input).unwrap_err();
+            assert_eq!(
+                production(input, leaf_errors(&errs)[0]),
+                Some(expected),
+                "{input:?}: {errs:?}"
+            );
+        }
+
+        let options = ParseOptions {
+            max_depth: 1,
+            on_duplicate_key: OnDuplicateKey::Error,
+            ..ParseOptions::default()
+        };
+        for (input, expected) in [
+            ("[[1]]", Production::Array),
+            (r#"[{"a": 1}]"#, Production::Object),
+            (r#"{"a": 1, "a": 2}"#, Production::Property),
+        ] {
+            let errs = parse_with_options(input, &options).unwrap_err();
+            assert_eq!(production(input, &errs[0]), Some(expected), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_partial_keeps_what_parsed() -> Result<(), Error> {
+        let (value, errs) = parse_partial(r#"{"a":1,"b":}"#);
+        assert_eq!(
+            value,
+            Some(jobject(vec![Property::new("a", jnum(1.0))])),
+            "{errs:?}"
+        );
+        let leaves = leaf_errors(&errs);
+        assert_eq!(leaves.len(), 1, "{errs:?}");
+        assert_eq!(leaves[0].start, 11, "{errs:?}");
+
+        for (input, partial) in [
+            ("[1, 2", "[1, 2]"),
+            ("[1, foo, 3]", "[1, 3]"),
+            (
+                r#"{"a": [1, {"b": [2 3]}], "c": true}"#,
+                r#"{"a": [1, {"b": [2]}], "c": true}"#,
+            ),
+            // Errors from checks after tree-sitter's are left out too.
+            (r#"[1, "\uD800", 1e400, 4]"#, "[1, 4]"),
+        ] {
+            let (value, errs) = parse_partial(input);
+            assert!(!errs.is_empty(), "{input}");
+            assert_eq!(value, Some(parse(partial)?), "{input}: {errs:?}");
+        }
+
+        // Nothing to go on at all.
+        for input in ["", r#"{"a" 1}"#] {
+            let (value, errs) = parse_partial(input);
+            assert_eq!(value, None, "{input}");
+            assert!(!errs.is_empty(), "{input}");
+        }
+
+        // Past the depth limit, nothing is kept, and nothing overflows either.
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let (value, errs) = parse_partial(&deep);
+        let max_depth = ParseOptions::default().max_depth;
+        assert_eq!(stats(&value.unwrap()).max_depth, max_depth);
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::DepthLimitExceeded(_)
+        ));
+
+        let (value, errs) = parse_partial(r#"{"a": [true]}"#);
+        assert_eq!(value, Some(parse(r#"{"a": [true]}"#)?));
+        assert!(errs.is_empty());
+        Ok(())
     }
-    if escape > 0 {
-        println!(">> {s} which is {s:?}");
-        return Err(EscapeError::UnfinishedEscapeSequence(s.to_string()));
+
+    #[test]
+    fn json_string_bad_escape_is_an_error() {
+        assert!(grammar::parse("\"\\x\"").is_err());
+        assert!(parse("\"\\x\"").is_err());
+        assert!(parse("[\"ok\", \"\\x\"]").is_err());
+
+        // Well-formed escapes that do not decode are caught before extraction.
+        let errs = parse("[\"\\uD800\"]").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::InvalidEscape(EscapeError::UnpairedSurrogate {
+                offset: 0,
+                unit: 0xD800
+            })
+        ));
+        assert_eq!((errs[0].start, errs[0].end), (2, 8));
     }
-    match String::from_utf16(&t) {
-        Ok(s) => Ok(s),
-        Err(_) => Err(EscapeError::UnicodeError), // (t)),
+
+    #[test]
+    fn lexer_rejects_unknown_escapes() {
+        // Each is a single error covering just the escape, however tree-sitter
+        // recovered from the rest of the literal.
+        for (input, ch, span) in [
+            (r#""\q""#, 'q', (1, 3)),
+            (r#""\u12g4""#, 'g', (1, 6)),
+            (r#"[1, "a\u12g4", 2]"#, 'g', (6, 11)),
+            (r#"{"\x": 1}"#, 'x', (2, 4)),
+            // Not in JSON, although JavaScript allows it.
+            (r#""\'""#, '\'', (1, 3)),
+        ] {
+            assert!(grammar::parse(input).is_err(), "{input}");
+            let errs = parse(input).unwrap_err();
+            let leaves = leaf_errors(&errs);
+            assert_eq!(leaves.len(), 1, "{input}: {errs:?}");
+            assert!(
+                matches!(
+                    leaves[0].reason,
+                    ParseErrorReason::InvalidEscape(EscapeError::InvalidEscape { ch: c, .. })
+                        if c == ch
+                ),
+                "{input}: {errs:?}"
+            );
+            assert_eq!((leaves[0].start, leaves[0].end), span, "{input}");
+        }
+        assert!(unescape("\\'").is_err());
     }
-}
 
-#[rust_sitter::grammar("parser")]
-pub mod grammar {
+    #[test]
+    fn escape_error_offsets() {
+        assert!(matches!(
+            unescape("ab\\x"),
+            Err(EscapeError::InvalidEscape { offset: 2, ch: 'x' })
+        ));
+        assert!(matches!(
+            unescape("\\u12"),
+            Err(EscapeError::UnfinishedEscapeSequence { offset: 0 })
+        ));
+        assert!(matches!(
+            unescape("a\\u12x4"),
+            Err(EscapeError::InvalidEscape { offset: 1, ch: 'x' })
+        ));
+        assert!(matches!(
+            unescape("\\n\\uDC00"),
+            Err(EscapeError::UnpairedSurrogate {
+                offset: 2,
+                unit: 0xDC00
+            })
+        ));
 
-    #[rust_sitter::language]
-    #[derive(PartialEq, Eq, Debug)]
-    pub enum JsonValue {
-        #[rust_sitter::leaf(text = "null")]
-        Null,
-        #[rust_sitter::leaf(pattern = "true")]
-        True,
-        #[rust_sitter::leaf(pattern = "false")]
-        False,
-        Number(JsonNumber),
-        Str(JsonString),
-        Array(
-            #[rust_sitter::leaf(text = "[")] (),
-            #[rust_sitter::delimited(
-                #[rust_sitter::leaf(text = ",")]
-                ()
-            )]
-            Vec<JsonValue>,
-            #[rust_sitter::leaf(text = "]")] (),
-        ),
-        Object(
-            #[rust_sitter::leaf(text = "{")] (),
-            #[rust_sitter::delimited(
-                #[rust_sitter::leaf(text = ",")]
-                ()
-            )]
-            Vec<Property>,
-            #[rust_sitter::leaf(text = "}")] (),
-        ),
+        // `parse` narrows its error to just the escape.
+        let errs = parse("\"ab\\uDC00cd\"").unwrap_err();
+        assert_eq!((errs[0].start, errs[0].end), (3, 9));
     }
 
-    #[derive(PartialEq, Eq, Debug)]
-    pub struct JsonString(
-        #[rust_sitter::leaf(pattern = "\"([^\\\"]|\\[burntf'\"\\/])*\"", transform = |v| crate::parser::unescape(&v[1..v.len()-1]).expect("?"))]
-        pub String,
-    );
+    #[test]
+    fn parse_borrowed_only_copies_escaped_strings() -> Result<(), Error> {
+        let BorrowedValue::Object(props) =
+            parse_borrowed(r#"{"plain": "text", "esc\u0041": "a\nb"}"#)?
+        else {
+            panic!("expected an object");
+        };
+        assert!(matches!(&props[0].0, Cow::Borrowed("plain")));
+        assert!(matches!(
+            &props[0].1,
+            BorrowedValue::Str(Cow::Borrowed("text"))
+        ));
+        assert!(matches!(&props[1].0, Cow::Owned(k) if k == "escA"));
+        assert!(matches!(&props[1].1, BorrowedValue::Str(Cow::Owned(v)) if v == "a\nb"));
 
-    #[derive(PartialEq, Eq, Debug)]
-    pub struct Property {
-        name: JsonString,
-        #[rust_sitter::leaf(text = ":")]
-        sep: (),
-        value: JsonValue,
+        let BorrowedValue::Number(n) = parse_borrowed(" 1.50 ")? else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.raw(), "1.50");
+        assert_eq!(n.as_f64(), 1.5);
+
+        assert!(parse_borrowed("\"\\uD800\"").is_err());
+        assert!(parse_borrowed(&"[".repeat(1000)).is_err());
+        Ok(())
     }
-    impl Property {
-        #[cfg(test)]
-        pub fn new<S: Into<String>>(name: S, value: JsonValue) -> Self {
-            Self {
-                name: JsonString(name.into()),
-                sep: (),
-                value,
-            }
+
+    #[test]
+    fn unescape_borrows_plain_text() {
+        assert!(matches!(
+            unescape("plain text"),
+            Ok(Cow::Borrowed("plain text"))
+        ));
+        assert!(matches!(unescape("a\\nb"), Ok(Cow::Owned(s)) if s == "a\nb"));
+    }
+
+    #[test]
+    fn escape_control_and_astral_characters() {
+        assert_eq!(escape("a\"b\\c\nd\te\u{1}"), r#"a\"b\\c\nd\te\u0001"#);
+        assert_eq!(escape("é😐"), "é😐");
+        let options = EscapeOptions {
+            astral_as_surrogates: true,
+        };
+        assert_eq!(escape_with_options("é😐", &options), r"é\ud83d\ude10");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn unescape_inverts_escape(s: String, astral_as_surrogates: bool) {
+            let options = EscapeOptions { astral_as_surrogates };
+            let escaped = escape_with_options(&s, &options);
+            proptest::prop_assert_eq!(unescape(&escaped).unwrap(), s);
         }
     }
 
-    #[derive(Debug)]
-    pub struct JsonNumber {
-        #[rust_sitter::leaf(pattern = "\\d+\\.?\\d*[eE]?\\d*", transform = |v| v.parse().unwrap())]
-        value: f64,
+    #[test]
+    fn unescape_surrogate_pairs() {
+        assert_eq!(unescape("\\uD83D\\uDE10").unwrap(), "\u{1F610}");
+        assert_eq!(unescape("\\ud83d\\ude10!").unwrap(), "\u{1F610}!");
+
+        // A lone high surrogate, at the end or before another high surrogate.
+        assert!(matches!(
+            unescape("ab\\uD83D"),
+            Err(EscapeError::UnpairedSurrogate {
+                offset: 2,
+                unit: 0xD83D
+            })
+        ));
+        assert!(matches!(
+            unescape("\\uD83D\\uD83D"),
+            Err(EscapeError::UnpairedSurrogate {
+                offset: 0,
+                unit: 0xD83D
+            })
+        ));
+
+        // A lone low surrogate.
+        assert!(matches!(
+            unescape("\\u0041\\uDE10"),
+            Err(EscapeError::UnpairedSurrogate {
+                offset: 6,
+                unit: 0xDE10
+            })
+        ));
+
+        // A high surrogate followed by something other than a `\u` escape.
+        for input in ["\\uD83Dx", "\\uD83D\\n"] {
+            assert!(matches!(
+                unescape(input),
+                Err(EscapeError::UnpairedSurrogate {
+                    offset: 0,
+                    unit: 0xD83D
+                })
+            ));
+        }
     }
-    impl JsonNumber {
-        #[cfg(test)]
-        pub fn new(value: f64) -> Self {
-            Self { value }
+
+    #[test]
+    fn lossy_surrogates() -> Result<(), Error> {
+        let lossy = ParseOptions {
+            lossy_surrogates: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            unescape_with_options("\\uD800", &lossy).unwrap(),
+            "\u{FFFD}"
+        );
+        assert!(matches!(
+            unescape_with_options("\\uD800", &ParseOptions::default()),
+            Err(EscapeError::UnpairedSurrogate {
+                offset: 0,
+                unit: 0xD800
+            })
+        ));
+
+        // Each lone half is replaced on its own, and pairs still decode.
+        assert_eq!(
+            unescape_with_options("\\uD83D\\uD83D\\uDE10x\\uDE10\\uD83D\\n", &lossy).unwrap(),
+            "\u{FFFD}\u{1F610}x\u{FFFD}\u{FFFD}\n"
+        );
+        // Other bad escapes are still errors.
+        assert!(unescape_with_options("\\uD800\\x", &lossy).is_err());
+
+        assert_eq!(
+            parse_with_options(r#"["\uD800"]"#, &lossy)?,
+            jarray(vec![JsonValue::Str(JsonString("\u{FFFD}".to_string()))])
+        );
+        assert!(parse_with_options(r#"["\uD800"]"#, &ParseOptions::default()).is_err());
+
+        // Keys that only differ in their lone surrogates come out the same.
+        let options = ParseOptions {
+            on_duplicate_key: OnDuplicateKey::Error,
+            ..lossy
+        };
+        let errs = parse_with_options(r#"{"\uD800": 1, "\uDC00": 2}"#, &options).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(
+            matches!(&errs[0].reason, ParseErrorReason::DuplicateKey(key) if key == "\u{FFFD}")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unescape_accumulates_all_four_hex_digits() {
+        assert_eq!(unescape("\\u0001").unwrap(), "\u{0001}");
+        assert_eq!(unescape("\\u0010").unwrap(), "\u{0010}");
+        assert_eq!(unescape("\\u0100").unwrap(), "\u{0100}");
+        assert_eq!(unescape("\\u1000").unwrap(), "\u{1000}");
+        assert_eq!(unescape("\\u9F9F").unwrap(), "\u{9F9F}");
+    }
+
+    #[test]
+    fn unescape_matches_serde_json() {
+        let serde = |body: &str| serde_json::from_str::<String>(&format!("\"{body}\"")).ok();
+        let bodies = [
+            "plain",
+            r#"\b\f\n\r\t\"\\\/"#,
+            r"\u0000\u001f\u001F\u007f",
+            r"\u00e9\u00E9\u00Aa\uFEFF\uFFFF",
+            r"a\u2028b",
+            // Astral characters as surrogate pairs, in any case.
+            r"\uD83D\uDE10",
+            r"\ud83d\ude10",
+            r"\uD83d\uDe10",
+            r"\uDBFF\uDFFF",
+            // And what both reject.
+            r"\uD800",
+            r"\uDC00",
+            r"\uD83D\u0041",
+            r"\uD83Dx",
+            r"\x",
+            r"\u12",
+        ];
+        for body in bodies {
+            assert_eq!(
+                unescape(body).ok().map(Cow::into_owned),
+                serde(body),
+                "{body}"
+            );
         }
+        // On purpose, `unescape` leaves raw control characters alone, since the
+        // grammar has already turned them away by the time it runs.
+        assert_eq!(unescape("a\u{1}b").unwrap(), "a\u{1}b");
+        assert_eq!(serde("a\u{1}b"), None);
     }
 
-    impl PartialEq for JsonNumber {
-        fn eq(&self, other: &Self) -> bool {
-            self.value == other.value
+    #[test]
+    fn json_number_integer_or_float() -> Result<(), Error> {
+        let JsonValue::Number(n) = parse("9007199254740993")? else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.as_i64(), Some(9007199254740993));
+
+        let JsonValue::Number(n) = parse("1.0")? else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.as_i64(), None);
+        assert_eq!(n.as_f64(), 1.0);
+
+        let JsonValue::Number(n) = parse("-0")? else {
+            panic!("expected a number");
+        };
+        assert!(n.as_f64().is_sign_negative());
+
+        // Too large for `i64`, so it falls back to a float.
+        let JsonValue::Number(n) = parse("10000000000000000000000")? else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.as_i64(), None);
+        assert_eq!(n.as_f64(), 1e22);
+
+        assert_eq!(parse("1")?, parse("1.0")?);
+        assert_ne!(parse("9007199254740993")?, parse("9007199254740992.0")?);
+        Ok(())
+    }
+
+    #[test]
+    fn hash_and_order_values() -> Result<(), Error> {
+        let values: HashSet<JsonValue> = [
+            "1",
+            "1.0",
+            "1e0",
+            "0",
+            "-0",
+            "-0.0",
+            "9007199254740993",
+            "9007199254740992.0",
+            r#""a""#,
+            r#""a""#,
+            "[1, {}]",
+            "[1.0, {}]",
+            r#"{"a": 1, "b": 2}"#,
+            r#"{"b": 2, "a": 1}"#,
+        ]
+        .into_iter()
+        .map(parse)
+        .collect::<Result<_, _>>()?;
+        // `1`, `0`, `9007199254740993`, `9007199254740992.0`, `"a"`, `[1, {}]` and
+        // the two objects, since property order counts.
+        assert_eq!(values.len(), 8, "{values:?}");
+
+        let mut values: Vec<_> = [
+            r#"{"a": 1}"#,
+            "[2]",
+            "[1, 2]",
+            r#""b""#,
+            "2.5",
+            "true",
+            "null",
+            "[]",
+            r#"{"a": 0, "b": 1}"#,
+            "false",
+            r#""a""#,
+            "-1e300",
+            "9007199254740993",
+            "9007199254740992.0",
+            "{}",
+            "[1]",
+            "2",
+        ]
+        .into_iter()
+        .map(parse)
+        .collect::<Result<_, _>>()?;
+        values.sort();
+        let sorted: Vec<_> = values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            sorted,
+            [
+                "null",
+                "false",
+                "true",
+                "-1e300",
+                "2",
+                "2.5",
+                "9007199254740992.0",
+                "9007199254740993",
+                r#""a""#,
+                r#""b""#,
+                "[]",
+                "[1]",
+                "[1,2]",
+                "[2]",
+                "{}",
+                r#"{"a":0,"b":1}"#,
+                r#"{"a":1}"#,
+            ]
+        );
+        Ok(())
+    }
+
+    fn num() -> impl proptest::strategy::Strategy<Value = Num> {
+        use proptest::prelude::*;
+        prop_oneof![
+            any::<i64>().prop_map(Num::Int),
+            any::<f64>().prop_map(Num::Float),
+            // Small whole numbers, so that integers and floats often meet.
+            (-3i64..3).prop_map(Num::Int),
+            (-3i64..3).prop_map(|i| Num::Float(i as f64)),
+            Just(Num::Float(-0.0)),
+        ]
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn num_order_and_hash_agree_with_eq(a in num(), b in num()) {
+            use std::hash::{BuildHasher, RandomState};
+            let state = RandomState::new();
+            proptest::prop_assert_eq!(a.cmp(&b) == Ordering::Equal, a == b);
+            proptest::prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            if a == b {
+                proptest::prop_assert_eq!(state.hash_one(a), state.hash_one(b));
+            }
         }
     }
-    impl Eq for JsonNumber {}
 
-    #[rust_sitter::extra]
-    struct Whitespace {
-        #[rust_sitter::leaf(pattern = "\\s")]
-        _whitespace: (),
+    #[test]
+    fn json_number_raw_text() -> Result<(), Error> {
+        let JsonValue::Array(_, values, _) = parse("[ 1.0000 , 1e10 ]")? else {
+            panic!("expected an array");
+        };
+        let raw: Vec<_> = values
+            .iter()
+            .map(|v| match v {
+                JsonValue::Number(n) => n.raw(),
+                _ => panic!("expected a number"),
+            })
+            .collect();
+        assert_eq!(raw, ["1.0000", "1e10"]);
+        assert_eq!(values[0], jnum(1.0));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::grammar::{
-        JsonNumber, JsonString, JsonValue, JsonValue::False, JsonValue::Null, JsonValue::True,
-        Property,
-    };
-    #[allow(clippy::useless_attribute)]
-    #[allow(dead_code)] // its dead for benches
-    use super::*;
-    use rust_sitter::errors::ParseError;
+    #[test]
+    fn json_number_out_of_range() -> Result<(), Error> {
+        let errs = parse("[1, 1e400]").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            &errs[0].reason,
+            ParseErrorReason::NumberOutOfRange(raw) if raw == "1e400"
+        ));
+        assert_eq!((errs[0].start, errs[0].end), (4, 9));
 
-    fn jstr<S: Into<String>>(s: S) -> JsonValue {
-        JsonValue::Str(JsonString(s.into()))
+        assert!(parse("-1e400").is_err());
+        assert!(parse("1e99999999999999999999").is_err());
+        assert!(parse(&format!("1{}", "0".repeat(400))).is_err());
+        // The raw grammar has no way to fail, so it saturates rather than panicking.
+        assert!(grammar::parse("1e400").is_ok());
+
+        // Underflow only loses precision, so it is still accepted.
+        assert_eq!(parse("1e-400")?, jnum(0.0));
+        Ok(())
     }
 
-    fn jobject(v: Vec<Property>) -> JsonValue {
-        JsonValue::Object((), v, ())
+    #[test]
+    fn json_max_depth() -> Result<(), Error> {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_with_options("[[1]]", &options)?,
+            jarray(vec![jarray(vec![jnum(1.0)])])
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": [1]}"#, &options)?,
+            jobject(vec![Property::new("a", jarray(vec![jnum(1.0)]))])
+        );
+
+        let errs = parse_with_options(r#"[{"a": [1]}]"#, &options).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::DepthLimitExceeded(2)
+        ));
+        assert_eq!((errs[0].start, errs[0].end), (7, 8));
+        Ok(())
     }
 
-    fn jarray(v: Vec<JsonValue>) -> JsonValue {
-        JsonValue::Array((), v, ())
+    #[test]
+    fn from_str() -> Result<(), ParseErrors> {
+        let value: JsonValue = r#"{"a": [1, 2]}"#.parse()?;
+        assert_eq!(value, parse(r#"{"a": [1, 2]}"#)?);
+        assert_eq!(
+            "[1.0]".parse::<JsonValue>()?,
+            jarray(vec![JsonValue::Number(JsonNumber::new(1.0))])
+        );
+
+        let errs = r#"["\x", "\q"]"#.parse::<JsonValue>().unwrap_err();
+        assert_eq!(leaf_errors(&errs.0).len(), 2, "{errs:?}");
+        assert_eq!(
+            errs.to_string(),
+            "invalid JSON: InvalidEscape at bytes 2..4, and 1 more error"
+        );
+        let errs = "".parse::<JsonValue>().unwrap_err();
+        assert_eq!(errs.to_string(), "invalid JSON: EmptyInput at bytes 0..0");
+        // Boxes like any other error.
+        let boxed: Box<dyn std::error::Error> = errs.into();
+        assert!(boxed.to_string().starts_with("invalid JSON"));
+        Ok(())
     }
 
-    fn jnum(f: f64) -> JsonValue {
-        JsonValue::Number(JsonNumber::new(f))
+    #[test]
+    fn parse_errors_box_dyn_error() {
+        fn sum(input: &str) -> Result<f64, Box<dyn std::error::Error>> {
+            let value: JsonValue = input.parse()?;
+            let options = ParseOptions {
+                max_depth: 1,
+                ..ParseOptions::default()
+            };
+            parse_with_options(input, &options).map_err(ParseErrors::from)?;
+            Ok(value.values().filter_map(JsonValue::as_f64).sum())
+        }
+
+        assert_eq!(sum("[1, 2.5]").unwrap(), 3.5);
+        let err = sum("[1, [2]]").unwrap_err();
+        let errs = err.downcast::<ParseErrors>().unwrap();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::DepthLimitExceeded(1)
+        ));
+        assert_eq!(
+            errs.to_string(),
+            "invalid JSON: DepthLimitExceeded at bytes 4..5"
+        );
+        // The errors come back out as the parse functions return them.
+        let errs: Vec<ParseError> = sum("[1, tru, nul]")
+            .unwrap_err()
+            .downcast::<ParseErrors>()
+            .unwrap()
+            .into_vec();
+        assert_eq!(leaf_errors(&errs).len(), 2, "{errs:?}");
     }
 
-    #[allow(clippy::useless_attribute)]
-    #[allow(dead_code)] // its dead for benches
-    type Error = Vec<ParseError>;
+    #[test]
+    fn imprecise_numbers_are_found() {
+        let input = "[9007199254740993, 9007199254740992, 1.0, 1e0, 0.1, -0, 0.0e5, \
+                     1e-400, 3.14159265358979323846, 1e23, 123456789012345678901234567890]";
+        let found: Vec<_> = imprecise_numbers(input)
+            .into_iter()
+            .map(|(start, end, f)| (&input[start..end], f))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("9007199254740993", 9007199254740992.0),
+                ("1e-400", 0.0),
+                ("3.14159265358979323846", std::f64::consts::PI),
+                (
+                    "123456789012345678901234567890",
+                    123456789012345678901234567890.0
+                ),
+            ]
+        );
+        assert_eq!(
+            imprecise_numbers(r#"{"a": [1, 2.5e-3, "9007199254740993"]}"#),
+            []
+        );
+    }
+
+    #[test]
+    fn decimal_ignores_how_numbers_are_written() {
+        for (a, b) in [
+            ("1", "1.0"),
+            ("1", "1e0"),
+            ("100", "1e2"),
+            ("0.01", "1e-2"),
+            ("1.5", "15E-1"),
+            ("0", "-0.0e10"),
+            ("-12.50", "-1.25e1"),
+        ] {
+            assert_eq!(decimal(a), decimal(b), "{a} and {b}");
+        }
+        for (a, b) in [("1", "-1"), ("1", "10"), ("0.1", "0.01"), ("12", "21")] {
+            assert_ne!(decimal(a), decimal(b), "{a} and {b}");
+        }
+        assert_eq!(decimal("1e99999999999999999999"), None);
+    }
+
+    #[test]
+    fn json_max_string_length() -> Result<(), Error> {
+        let options = ParseOptions {
+            max_string_length: Some(3),
+            ..ParseOptions::default()
+        };
+        // Counted in bytes as written, so an escape counts for all of its own.
+        assert_eq!(
+            parse_with_options(r#"["abc", "\n", "é"]"#, &options)?,
+            parse(r#"["abc", "\n", "é"]"#)?
+        );
+
+        // Keys count too, and every string over the limit is reported.
+        let errs = parse_with_options(r#"{"abcd": ["\u00e9", "abc"]}"#, &options).unwrap_err();
+        let spans: Vec<_> = errs
+            .iter()
+            .map(|err| {
+                assert!(
+                    matches!(err.reason, ParseErrorReason::StringTooLong(3)),
+                    "{errs:?}"
+                );
+                (err.start, err.end)
+            })
+            .collect();
+        assert_eq!(spans, [(1, 2), (10, 11)]);
+
+        // There is no limit by default.
+        assert!(parse(&format!("\"{}\"", "x".repeat(100_000))).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn json_nul_bytes() -> Result<(), Error> {
+        let first_error = |input: &str| {
+            let errs = parse(input).unwrap_err();
+            let leaf = leaf_errors(&errs)[0];
+            (leaf.reason.name(), leaf.start, leaf.end)
+        };
+        // A NUL doesn't end the input, so one after the value is trailing
+        // content, and one between tokens is out of place.
+        assert_eq!(first_error("[1]\0"), ("UnexpectedToken", 3, 4));
+        assert_eq!(first_error("[1,\0 2]"), ("UnexpectedToken", 3, 4));
+        assert_eq!(first_error("[1, 2]\0[3]"), ("UnexpectedToken", 6, 7));
+        // Written raw in a string, it is a control character like any other.
+        assert!(parse("[\"a\0b\"]").is_err());
+        // Escaped, it is part of the string.
+        let value = parse(r#"{"a\u0000b": "\u0000"}"#)?;
+        assert_eq!(value["a\0b"].as_str(), Some("\0"));
+        Ok(())
+    }
+
+    #[test]
+    fn json_max_depth_deeply_nested() {
+        let depth = 10_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let errs = parse(&input).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].reason,
+            ParseErrorReason::DepthLimitExceeded(128)
+        ));
+        assert_eq!(errs[0].start, 128);
+
+        // Unbalanced input has to be rejected without recursing as well.
+        assert!(parse(&"[".repeat(depth)).is_err());
+        assert!(parse(&"{\"a\":".repeat(depth)).is_err());
+    }
+
+    #[test]
+    fn stats_whitespace_fixture() -> Result<(), Error> {
+        assert_eq!(
+            stats(&parse(corpus::WHITESPACE)?),
+            Stats {
+                objects: 3,
+                arrays: 2,
+                strings: 3,
+                numbers: 3,
+                booleans: 3,
+                nulls: 1,
+                max_depth: 2,
+            }
+        );
+        assert_eq!(
+            stats(&parse("1")?),
+            Stats {
+                numbers: 1,
+                ..Stats::default()
+            }
+        );
+        assert_eq!(stats(&parse("[[], [[{}]]]")?).max_depth, 4);
+
+        let depth = 100;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let deep = stats(&parse(&input)?);
+        assert_eq!((deep.arrays, deep.max_depth), (depth, depth));
+        Ok(())
+    }
 
     #[test]
-    fn unfinished_json_string_1() -> Result<(), Error> {
-        assert_eq!(r#""\""#, "\"\\\"");
-        assert!(grammar::parse(r#""\""#).is_err());
-        assert!(grammar::parse("\"\\\"").is_err());
+    fn outline_whitespace_fixture() -> Result<(), Error> {
+        assert_eq!(
+            outline(&parse(corpus::WHITESPACE)?, 4),
+            r#"object (9)
+  null: null
+  true: true
+  false: false
+  number: 123e…
+  string: " abc…"
+  array: array (3)
+    false
+    1
+    "two"
+  object: object (2)
+    a: 1.0
+    b: "c"
+  empty_array: array (0)
+  empty_object: object (0)
+"#
+        );
+        assert_eq!(outline(&parse(r#""tab\tbé""#)?, 5), "\"tab\\tb…\"\n");
+        assert_eq!(outline(&parse("[[]]")?, 0), "array (1)\n  array (0)\n");
         Ok(())
     }
 
     #[test]
-    fn unfinished_json_string_2() -> Result<(), Error> {
-        assert!(grammar::parse("\\\"").is_err());
+    fn json_number_conversions() {
+        let number = |input| match parse(input) {
+            Ok(JsonValue::Number(n)) => n,
+            other => panic!("expected a number, got {other:?}"),
+        };
+        assert_eq!(f64::from(number("1.5")), 1.5);
+        assert_eq!(number("-2e3").value(), -2000.0);
+        assert_eq!(f64::from(number("9007199254740993")), 9007199254740992.0);
+
+        for (input, expected) in [
+            ("42", 42),
+            ("-0", 0),
+            ("1.0", 1),
+            ("1e2", 100),
+            ("-9223372036854775808", i64::MIN),
+            ("9223372036854775807", i64::MAX),
+            ("-9223372036854775808.0", i64::MIN),
+        ] {
+            assert_eq!(i64::try_from(number(input)), Ok(expected), "{input}");
+        }
+        for input in [
+            "1.5",
+            "1e-1",
+            "9223372036854775808",
+            "9.3e18",
+            "-9.3e18",
+            "1e300",
+        ] {
+            assert_eq!(i64::try_from(number(input)), Err(NotAnI64), "{input}");
+        }
+        let i: Result<i64, _> = number("7").try_into();
+        assert_eq!(i, Ok(7));
+
+        // `Eq` stays reflexive even for NaN, which parsing never produces.
+        let nan = JsonNumber::new(f64::NAN);
+        assert_eq!(nan, JsonNumber::new(f64::NAN));
+        assert_ne!(nan, JsonNumber::new(0.0));
+        assert_eq!(i64::try_from(nan), Err(NotAnI64));
+        assert_eq!(JsonNumber::new(-0.0), number("0"));
+    }
+
+    #[test]
+    fn json_trailing_content() -> Result<(), Error> {
+        for (input, token, start) in [
+            ("1 2", "2", 2),
+            ("{}{}", "{", 2),
+            ("[] x", "x", 3),
+            ("{} garbage", "garbage", 3),
+            (r#""a" "b""#, "\"", 4),
+            ("null,", ",", 4),
+            ("[1, [2]]\n]", "]", 9),
+            ("\u{FEFF}true false", "false", 8),
+        ] {
+            let errs = parse(input).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input:?}: {errs:?}");
+            assert!(
+                matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == token),
+                "{input:?}: {errs:?}"
+            );
+            assert_eq!(
+                (errs[0].start, errs[0].end),
+                (start, start + token.len()),
+                "{input:?}"
+            );
+        }
+
+        // Brackets in strings do not end the value early.
+        let errs = parse(r#"{"a": "}"} 1"#).unwrap_err();
+        assert_eq!((errs[0].start, errs[0].end), (11, 12));
+
+        // Whitespace after the value is fine, and so are comments when allowed.
+        assert_eq!(parse("[]  \n\t\r\n")?, jarray(vec![]));
+        assert_eq!(parse(" 1 ")?, jnum(1.0));
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(parse_with_options("1 // one\n/* */", &options)?, jnum(1.0));
+        let errs = parse_with_options("1 /* one */ 2", &options).unwrap_err();
+        assert_eq!((errs[0].start, errs[0].end), (12, 13));
         Ok(())
     }
 
     #[test]
-    fn unfinished_json_string_3() -> Result<(), Error> {
-        assert!(grammar::parse("\\").is_err());
+    fn canonicalize_sorts_keys() -> Result<(), Error> {
+        let a = parse(r#"{"b": [{"y": 1, "x": 2}], "a": null, "é": 0, "Z": 1}"#)?;
+        let b = parse(r#"{"Z": 1, "a": null, "é": 0, "b": [{"x": 2, "y": 1}]}"#)?;
+        assert_ne!(a, b);
+        assert_eq!(canonicalize(&a), canonicalize(&b));
+        assert_eq!(
+            canonicalize(&a).to_string(),
+            r#"{"Z":1,"a":null,"b":[{"x":2,"y":1}],"é":0}"#
+        );
+
+        // Only the last value of a repeated key survives.
+        assert_eq!(
+            canonicalize(&parse(r#"{"b": 1, "a": 2, "b": 3}"#)?).to_string(),
+            r#"{"a":2,"b":3}"#
+        );
+        // Array order is part of the value, so it is left alone.
+        assert_eq!(canonicalize(&parse("[3, 1, 2]")?).to_string(), "[3,1,2]");
         Ok(())
     }
 
     #[test]
-    fn json_string() -> Result<(), Error> {
-        assert_eq!(grammar::parse("\"\"")?, jstr(""));
-        assert_eq!(grammar::parse("\"abc\"")?, jstr("abc"));
+    fn json_duplicate_keys() -> Result<(), Error> {
+        let input = r#"{"a":1,"b":3,"a":2}"#;
+        let with = |on_duplicate_key| ParseOptions {
+            on_duplicate_key,
+            ..ParseOptions::default()
+        };
+
         assert_eq!(
-            grammar::parse("\"abc\\\"\\\\\\/\\b\\f\\n\\r\\t\\u0001\\u2014\u{2014}def\"")?,
-            jstr("abc\"\\/\x08\x0C\n\r\t\x01——def"),
+            parse_with_options(input, &with(OnDuplicateKey::Keep))?,
+            jobject(vec![
+                Property::new("a", jnum(1.0)),
+                Property::new("b", jnum(3.0)),
+                Property::new("a", jnum(2.0)),
+            ])
+        );
+        assert_eq!(
+            parse_with_options(input, &with(OnDuplicateKey::UseLast))?,
+            jobject(vec![
+                Property::new("b", jnum(3.0)),
+                Property::new("a", jnum(2.0)),
+            ])
+        );
+        assert_eq!(
+            parse_with_options(r#"[{"x":{"a":1,"a":2}}]"#, &with(OnDuplicateKey::UseLast))?,
+            jarray(vec![jobject(vec![Property::new(
+                "x",
+                jobject(vec![Property::new("a", jnum(2.0))])
+            )])])
         );
-        assert_eq!(grammar::parse("\"\\uD83D\\uDE10\"")?, jstr("😐"));
 
-        assert!(grammar::parse("\"").is_err());
-        assert!(grammar::parse("\"abc").is_err());
-        assert!(grammar::parse("\"\\\"").is_err());
-        assert!(grammar::parse("\"\\u123\"").is_err());
-        assert!(grammar::parse("\"\\uD800\"").is_err());
-        assert!(grammar::parse("\"\\uD800\\uD800\"").is_err());
-        assert!(grammar::parse("\"\\uDC00\"").is_err());
+        let errs = parse_with_options(input, &with(OnDuplicateKey::Error)).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(&errs[0].reason, ParseErrorReason::DuplicateKey(key) if key == "a"));
+        assert_eq!((errs[0].start, errs[0].end), (13, 16));
 
+        // Keys are compared after unescaping.
+        assert!(parse_with_options(r#"{"a":1,"\u0061":2}"#, &with(OnDuplicateKey::Error)).is_err());
         Ok(())
     }
 
     #[test]
-    fn json_object() -> Result<(), Error> {
-        let input = "{\"a\":42,\"b\":\"x\"}";
+    fn json_intern_keys() -> Result<(), Error> {
+        let input = r#"[{"id":1,"tags":{"id":"x"}},{"id":2,"n\u0061me":"y"},{"name":null}]"#;
+        let interning = ParseOptions {
+            intern_keys: true,
+            ..ParseOptions::default()
+        };
+        let value = parse_with_options(input, &interning)?;
+        assert_eq!(value, parse(input)?);
 
-        let expected: JsonValue = jobject(vec![
-            Property::new("a", jnum(42.0)),
-            Property::new("b", jstr("x")),
-        ]);
+        let key = |pointer: &str| {
+            let (object, key) = pointer.rsplit_once('/').unwrap();
+            let object = value.pointer(object).unwrap().as_object().unwrap();
+            object
+                .iter()
+                .find(|p| p.key() == key)
+                .unwrap()
+                .key()
+                .as_ptr()
+        };
+        // Keys with the same text share one allocation, however deep they are
+        // and however they were escaped.
+        assert_eq!(key("/0/id"), key("/1/id"));
+        assert_eq!(key("/0/id"), key("/0/tags/id"));
+        assert_eq!(key("/1/name"), key("/2/name"));
+        assert_ne!(key("/0/id"), key("/2/name"));
 
-        assert_eq!(grammar::parse(input)?, expected);
+        let value = parse(input)?;
+        let id = |i: usize| value[i].as_object().unwrap()[0].key().as_ptr();
+        assert_ne!(id(0), id(1));
         Ok(())
     }
 
     #[test]
-    fn json_array() -> Result<(), Error> {
-        let input = r#"[42,"x"]"#;
+    fn json_trailing_commas() -> Result<(), Error> {
+        let relaxed = ParseOptions {
+            allow_trailing_commas: true,
+            ..ParseOptions::default()
+        };
 
-        let expected = jarray(vec![jnum(42.0), jstr("x")]);
+        for input in ["[1,2,]", "[1,2 , \n]"] {
+            let errs = parse(input).unwrap_err();
+            assert_eq!(errs.len(), 1);
+            assert!(matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == ","));
+            assert_eq!(
+                parse_with_options(input, &relaxed)?,
+                jarray(vec![jnum(1.0), jnum(2.0)])
+            );
+        }
+
+        let errs = parse(r#"{"a":1,}"#).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert_eq!((errs[0].start, errs[0].end), (6, 7));
+        assert_eq!(
+            parse_with_options(r#"{"a":1,}"#, &relaxed)?,
+            jobject(vec![Property::new("a", jnum(1.0))])
+        );
 
-        assert_eq!(grammar::parse(input)?, expected);
+        // A comma still needs an element before it.
+        for input in ["[,]", "{,}", "[1,,]"] {
+            assert!(parse(input).is_err());
+            assert!(parse_with_options(input, &relaxed).is_err());
+        }
         Ok(())
     }
 
     #[test]
-    fn json_whitespace() -> Result<(), Error> {
-        let input = r#"
-  {
-    "null" : null,
-    "true"  :true ,
-    "false":  false  ,
-    "number" : 123e4 ,
-    "string" : " abc 123 " ,
-    "array" : [ false , 1 , "two" ] ,
-    "object" : { "a" : 1.0 , "b" : "c" } ,
-    "empty_array" : [  ] ,
-    "empty_object" : {   }
-  }
-  "#;
+    fn json_non_finite() -> Result<(), Error> {
+        let options = ParseOptions {
+            allow_non_finite: true,
+            ..ParseOptions::default()
+        };
+        let value = parse_with_options("[NaN, Infinity, -Infinity, 1]", &options)?;
+        let numbers: Vec<_> = value.values().filter_map(JsonValue::as_f64).collect();
+        assert!(numbers[0].is_nan());
+        assert_eq!(numbers[1..], [f64::INFINITY, f64::NEG_INFINITY, 1.0]);
+        let JsonValue::Number(n) = parse_with_options("-Infinity", &options)? else {
+            panic!("expected a number");
+        };
+        assert_eq!(n.raw(), "-Infinity");
+        assert_eq!(
+            parse_with_options("NaN", &options)?,
+            parse_with_options("NaN", &options)?
+        );
+
+        for (input, token, start) in [
+            ("NaN", "NaN", 0),
+            ("[1, Infinity]", "Infinity", 4),
+            (r#"{"a": -Infinity}"#, "-Infinity", 6),
+        ] {
+            let errs = parse(input).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input}: {errs:?}");
+            assert!(
+                matches!(&errs[0].reason, ParseErrorReason::NonFiniteNumber(t) if t == token),
+                "{input}: {errs:?}"
+            );
+            assert_eq!(
+                (errs[0].start, errs[0].end),
+                (start, start + token.len()),
+                "{input}"
+            );
+        }
+
+        // Only these spellings, and finite literals that overflow are still out
+        // of range.
+        for input in ["nan", "-NaN", "+Infinity", "Inf", "infinity", "1e400"] {
+            assert!(parse_with_options(input, &options).is_err(), "{input}");
+        }
+        Ok(())
+    }
 
+    #[test]
+    fn json_comments() -> Result<(), Error> {
+        let jsonc = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let input = r#"// leading
+            {
+                "url": "http://example.com/*not a comment*/", // trailing
+                /* before */ "n" /* between */ : /* after */ 1
+                /* multi
+                 * line **/
+            }"#;
         assert_eq!(
-            grammar::parse(input)?,
+            parse_with_options(input, &jsonc)?,
             jobject(vec![
-                Property::new("null", Null),
-                Property::new("true", True),
-                Property::new("false", False),
-                Property::new("number", jnum(123e4)),
-                Property::new("string", jstr(" abc 123 ")),
-                Property::new("array", jarray(vec![False, jnum(1.0), jstr("two")])),
                 Property::new(
-                    "object",
-                    jobject(vec![
-                        Property::new("a", jnum(1.0)),
-                        Property::new("b", jstr("c")),
-                    ])
+                    "url",
+                    JsonValue::Str(JsonString(
+                        "http://example.com/*not a comment*/".to_string()
+                    ))
                 ),
-                Property::new("empty_array", jarray(vec![]),),
-                Property::new("empty_object", jobject(vec![]),),
+                Property::new("n", jnum(1.0)),
             ])
         );
+        assert!(parse(input).is_err());
+
+        let errs = parse("[1 // one\n]").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == "//"));
+        assert_eq!(errs[0].start, 3);
+        assert!(parse("[/* none */]").is_err());
+        assert_eq!(parse_with_options("[/* none */]", &jsonc)?, jarray(vec![]));
+
+        // Both relaxations together, with a comment after the trailing comma.
+        let relaxed = ParseOptions {
+            allow_trailing_commas: true,
+            ..jsonc
+        };
+        assert_eq!(
+            parse_with_options("[1, // one\n]", &relaxed)?,
+            jarray(vec![jnum(1.0)])
+        );
+        assert!(parse_with_options("[1, // one\n]", &jsonc).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn json_whitespace_only() -> Result<(), Error> {
+        let lenient = ParseOptions {
+            allow_any_whitespace: true,
+            ..ParseOptions::default()
+        };
+        // No-break space, vertical tab, form feed and ideographic space.
+        for ws in ["\u{A0}", "\u{B}", "\u{C}", "\u{3000}"] {
+            let input = format!("[1,{ws}2]");
+            let errs = parse(&input).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input:?}");
+            assert!(
+                matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == ws),
+                "{input:?}: {errs:?}"
+            );
+            assert_eq!((errs[0].start, errs[0].end), (3, 3 + ws.len()));
+            assert_eq!(
+                parse_with_options(&input, &lenient)?,
+                jarray(vec![jnum(1.0), jnum(2.0)])
+            );
+
+            // Before a closing bracket, where it is part of the bracket's token.
+            let input = format!("{{\"a\": [1{ws}{ws}]{ws}}}");
+            let mut spans: Vec<_> = parse(&input)
+                .unwrap_err()
+                .iter()
+                .map(|err| (err.start, err.end))
+                .collect();
+            spans.sort();
+            let end = 8 + 2 * ws.len();
+            assert_eq!(spans, [(8, end), (end + 1, end + 1 + ws.len())]);
+            assert!(parse_with_options(&input, &lenient).is_ok(), "{input:?}");
+        }
+
+        // Inside a comment, anything goes.
+        let jsonc = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_with_options("[1 /*\u{A0}*/]", &jsonc)?,
+            jarray(vec![jnum(1.0)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_byte_order_mark() -> Result<(), Error> {
+        assert_eq!(parse("\u{FEFF}{}")?, jobject(vec![]));
+        assert_eq!(parse("\u{FEFF} [1]")?, jarray(vec![jnum(1.0)]));
+        assert!(matches!(
+            parse_borrowed("\u{FEFF}null")?,
+            BorrowedValue::Null
+        ));
+
+        // Anywhere else it is an error.
+        let errs = parse("[1,\u{FEFF}2]").unwrap_err();
+        assert_eq!(errs[0].start, 3);
+        assert!(parse("{}\u{FEFF}").is_err());
+        assert!(parse("\u{FEFF}\u{FEFF}{}").is_err());
+        // Inside a string it is just another character.
+        assert_eq!(
+            parse("\"\u{FEFF}\"")?,
+            JsonValue::Str(JsonString("\u{FEFF}".to_string()))
+        );
         Ok(())
     }
+
+    #[test]
+    fn json_empty_input() {
+        for input in ["", "   ", "\n\n", "\u{FEFF}"] {
+            let errs = parse(input).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input:?}");
+            assert!(
+                matches!(errs[0].reason, ParseErrorReason::EmptyInput),
+                "{input:?}"
+            );
+            assert_eq!((errs[0].start, errs[0].end), (0, 0), "{input:?}");
+        }
+
+        // Whitespace that JSON doesn't allow doesn't make an input empty, unless
+        // it is allowed.
+        let errs = parse("\u{FEFF} \u{a0}\n").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == "\u{a0}"));
+        assert_eq!((errs[0].start, errs[0].end), (4, 6));
+        let any_whitespace = ParseOptions {
+            allow_any_whitespace: true,
+            ..ParseOptions::default()
+        };
+        for input in ["\u{a0}", "\u{FEFF} \u{a0}\n"] {
+            let errs = parse_with_options(input, &any_whitespace).unwrap_err();
+            assert_eq!(errs.len(), 1, "{input:?}");
+            assert!(
+                matches!(errs[0].reason, ParseErrorReason::EmptyInput),
+                "{input:?}"
+            );
+        }
+    }
 }
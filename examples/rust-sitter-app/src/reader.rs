@@ -0,0 +1,255 @@
+//! Parsing JSON straight from an [`io::Read`].
+
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::parser::{grammar::JsonValue, parse, ParseError, ParseErrorReason};
+
+/// An error from [`parse_reader`] or [`for_each_array_element`].
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading failed, or what was read is not UTF-8.
+    Io(io::Error),
+    /// What was read is not valid JSON. Offsets count bytes from the start of
+    /// the reader.
+    Parse(Vec<ParseError>),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> Self {
+        ReadError::Io(error)
+    }
+}
+
+/// Reads everything from `reader` and parses it like [`parse`].
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<JsonValue, ReadError> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    parse(&input).map_err(ReadError::Parse)
+}
+
+/// Reads a top-level JSON array from `reader` and calls `f` on each element in
+/// turn, without ever holding more than one element in memory.
+///
+/// The array is split into elements as it is read and each one is parsed on its
+/// own, so parsing stops at the first invalid element, after `f` has seen every
+/// element before it.
+pub fn for_each_array_element<R: Read>(
+    reader: R,
+    mut f: impl FnMut(JsonValue),
+) -> Result<(), ReadError> {
+    let mut bytes = Bytes {
+        reader: BufReader::new(reader),
+        offset: 0,
+    };
+
+    bytes.skip_whitespace()?;
+    match bytes.peek()? {
+        Some(b'[') => bytes.consume(),
+        Some(byte) => return Err(unexpected(byte, bytes.offset)),
+        None => return Err(missing("[", bytes.offset)),
+    }
+    bytes.skip_whitespace()?;
+    if bytes.peek()? == Some(b']') {
+        bytes.consume();
+    } else {
+        let mut element = vec![];
+        loop {
+            let start = bytes.offset;
+            let end = bytes.read_element(&mut element)?;
+            let text = String::from_utf8(std::mem::take(&mut element))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            match parse(&text) {
+                Ok(value) => f(value),
+                Err(mut errors) => {
+                    errors.iter_mut().for_each(|error| error.shift(start));
+                    return Err(ReadError::Parse(errors));
+                }
+            }
+            match end {
+                b',' => continue,
+                b']' => break,
+                _ => unreachable!("elements only end at `,` or `]`"),
+            }
+        }
+    }
+    bytes.skip_whitespace()?;
+    match bytes.peek()? {
+        Some(byte) => Err(unexpected(byte, bytes.offset)),
+        None => Ok(()),
+    }
+}
+
+fn unexpected(byte: u8, at: usize) -> ReadError {
+    ReadError::Parse(vec![ParseError {
+        reason: ParseErrorReason::UnexpectedToken(String::from_utf8_lossy(&[byte]).into_owned()),
+        start: at,
+        end: at + 1,
+    }])
+}
+
+fn missing(token: &str, at: usize) -> ReadError {
+    ReadError::Parse(vec![ParseError {
+        reason: ParseErrorReason::MissingToken(token.to_string()),
+        start: at,
+        end: at,
+    }])
+}
+
+/// The bytes of a reader, counting how many have been taken so far.
+struct Bytes<R> {
+    reader: BufReader<R>,
+    offset: usize,
+}
+
+impl<R: Read> Bytes<R> {
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    /// Takes the byte that [`Bytes::peek`] just saw.
+    fn consume(&mut self) {
+        self.reader.consume(1);
+        self.offset += 1;
+    }
+
+    fn next(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.consume();
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+            self.consume();
+        }
+        Ok(())
+    }
+
+    /// Appends the bytes up to the next `,` or `]` that is not nested in a
+    /// string, array or object to `element`, and returns which of the two ended
+    /// it.
+    fn read_element(&mut self, element: &mut Vec<u8>) -> Result<u8, ReadError> {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let Some(byte) = self.next()? else {
+                return Err(missing("]", self.offset));
+            };
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    // Unbalanced closing brackets are left for `parse` to report.
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b',' | b']' if depth == 0 => return Ok(byte),
+                    _ => {}
+                }
+            }
+            element.push(byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn errors(result: Result<(), ReadError>) -> Vec<ParseError> {
+        match result {
+            Err(ReadError::Parse(errors)) => errors,
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_reader_reads_everything() {
+        let value = parse_reader(Cursor::new(r#"{"a": [1, "two"]}"#)).unwrap();
+        assert_eq!(value["a"][1].as_str(), Some("two"));
+
+        assert!(matches!(
+            parse_reader(Cursor::new("[1,")),
+            Err(ReadError::Parse(_))
+        ));
+        assert!(matches!(
+            parse_reader(Cursor::new(b"\"\xFF\"")),
+            Err(ReadError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn for_each_array_element_large_array() {
+        let count = 10_000;
+        let input = format!(
+            "[{}]",
+            (0..count)
+                .map(|i| format!(r#"{{"i": {i}, "s": "a, [b] {{c}} \"d\""}}"#))
+                .collect::<Vec<_>>()
+                .join(",\n")
+        );
+        let mut seen = 0;
+        for_each_array_element(Cursor::new(input), |value| {
+            assert_eq!(value["i"].as_f64(), Some(seen as f64));
+            assert_eq!(value["s"].as_str(), Some(r#"a, [b] {c} "d""#));
+            seen += 1;
+        })
+        .unwrap();
+        assert_eq!(seen, count);
+    }
+
+    #[test]
+    fn for_each_array_element_nested_and_empty() {
+        let mut values = vec![];
+        for_each_array_element(Cursor::new(" [ [1, [2]], {\"a\": []}, null ] \n"), |v| {
+            values.push(v)
+        })
+        .unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0][1][0].as_f64(), Some(2.0));
+
+        let mut seen = 0;
+        for_each_array_element(Cursor::new("[ ]"), |_| seen += 1).unwrap();
+        assert_eq!(seen, 0);
+    }
+
+    #[test]
+    fn for_each_array_element_errors() {
+        // Errors point into the whole input, after every earlier element was seen.
+        let mut seen = 0;
+        let errs = errors(for_each_array_element(Cursor::new("[1, 2, [3 4]]"), |_| {
+            seen += 1
+        }));
+        assert_eq!(seen, 2);
+        assert!(errs.iter().all(|e| e.start >= 7), "{errs:?}");
+
+        let errs = errors(for_each_array_element(Cursor::new("[1,]"), |_| {}));
+        assert!(matches!(errs[0].reason, ParseErrorReason::EmptyInput));
+        assert_eq!(errs[0].start, 3);
+
+        let errs = errors(for_each_array_element(Cursor::new("[1, 2"), |_| {}));
+        assert!(matches!(&errs[0].reason, ParseErrorReason::MissingToken(t) if t == "]"));
+        assert_eq!(errs[0].start, 5);
+
+        let errs = errors(for_each_array_element(Cursor::new("{}"), |_| {}));
+        assert!(matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == "{"));
+        assert_eq!(errs[0].start, 0);
+
+        let errs = errors(for_each_array_element(Cursor::new(""), |_| {}));
+        assert!(matches!(&errs[0].reason, ParseErrorReason::MissingToken(t) if t == "["));
+
+        let errs = errors(for_each_array_element(Cursor::new("[1] 2"), |_| {}));
+        assert!(matches!(&errs[0].reason, ParseErrorReason::UnexpectedToken(t) if t == "2"));
+        assert_eq!(errs[0].start, 4);
+    }
+}
@@ -0,0 +1,207 @@
+//! Turning a [`JsonValue`] back into JSON text.
+
+use core::fmt::{self, Write};
+
+use crate::parser::{grammar::JsonValue, write_escaped};
+
+/// Writes the value as compact JSON, with no whitespace between tokens.
+///
+/// Numbers are written exactly as they appeared in the input, so parsing the
+/// output gives back an equal value.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::True => f.write_str("true"),
+            JsonValue::False => f.write_str("false"),
+            JsonValue::Number(n) => f.write_str(n.raw()),
+            JsonValue::Str(s) => write_string(f, &s.0),
+            JsonValue::Array(_, values, _) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_char(']')
+            }
+            JsonValue::Object(_, properties, _) => {
+                f.write_char('{')?;
+                for (i, property) in properties.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, property.key())?;
+                    f.write_char(':')?;
+                    property.value.fmt(f)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+impl JsonValue {
+    /// Formats the value as JSON with each array element and object property on
+    /// its own line, indented by `indent` spaces per level of nesting.
+    ///
+    /// Empty arrays and objects stay on one line as `[]` and `{}`.
+    pub fn to_string_pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_pretty(&mut out, self, indent, 0).expect("writing to a `String` cannot fail");
+        out
+    }
+}
+
+fn write_pretty<W: Write>(
+    out: &mut W,
+    value: &JsonValue,
+    indent: usize,
+    level: usize,
+) -> fmt::Result {
+    match value {
+        JsonValue::Array(_, values, _) if !values.is_empty() => {
+            out.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_newline(out, indent, level + 1)?;
+                write_pretty(out, value, indent, level + 1)?;
+            }
+            write_newline(out, indent, level)?;
+            out.write_char(']')
+        }
+        JsonValue::Object(_, properties, _) if !properties.is_empty() => {
+            out.write_char('{')?;
+            for (i, property) in properties.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_newline(out, indent, level + 1)?;
+                write_string(out, property.key())?;
+                out.write_str(": ")?;
+                write_pretty(out, &property.value, indent, level + 1)?;
+            }
+            write_newline(out, indent, level)?;
+            out.write_char('}')
+        }
+        // Everything else, including empty containers, is the same as compact output.
+        _ => write!(out, "{value}"),
+    }
+}
+
+fn write_newline<W: Write>(out: &mut W, indent: usize, level: usize) -> fmt::Result {
+    write!(out, "\n{:width$}", "", width = indent * level)
+}
+
+fn write_string<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    write_escaped(out, s)?;
+    out.write_char('"')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::grammar::{JsonNumber, JsonString, JsonValue, Property};
+    use crate::parser::{parse, ParseError};
+    use corpus::arbitrary::{self, Json};
+    use corpus::WHITESPACE;
+
+    type Error = Vec<ParseError>;
+
+    fn from_arbitrary(json: &Json) -> JsonValue {
+        match json {
+            Json::Null => JsonValue::Null,
+            Json::Bool(true) => JsonValue::True,
+            Json::Bool(false) => JsonValue::False,
+            Json::Number(n) => JsonValue::Number(JsonNumber::new(*n)),
+            Json::String(s) => JsonValue::Str(JsonString(s.clone())),
+            Json::Array(values) => {
+                JsonValue::Array((), values.iter().map(from_arbitrary).collect(), ())
+            }
+            Json::Object(properties) => JsonValue::Object(
+                (),
+                properties
+                    .iter()
+                    .map(|(key, value)| Property::new(key.as_str(), from_arbitrary(value)))
+                    .collect(),
+                (),
+            ),
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_round_trip(json in arbitrary::json()) {
+            let value = from_arbitrary(&json);
+            proptest::prop_assert_eq!(&parse(&value.to_string()).unwrap(), &value);
+            proptest::prop_assert_eq!(&parse(&value.to_string_pretty(2)).unwrap(), &value);
+        }
+    }
+
+    #[test]
+    fn compact_round_trip() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        let compact = value.to_string();
+        assert_eq!(
+            compact,
+            r#"{"null":null,"true":true,"false":false,"number":123e4,"string":" abc 123 ","array":[false,1,"two"],"object":{"a":1.0,"b":"c"},"empty_array":[],"empty_object":{}}"#
+        );
+        assert_eq!(parse(&compact)?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_round_trip() -> Result<(), Error> {
+        let input = r#""\"\\\/\b\f\n\r\t\u0001\u001Fé—""#;
+        let value = parse(input)?;
+        let compact = value.to_string();
+        // `/` and non-ASCII characters do not need escaping, and every other
+        // control character has to be written as `\u00XX`.
+        assert_eq!(compact, "\"\\\"\\\\/\\b\\f\\n\\r\\t\\u0001\\u001fé—\"");
+        assert_eq!(parse(&compact)?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn pretty() -> Result<(), Error> {
+        let value = parse(r#"{"a":[1,{"b":null}],"c":{},"d":[],"e":"x\ny"}"#)?;
+        assert_eq!(
+            value.to_string_pretty(2),
+            r#"{
+  "a": [
+    1,
+    {
+      "b": null
+    }
+  ],
+  "c": {},
+  "d": [],
+  "e": "x\ny"
+}"#
+        );
+        assert_eq!(
+            parse("[[1]]")?.to_string_pretty(4),
+            "[\n    [\n        1\n    ]\n]"
+        );
+        assert_eq!(parse("[1,2]")?.to_string_pretty(0), "[\n1,\n2\n]");
+        assert_eq!(parse("\"flat\"")?.to_string_pretty(2), "\"flat\"");
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_round_trip() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert_eq!(parse(&value.to_string_pretty(3))?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_keep_their_text() -> Result<(), Error> {
+        let input = "[0,-0,1.50,1E+2,-2.5e-3,9007199254740993]";
+        assert_eq!(parse(input)?.to_string(), input);
+        Ok(())
+    }
+}
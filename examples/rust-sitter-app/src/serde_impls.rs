@@ -0,0 +1,215 @@
+//! [`Serialize`] and [`Deserialize`] for the parsed tree, as plain JSON.
+//!
+//! Unlike going through [`serde_json::Value`], objects keep their properties in
+//! document order, duplicate keys included.
+
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::Number;
+
+use crate::parser::grammar::{JsonNumber, JsonString, JsonValue, Property};
+
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::True => serializer.serialize_bool(true),
+            JsonValue::False => serializer.serialize_bool(false),
+            JsonValue::Number(n) => n.serialize(serializer),
+            JsonValue::Str(s) => s.serialize(serializer),
+            JsonValue::Array(_, values, _) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            JsonValue::Object(_, properties, _) => {
+                let mut map = serializer.serialize_map(Some(properties.len()))?;
+                for property in properties {
+                    map.serialize_entry(property.key(), property.value())?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl Serialize for JsonString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Integers that fit in an `i64` or `u64` stay exact, as they do in the
+/// conversion to [`serde_json::Value`]; everything else goes through `f64`.
+impl Serialize for JsonNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.as_i64(), self.raw().parse::<u64>()) {
+            (Some(i), _) => serializer.serialize_i64(i),
+            (None, Ok(u)) => serializer.serialize_u64(u),
+            (None, Err(_)) => serializer.serialize_f64(self.as_f64()),
+        }
+    }
+}
+
+/// A property on its own is written as an object with just that property.
+impl Serialize for Property {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(self.key(), self.value())?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(JsonString)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match JsonValue::deserialize(deserializer)? {
+            JsonValue::Number(n) => Ok(n),
+            _ => Err(de::Error::custom("expected a JSON number")),
+        }
+    }
+}
+
+/// Reads back what [`Property`]'s `Serialize` writes: an object with exactly one
+/// property.
+impl<'de> Deserialize<'de> for Property {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match JsonValue::deserialize(deserializer)? {
+            JsonValue::Object(_, mut properties, _) if properties.len() == 1 => {
+                Ok(properties.remove(0))
+            }
+            _ => Err(de::Error::custom("expected an object with one property")),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<JsonValue, E> {
+        Ok(if v { JsonValue::True } else { JsonValue::False })
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::from_literal(&v.to_string())))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::from_literal(&v.to_string())))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<JsonValue, E> {
+        // serde_json prints every finite number as valid JSON.
+        let n = Number::from_f64(v).ok_or_else(|| E::custom("JSON numbers must be finite"))?;
+        Ok(JsonValue::Number(JsonNumber::from_literal(&n.to_string())))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::Str(JsonString(v.to_owned())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::Str(JsonString(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(JsonValue::Array((), values, ()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut properties = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, JsonValue>()? {
+            properties.push(Property::new(key, value));
+        }
+        Ok(JsonValue::Object((), properties, ()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse;
+    use corpus::WHITESPACE;
+
+    #[test]
+    fn serialize_as_plain_json() {
+        let parsed = parse(WHITESPACE).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"{"null":null,"true":true,"false":false,"number":1230000.0,"string":" abc 123 ","array":[false,1,"two"],"object":{"a":1.0,"b":"c"},"empty_array":[],"empty_object":{}}"#
+        );
+
+        let parsed = parse(r#"[-0, 9007199254740993, 18446744073709551615, "é\n"]"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"[-0.0,9007199254740993,18446744073709551615,"é\n"]"#
+        );
+
+        let JsonValue::Object(_, properties, _) = parse(r#"{"a": [1]}"#).unwrap() else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            serde_json::to_string(&properties[0]).unwrap(),
+            r#"{"a":[1]}"#
+        );
+    }
+
+    #[test]
+    fn deserialize_keeps_order_and_duplicates() {
+        let input = r#"{"b": 1, "a": [true, null, -2.5e-3], "b": "again"}"#;
+        let value: JsonValue = serde_json::from_str(input).unwrap();
+        assert_eq!(value, parse(input).unwrap());
+        let keys: Vec<_> = value.entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["b", "a", "b"]);
+
+        let round_trip: JsonValue =
+            serde_json::from_str(&serde_json::to_string(&parse(WHITESPACE).unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(round_trip, parse(WHITESPACE).unwrap());
+
+        let n: JsonNumber = serde_json::from_str("18446744073709551615").unwrap();
+        assert_eq!(n.raw(), "18446744073709551615");
+        assert!(serde_json::from_str::<JsonNumber>("\"1\"").is_err());
+
+        let property: Property = serde_json::from_str(r#"{"k": "v"}"#).unwrap();
+        assert_eq!(property.key(), "k");
+        assert!(serde_json::from_str::<Property>(r#"{"k": 1, "l": 2}"#).is_err());
+    }
+}
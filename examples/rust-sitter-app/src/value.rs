@@ -0,0 +1,408 @@
+//! Reading the contents of a parsed [`JsonValue`] without matching on it.
+
+use core::ops::Index;
+use std::collections::BTreeMap;
+
+use crate::parser::grammar::{JsonValue, Property};
+
+/// What indexing gives back when there is nothing at the key or index.
+static NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+    /// The value of the property named `key`, if this is an object that has one.
+    ///
+    /// If the object repeats `key`, the last property with it wins, as it does
+    /// when converting to serde_json.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|property| property.key() == key)
+            .map(|property| property.value())
+    }
+
+    /// The element at `index`, if this is an array that long.
+    pub fn get_index(&self, index: usize) -> Option<&JsonValue> {
+        self.as_array().and_then(|values| values.get(index))
+    }
+
+    /// The value that the JSON Pointer `pointer` refers to, as in RFC 6901, if
+    /// there is one. The empty pointer refers to the value itself, and each
+    /// `/`-prefixed token after that to a property, with `~1` standing for `/`
+    /// and `~0` for `~`, or to an array element by its index.
+    ///
+    /// Properties are looked up like [`JsonValue::get`], so the last of any
+    /// duplicate keys wins.
+    ///
+    /// ```
+    /// use rust_sitter_app::parser::parse;
+    ///
+    /// let value = parse(r#"{"a/b": [1, {"~": true}]}"#).unwrap();
+    /// assert_eq!(value.pointer("/a~1b/1/~0"), Some(&parse("true").unwrap()));
+    /// assert_eq!(value.pointer("/a~1b/2"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .try_fold(self, |value, token| match value {
+                JsonValue::Object(..) => {
+                    let key = token.replace("~1", "/").replace("~0", "~");
+                    value.get(&key)
+                }
+                JsonValue::Array(_, values, _) => {
+                    // Only the plain decimal form, so no sign or leading zeros.
+                    let canonical = token == "0"
+                        || (!token.starts_with('0')
+                            && !token.is_empty()
+                            && token.bytes().all(|b| b.is_ascii_digit()));
+                    values.get(token.parse::<usize>().ok().filter(|_| canonical)?)
+                }
+                _ => None,
+            })
+    }
+
+    /// Whether this is `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    /// The boolean, if this is `true` or `false`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::True => Some(true),
+            JsonValue::False => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The number as an `f64`, if this is a number, rounding integers that
+    /// `f64` cannot represent exactly.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    /// The string, unescaped, if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::Str(s) => Some(&s.0),
+            _ => None,
+        }
+    }
+
+    /// The elements, if this is an array.
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(_, values, _) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// The properties in document order, duplicates included, if this is an
+    /// object.
+    pub fn as_object(&self) -> Option<&[Property]> {
+        match self {
+            JsonValue::Object(_, properties, _) => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// The key and value of each property, in document order, if this is an
+    /// object, and nothing otherwise.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &JsonValue)> {
+        self.as_object()
+            .unwrap_or_default()
+            .iter()
+            .map(|property| (property.key(), property.value()))
+    }
+
+    /// The elements, if this is an array, and nothing otherwise.
+    pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
+        self.as_array().unwrap_or_default().iter()
+    }
+
+    /// Whether the two values mean the same thing, which `==` is stricter about:
+    /// objects are compared as maps, so property order does not count and, as
+    /// with [`JsonValue::get`], only the last of any duplicate keys does.
+    /// Numbers compare by value under either, so `1` equals `1.0`.
+    ///
+    /// ```
+    /// use rust_sitter_app::parser::parse;
+    ///
+    /// let a = parse(r#"{"a": 1, "b": [2]}"#).unwrap();
+    /// let b = parse(r#"{"b": [2.0], "a": 1}"#).unwrap();
+    /// assert_ne!(a, b);
+    /// assert!(a.semantic_eq(&b));
+    /// ```
+    pub fn semantic_eq(&self, other: &JsonValue) -> bool {
+        fn members(properties: &[Property]) -> BTreeMap<&str, &JsonValue> {
+            properties
+                .iter()
+                .map(|property| (property.key(), property.value()))
+                .collect()
+        }
+        match (self, other) {
+            (JsonValue::Array(_, a, _), JsonValue::Array(_, b, _)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.semantic_eq(b))
+            }
+            (JsonValue::Object(_, a, _), JsonValue::Object(_, b, _)) => {
+                let (a, b) = (members(a), members(b));
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(&b)
+                        .all(|((ka, va), (kb, vb))| ka == kb && va.semantic_eq(vb))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// Looks up a property like [`JsonValue::get`], but gives `Null` when there is
+/// none, as serde_json does, so that lookups can be chained.
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Looks up an element like [`JsonValue::get_index`], but gives `Null` when
+/// there is none, as serde_json does, so that lookups can be chained.
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &JsonValue {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::grammar::{JsonString, JsonValue};
+    use crate::parser::{parse, ParseError};
+    use corpus::WHITESPACE;
+
+    type Error = Vec<ParseError>;
+
+    #[test]
+    fn index_whitespace_fixture() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert_eq!(value["object"]["a"], parse("1")?);
+        assert_eq!(
+            value["object"]["b"],
+            JsonValue::Str(JsonString("c".to_string()))
+        );
+        assert_eq!(value["array"][2], parse(r#""two""#)?);
+        assert_eq!(value["null"], JsonValue::Null);
+
+        // Anything missing is `Null`, however deep the chain goes.
+        assert_eq!(value["missing"]["a"][0], JsonValue::Null);
+        assert_eq!(value["array"][3], JsonValue::Null);
+        assert_eq!(value["array"]["a"], JsonValue::Null);
+        assert_eq!(value["object"][0], JsonValue::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn pointer_whitespace_fixture() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(
+            value.pointer("/object/b"),
+            Some(&JsonValue::Str(JsonString("c".to_string())))
+        );
+        assert_eq!(value.pointer("/object/a"), Some(&parse("1")?));
+        assert_eq!(value.pointer("/array/2"), Some(&parse(r#""two""#)?));
+        assert_eq!(value.pointer("/empty_object"), Some(&parse("{}")?));
+
+        for pointer in [
+            "object",
+            "/missing",
+            "/array/3",
+            "/array/-",
+            "/array/01",
+            "/array/+1",
+            "/array/",
+            "/array/a",
+            "/object/a/0",
+            "/null/a",
+            // The trailing slash is a property named "", which there is not.
+            "/object/",
+        ] {
+            assert_eq!(value.pointer(pointer), None, "{pointer}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pointer_escapes() -> Result<(), Error> {
+        // The examples from RFC 6901, section 5.
+        let value = parse(
+            r#"{
+                "foo": ["bar", "baz"],
+                "": 0,
+                "a/b": 1,
+                "c%d": 2,
+                "e^f": 3,
+                "g|h": 4,
+                "i\\j": 5,
+                "k\"l": 6,
+                " ": 7,
+                "m~n": 8
+            }"#,
+        )?;
+        assert_eq!(value.pointer("/foo"), Some(&parse(r#"["bar", "baz"]"#)?));
+        assert_eq!(value.pointer("/foo/0"), Some(&parse(r#""bar""#)?));
+        for (pointer, expected) in [
+            ("/", 0),
+            ("/a~1b", 1),
+            ("/c%d", 2),
+            ("/e^f", 3),
+            ("/g|h", 4),
+            ("/i\\j", 5),
+            ("/k\"l", 6),
+            ("/ ", 7),
+            ("/m~0n", 8),
+        ] {
+            assert_eq!(
+                value.pointer(pointer).and_then(JsonValue::as_f64),
+                Some(expected as f64),
+                "{pointer}"
+            );
+        }
+        // `~01` unescapes to `~1`, not to `/`.
+        let value = parse(r#"{"~1": 1, "/": 2}"#)?;
+        assert_eq!(value.pointer("/~01"), Some(&parse("1")?));
+        Ok(())
+    }
+
+    #[test]
+    fn semantic_eq() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert!(value.semantic_eq(&value));
+
+        for (a, b) in [
+            (r#"{"a": 1, "b": 2}"#, r#"{"b": 2, "a": 1}"#),
+            (
+                r#"[{"a": {"x": 1, "y": 2}}]"#,
+                r#"[{"a": {"y": 2, "x": 1.0}}]"#,
+            ),
+            (r#"{"a": 1, "a": 2}"#, r#"{"a": 2}"#),
+        ] {
+            let (a, b) = (parse(a)?, parse(b)?);
+            assert_ne!(a, b);
+            assert!(a.semantic_eq(&b), "{a} and {b}");
+            assert!(b.semantic_eq(&a), "{b} and {a}");
+        }
+        // Both notions agree on numbers.
+        assert_eq!(parse("[1, 1e2]")?, parse("[1.0, 100]")?);
+
+        for (a, b) in [
+            ("[1, 2]", "[2, 1]"),
+            ("[1]", "[1, 1]"),
+            (r#"{"a": 1}"#, r#"{"a": 1, "b": 1}"#),
+            (r#"{"a": 1}"#, r#"{"b": 1}"#),
+            (r#"{"a": 1, "a": 2}"#, r#"{"a": 1}"#),
+            (r#"{"a": "1"}"#, r#"{"a": 1}"#),
+            ("[]", "{}"),
+        ] {
+            let (a, b) = (parse(a)?, parse(b)?);
+            assert!(!a.semantic_eq(&b), "{a} and {b}");
+            assert!(!b.semantic_eq(&a), "{b} and {a}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn get() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert_eq!(value.get("true"), Some(&JsonValue::True));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(value["array"].get_index(0), Some(&JsonValue::False));
+        assert_eq!(value["array"].get_index(3), None);
+        assert_eq!(value["empty_object"].get(""), None);
+        assert_eq!(value.get_index(0), None);
+
+        // The last of a repeated key wins.
+        assert_eq!(parse(r#"{"a":1,"a":2}"#)?.get("a"), Some(&parse("2")?));
+        Ok(())
+    }
+
+    #[test]
+    fn accessors() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        assert!(value["null"].is_null());
+        assert!(!value["false"].is_null());
+        assert!(value["missing"].is_null());
+
+        assert_eq!(value["true"].as_bool(), Some(true));
+        assert_eq!(value["false"].as_bool(), Some(false));
+        assert_eq!(value["null"].as_bool(), None);
+
+        assert_eq!(value["number"].as_f64(), Some(123e4));
+        assert_eq!(value["object"]["a"].as_f64(), Some(1.0));
+        assert_eq!(value["string"].as_f64(), None);
+
+        assert_eq!(value["string"].as_str(), Some(" abc 123 "));
+        assert_eq!(parse(r#""\u00e9\n""#)?.as_str(), Some("é\n"));
+        assert_eq!(value["number"].as_str(), None);
+
+        let array = value["array"].as_array().expect("an array");
+        assert_eq!(array.len(), 3);
+        assert_eq!(array[2].as_str(), Some("two"));
+        assert_eq!(value["empty_array"].as_array(), Some(&[][..]));
+        assert_eq!(value["object"].as_array(), None);
+
+        let object = value["object"].as_object().expect("an object");
+        let keys: Vec<_> = object.iter().map(|property| property.key()).collect();
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(value["empty_object"].as_object(), Some(&[][..]));
+        assert_eq!(value["array"].as_object(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn entries_and_values() -> Result<(), Error> {
+        let value = parse(WHITESPACE)?;
+        let keys: Vec<_> = value.entries().map(|(key, _)| key).collect();
+        assert_eq!(
+            keys,
+            [
+                "null",
+                "true",
+                "false",
+                "number",
+                "string",
+                "array",
+                "object",
+                "empty_array",
+                "empty_object"
+            ]
+        );
+
+        // Walk down into the nested containers.
+        let nested: Vec<_> = value
+            .entries()
+            .flat_map(|(key, value)| value.entries().map(move |(inner, _)| (key, inner)))
+            .collect();
+        assert_eq!(nested, [("object", "a"), ("object", "b")]);
+        let strings: Vec<_> = value["array"]
+            .values()
+            .filter_map(JsonValue::as_str)
+            .collect();
+        assert_eq!(strings, ["two"]);
+
+        assert_eq!(value["empty_object"].entries().count(), 0);
+        assert_eq!(value["empty_array"].values().count(), 0);
+        assert_eq!(value["array"].entries().count(), 0);
+        assert_eq!(value.values().count(), 0);
+        Ok(())
+    }
+}
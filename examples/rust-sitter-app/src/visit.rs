@@ -0,0 +1,142 @@
+//! Walking a parsed [`JsonValue`] in document order, with a callback for each
+//! value, key and bracket, and no collections built along the way.
+
+use crate::parser::grammar::{JsonNumber, JsonValue};
+
+/// Callbacks for [`walk`], each of which does nothing unless overridden.
+///
+/// An array's elements come between its `on_array_start` and `on_array_end`,
+/// and an object's properties between its `on_object_start` and
+/// `on_object_end`, each as an `on_key` followed by the property's value.
+pub trait Visitor {
+    fn on_null(&mut self) {}
+    fn on_bool(&mut self, _value: bool) {}
+    fn on_number(&mut self, _value: &JsonNumber) {}
+    fn on_string(&mut self, _value: &str) {}
+    /// The start of an array with `len` elements.
+    fn on_array_start(&mut self, _len: usize) {}
+    fn on_array_end(&mut self) {}
+    /// The start of an object with `len` properties.
+    fn on_object_start(&mut self, _len: usize) {}
+    fn on_object_end(&mut self) {}
+    /// The key of the property whose value comes next, unescaped.
+    fn on_key(&mut self, _key: &str) {}
+}
+
+/// What is left to visit, innermost last.
+enum Step<'a> {
+    Value(&'a JsonValue),
+    Key(&'a str),
+    ArrayEnd,
+    ObjectEnd,
+}
+
+/// Calls `visitor` back for everything in `value`, in document order.
+///
+/// This does not recurse, so it copes with however deep a tree parsing allowed.
+pub fn walk<V: Visitor>(value: &JsonValue, visitor: &mut V) {
+    let mut stack = vec![Step::Value(value)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Value(JsonValue::Null) => visitor.on_null(),
+            Step::Value(JsonValue::True) => visitor.on_bool(true),
+            Step::Value(JsonValue::False) => visitor.on_bool(false),
+            Step::Value(JsonValue::Number(n)) => visitor.on_number(n),
+            Step::Value(JsonValue::Str(s)) => visitor.on_string(&s.0),
+            Step::Value(JsonValue::Array(_, values, _)) => {
+                visitor.on_array_start(values.len());
+                stack.push(Step::ArrayEnd);
+                stack.extend(values.iter().rev().map(Step::Value));
+            }
+            Step::Value(JsonValue::Object(_, properties, _)) => {
+                visitor.on_object_start(properties.len());
+                stack.push(Step::ObjectEnd);
+                for property in properties.iter().rev() {
+                    stack.push(Step::Value(property.value()));
+                    stack.push(Step::Key(property.key()));
+                }
+            }
+            Step::Key(key) => visitor.on_key(key),
+            Step::ArrayEnd => visitor.on_array_end(),
+            Step::ObjectEnd => visitor.on_object_end(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse, ParseError};
+
+    type Error = Vec<ParseError>;
+
+    #[test]
+    fn concatenates_strings() -> Result<(), Error> {
+        #[derive(Default)]
+        struct Strings(String);
+
+        impl Visitor for Strings {
+            fn on_string(&mut self, value: &str) {
+                self.0.push_str(value);
+            }
+        }
+
+        let mut strings = Strings::default();
+        walk(
+            &parse(r#"["a", {"key": "b", "c": ["d", 1, null]}, "e\n"]"#)?,
+            &mut strings,
+        );
+        assert_eq!(strings.0, "abde\n");
+        Ok(())
+    }
+
+    #[test]
+    fn document_order() -> Result<(), Error> {
+        #[derive(Default)]
+        struct Events(Vec<String>);
+
+        impl Visitor for Events {
+            fn on_null(&mut self) {
+                self.0.push("null".to_owned());
+            }
+            fn on_bool(&mut self, value: bool) {
+                self.0.push(value.to_string());
+            }
+            fn on_number(&mut self, value: &JsonNumber) {
+                self.0.push(value.raw().to_owned());
+            }
+            fn on_string(&mut self, value: &str) {
+                self.0.push(format!("{value:?}"));
+            }
+            fn on_array_start(&mut self, len: usize) {
+                self.0.push(format!("[{len}"));
+            }
+            fn on_array_end(&mut self) {
+                self.0.push("]".to_owned());
+            }
+            fn on_object_start(&mut self, len: usize) {
+                self.0.push(format!("{{{len}"));
+            }
+            fn on_object_end(&mut self) {
+                self.0.push("}".to_owned());
+            }
+            fn on_key(&mut self, key: &str) {
+                self.0.push(format!("{key}:"));
+            }
+        }
+
+        let mut events = Events::default();
+        walk(
+            &parse(r#"{"a": [1, true], "b": {}, "c": [], "d": false}"#)?,
+            &mut events,
+        );
+        assert_eq!(
+            events.0,
+            [
+                "{4", "a:", "[2", "1", "true", "]", "b:", "{0", "}", "c:", "[0", "]", "d:",
+                "false", "}"
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! [`parse_json`] for JavaScript, through `wasm-bindgen`, so the parser can be
+//! sized up and timed in a browser.
+
+use js_sys::{Array, Object};
+use wasm_bindgen::prelude::*;
+
+use crate::parser::{self, grammar::JsonValue, ParseError};
+
+/// Parses `input` into plain JavaScript values, as `JSON.parse` would, or fails
+/// with a JSON-encoded array of errors like
+/// `[{"reason":"UnexpectedToken","start":4,"end":7}]`.
+///
+/// As with `JSON.parse`, the last of an object's duplicate keys wins.
+#[wasm_bindgen]
+pub fn parse_json(input: &str) -> Result<JsValue, JsValue> {
+    match parser::parse(input) {
+        Ok(value) => Ok(to_js(&value)),
+        Err(errors) => Err(JsValue::from_str(&errors_to_json(&errors))),
+    }
+}
+
+/// Builds `value` out of JavaScript values. This recurses, but no deeper than
+/// [`parser::ParseOptions::max_depth`] allowed.
+fn to_js(value: &JsonValue) -> JsValue {
+    match value {
+        JsonValue::Null => JsValue::NULL,
+        JsonValue::True => JsValue::TRUE,
+        JsonValue::False => JsValue::FALSE,
+        JsonValue::Number(n) => JsValue::from_f64(n.as_f64()),
+        JsonValue::Str(s) => JsValue::from_str(&s.0),
+        JsonValue::Array(_, values, _) => values.iter().map(to_js).collect::<Array>().into(),
+        JsonValue::Object(_, properties, _) => {
+            let entries: Array = properties
+                .iter()
+                .map(|property| {
+                    Array::of2(&JsValue::from_str(property.key()), &to_js(property.value()))
+                })
+                .collect();
+            // Unlike assigning the properties one by one, this makes a
+            // `__proto__` key an ordinary property.
+            Object::from_entries(&entries)
+                .expect("entries are all [key, value] pairs")
+                .into()
+        }
+    }
+}
+
+/// The errors that tree-sitter nests inside failed nodes, flattened into a
+/// JSON array.
+fn errors_to_json(errors: &[ParseError]) -> String {
+    let leaves: Vec<_> = parser::leaf_errors(errors)
+        .into_iter()
+        .map(|error| {
+            format!(
+                r#"{{"reason":"{}","start":{},"end":{}}}"#,
+                error.reason.name(),
+                error.start,
+                error.end
+            )
+        })
+        .collect();
+    format!("[{}]", leaves.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn errors_flatten_into_json() {
+        let errors = parser::parse(r#"{"a": [1, tru]}"#).unwrap_err();
+        assert_eq!(
+            errors_to_json(&errors),
+            r#"[{"reason":"UnexpectedToken","start":10,"end":13}]"#
+        );
+        let errors = parser::parse(r#"[1e400, "\uD800"]"#).unwrap_err();
+        assert_eq!(
+            errors_to_json(&errors),
+            r#"[{"reason":"NumberOutOfRange","start":1,"end":6},{"reason":"InvalidEscape","start":9,"end":15}]"#
+        );
+    }
+}
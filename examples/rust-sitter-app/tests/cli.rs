@@ -0,0 +1,792 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use rust_sitter_app::parser::{grammar::JsonValue, parse};
+
+fn app() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_rust-sitter-app"))
+}
+
+/// Runs the app with `args`, piping `stdin` in.
+fn run_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = app()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Writes `contents` to a file named `name` in a scratch directory.
+fn input(name: &str, contents: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn field<'v>(value: &'v JsonValue, name: &str) -> &'v JsonValue {
+    let JsonValue::Object(_, properties, _) = value else {
+        panic!("expected an object, got {value:?}");
+    };
+    properties
+        .iter()
+        .find(|p| p.key() == name)
+        .unwrap_or_else(|| panic!("no {name:?} in {value:?}"))
+        .value()
+}
+
+#[test]
+fn json_diagnostics() {
+    let path = input("json_diagnostics.json", r#"{"a": [1, 2}"#);
+    let output = app().arg("--format=json").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let diagnostics = parse(&stdout).unwrap_or_else(|errs| panic!("{stdout}: {errs:?}"));
+    let JsonValue::Array(_, diagnostics, _) = diagnostics else {
+        panic!("expected an array, got {stdout}");
+    };
+    assert_eq!(diagnostics.len(), 1, "{stdout}");
+    let diagnostic = &diagnostics[0];
+    let JsonValue::Str(reason) = field(diagnostic, "reason") else {
+        panic!("{stdout}");
+    };
+    assert_eq!(reason.0, "MissingToken");
+    let (JsonValue::Number(start), JsonValue::Number(end)) =
+        (field(diagnostic, "start"), field(diagnostic, "end"))
+    else {
+        panic!("{stdout}");
+    };
+    assert_eq!((start.as_i64(), end.as_i64()), (Some(11), Some(11)));
+    let JsonValue::Str(message) = field(diagnostic, "message") else {
+        panic!("{stdout}");
+    };
+    assert!(message.0.starts_with("Missing token"), "{stdout}");
+}
+
+#[test]
+fn empty_input() {
+    for stdin in ["", "   ", "\n\n"] {
+        let output = run_with_stdin(&[], stdin);
+        assert_eq!(output.status.code(), Some(1), "{stdin:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("Unexpected end of input: expected a JSON value at 1:1"),
+            "{stdin:?}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn ndjson() {
+    let path = input("ndjson.jsonl", "{\"a\": 1}\n\n[1, 2\n  \n\"three\"\n");
+    let output = app()
+        .arg("--ndjson")
+        .arg("--format=json")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let JsonValue::Array(_, diagnostics, _) = parse(&stdout).unwrap() else {
+        panic!("expected an array, got {stdout}");
+    };
+    assert_eq!(diagnostics.len(), 1, "{stdout}");
+    let (JsonValue::Number(start), JsonValue::Number(end)) = (
+        field(&diagnostics[0], "start"),
+        field(&diagnostics[0], "end"),
+    ) else {
+        panic!("{stdout}");
+    };
+    // The missing `]` is at the end of the third line, not the end of the file.
+    assert_eq!((start.as_i64(), end.as_i64()), (Some(15), Some(15)));
+
+    let output = app().arg("--ndjson").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Missing token"), "{stderr}");
+    assert!(stderr.contains(" at 3:6\n"), "{stderr}");
+    assert_eq!(stderr.matches("error[S000]").count(), 1, "{stderr}");
+
+    let path = input("ndjson_valid.jsonl", "{\"a\": 1}\n[1, 2]\n");
+    let output = app().arg("--ndjson").arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    // Without the flag the lines run together into one invalid document.
+    let output = app().arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn count() {
+    let mut records: Vec<String> = (0..20_000)
+        .map(|i| format!(r#"{{"id": {i}, "tags": ["a", "b"], "ok": true}}"#))
+        .collect();
+    records[9] = r#"{"id": 9, "tags": ["a", "b"], "ok": tru}"#.to_string();
+    records[10_000] = String::new();
+    records[19_998] = "[1, 2".to_string();
+    let path = input("count.jsonl", &(records.join("\n") + "\n"));
+    let output = app().arg("--count").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout
+        .lines()
+        .map(|line| line.rsplit_once("count.jsonl").unwrap().1)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            r#":10:37: Unexpected token: "tru""#,
+            r#":19999:6: Missing token: "JsonValue_Array_2""#,
+            ": 19997 valid, 2 invalid",
+        ]
+    );
+
+    let output = run_with_stdin(&["--count", "--quiet"], "[1]\n\n{}\n");
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+    let output = run_with_stdin(&["--count"], "[1]\n\n{}\n");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>: 2 valid, 0 invalid\n"
+    );
+}
+
+#[test]
+fn bench() {
+    let path = input("bench.json", r#"{"a": [1, 2, {"b": null}]}"#);
+    for args in [&["--bench", "5"][..], &["--bench=5"]] {
+        let output = app().args(args).arg(&path).output().unwrap();
+        assert!(output.status.success(), "{args:?}: {output:?}");
+        // The tree is not printed, only the timings.
+        assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("over 5 parses"), "{stderr}");
+        let rate = stderr
+            .split(", ")
+            .find_map(|part| part.strip_suffix(" MB/s"))
+            .unwrap_or_else(|| panic!("no MB/s in {stderr}"));
+        assert!(rate.parse::<f64>().unwrap() > 0.0, "{stderr}");
+    }
+
+    let output = app().arg("--bench=0").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn generate() {
+    if !cfg!(feature = "generate") {
+        let output = app().arg("--generate=deep:10").output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.starts_with("--generate=deep:10 needs the generate feature\n"),
+            "{stderr}"
+        );
+        return;
+    }
+
+    let output = app()
+        .args(["--generate", "numbers:1000", "--bench=3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("numbers:1000: median"), "{stderr}");
+
+    for shape in ["deep:10", "wide_array:10", "string_heavy:10"] {
+        let output = app()
+            .arg(format!("--generate={shape}"))
+            .arg("--quiet")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{shape}: {output:?}");
+    }
+    // Past the default depth limit.
+    let output = app()
+        .args(["--generate=deep:200", "--quiet"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    for arg in [
+        "--generate=deep",
+        "--generate=shallow:1",
+        "--generate=deep:-1",
+    ] {
+        let output = app().arg(arg).output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{arg}");
+    }
+}
+
+#[test]
+fn max_string_length() {
+    let path = input("max_string_length.json", r#"{"key": "a long value"}"#);
+    let output = app().arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    for args in [
+        &["--max-string-length", "5"][..],
+        &["--max-string-length=5"],
+    ] {
+        let output = app()
+            .args(args)
+            .arg("--color=never")
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("String too long: more than 5 bytes at 1:9\n"),
+            "{args:?}: {stderr}"
+        );
+        assert_eq!(stderr.matches("String too long").count(), 1, "{stderr}");
+    }
+
+    let output = app()
+        .args(["--max-string-length=-1"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn max_bytes() {
+    let small = input("max_bytes_small.json", "[1, 2]");
+    let large = input("max_bytes_large.json", "[1, 2, 3, 4, 5]");
+    for args in [&["--max-bytes", "6"][..], &["--max-bytes=6"]] {
+        let output = app().args(args).arg(&small).output().unwrap();
+        assert!(output.status.success(), "{args:?}: {output:?}");
+
+        let output = app().args(args).arg(&large).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.ends_with(
+                "max_bytes_large.json: refusing to parse: \
+                 15 bytes is over the --max-bytes limit of 6\n"
+            ),
+            "{args:?}: {stderr}"
+        );
+    }
+    // The rest of the inputs are still parsed.
+    let output = app()
+        .args(["--max-bytes=6", "--format=json"])
+        .arg(&large)
+        .arg(&small)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[]\n");
+
+    let output = run_with_stdin(&["--max-bytes=6"], "[1, 2, 3]");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("the input is over the --max-bytes limit of 6"),
+        "{stderr}"
+    );
+    assert!(run_with_stdin(&["--max-bytes=6"], "[1, 2]")
+        .status
+        .success());
+
+    let output = app()
+        .args(["--max-bytes=1e3"])
+        .arg(&small)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn not_utf8() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("not_utf8.json");
+    fs::write(&path, b"[1, \"\xff\"]").unwrap();
+    let output = app().arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.ends_with("not_utf8.json: file is not valid UTF-8 at byte 5\n"),
+        "{stderr}"
+    );
+
+    let mut child = app()
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"\xc3(").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "<stdin>: input is not valid UTF-8 at byte 0\n"
+    );
+}
+
+#[test]
+fn nul_byte() {
+    let path = input("nul_byte.json", "[1,\0 2]");
+    let output = app().arg("--color=never").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // Quoted as an escape, rather than written to the terminal as it is.
+    assert!(
+        stderr.contains("Unexpected token: \"\\u{0}\" at 1:4"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn warn_precision() {
+    let path = input(
+        "warn_precision.json",
+        "{\"id\": 9007199254740993, \"ok\": [9007199254740992, 0.1, 1.0]}",
+    );
+    let output = app()
+        .args(["--warn-precision", "--color=never"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    // Only a warning, so the document still passes.
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "warning[W000]: Number loses precision: \
+             9007199254740993 is read as 9007199254740992 at 1:8\n"
+        ),
+        "{stderr}"
+    );
+    assert_eq!(stderr.matches("warning").count(), 1, "{stderr}");
+
+    let output = app().arg(&path).output().unwrap();
+    assert!(!String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("warning"));
+
+    let path = input(
+        "warn_precision.jsonl",
+        "[1]\n\n{\"a\": 1e-400}\n[12345678901234567890]\n",
+    );
+    let output = app()
+        .args(["--warn-precision", "--ndjson", "--color=never"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1e-400 is read as 0 at 3:7\n"), "{stderr}");
+    assert!(
+        stderr.contains("12345678901234567890 is read as 12345678901234567000 at 4:2\n"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn depth_limit() {
+    let path = input("depth_limit.json", r#"{"a": [[{"b": [[]]}]], "c": [1]}"#);
+    let output = app().arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    for args in [&["--depth-limit", "3"][..], &["--depth-limit=3"]] {
+        let output = app()
+            .args(args)
+            .arg("--color=never")
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        // Only the first brace past the limit is reported.
+        assert!(
+            stderr.contains("more than 3 levels of arrays and objects at 1:9\n"),
+            "{args:?}: {stderr}"
+        );
+        assert_eq!(stderr.matches("Nesting too deep").count(), 1, "{stderr}");
+    }
+
+    let output = app().args(["--depth-limit=6"]).arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let deep = input(
+        "depth_limit_deep.json",
+        &format!("{}{}", "[".repeat(100), "]".repeat(100)),
+    );
+    let output = app()
+        .args(["--depth-limit", "10", "--color=never"])
+        .arg(&deep)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("more than 10 levels"), "{stderr}");
+    assert!(stderr.contains(" at 1:11\n"), "{stderr}");
+
+    let output = app()
+        .args(["--depth-limit=deep"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    // A limit deeper than extraction can recurse is refused up front, rather
+    // than overflowing the stack on input that nests that deep.
+    let deeper = input(
+        "depth_limit_deeper.json",
+        &format!("{}{}", "[".repeat(200_000), "]".repeat(200_000)),
+    );
+    let output = app()
+        .args(["--depth-limit", "1000000"])
+        .arg(&deeper)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("depth limit 1000000 is over the maximum of 1000\n"),
+        "{stderr}"
+    );
+    let output = app()
+        .args(["--depth-limit=1000", "--format=json"])
+        .arg(&deeper)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
+
+#[test]
+fn stats() {
+    let path = input("stats.json", r#"{"a": [1, "two", null], "b": {"c": true}}"#);
+    let output = app().arg("--stats").arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{}: 2 objects, 1 arrays, 1 strings, 1 numbers, 1 booleans, 1 nulls, depth 2\n",
+            path.display()
+        )
+    );
+
+    // One line for each document.
+    let path = input("stats.jsonl", "[]\n1\n");
+    let output = app()
+        .args(["--stats", "--ndjson"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 2);
+}
+
+#[test]
+fn tree() {
+    let long = "x".repeat(100);
+    let path = input(
+        "tree.json",
+        &format!(r#"{{"a": [1, {{"b": "{long}"}}], "c": null}}"#),
+    );
+    let output = app().arg("--tree").arg(&path).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "object (2)\n  a: array (2)\n    1\n    object (1)\n      b: \"{}…\"\n  c: null\n",
+            &long[..40]
+        )
+    );
+}
+
+#[test]
+fn keyword_suggestions() {
+    for (input, keyword) in [
+        (r#"{"a": ture}"#, "true"),
+        ("[nul]", "null"),
+        ("fales", "false"),
+        ("[1, tru, 2]", "true"),
+    ] {
+        let output = run_with_stdin(&["--color=never"], input);
+        assert_eq!(output.status.code(), Some(1), "{input}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains(&format!("did you mean `{keyword}`?")),
+            "{input}: {stderr}"
+        );
+    }
+
+    // Nothing that is too far from every keyword gets a suggestion.
+    let output = run_with_stdin(&["--color=never"], "[1, foo]");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unexpected \"foo\""), "{stderr}");
+    assert!(!stderr.contains("did you mean"), "{stderr}");
+}
+
+#[test]
+fn sort_keys() {
+    let a = input(
+        "sort_keys_a.json",
+        r#"{"b": {"d": 1, "c": [2]}, "a": true}"#,
+    );
+    let b = input(
+        "sort_keys_b.json",
+        "{\n\t\"a\" : true,\n\t\"b\" : { \"c\" : [ 2 ], \"d\" : 1 }\n}",
+    );
+    let canonical = |path: &PathBuf| {
+        let output = app().arg("--sort-keys").arg(path).output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(
+        canonical(&a),
+        "{\n  \"a\": true,\n  \"b\": {\n    \"c\": [\n      2\n    ],\n    \"d\": 1\n  }\n}\n"
+    );
+    assert_eq!(canonical(&a), canonical(&b));
+}
+
+#[test]
+fn json_format_without_errors() {
+    let path = input("json_format_without_errors.json", "[1, 2]");
+    let output = app().arg("--format=json").arg(&path).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[]\n");
+}
+
+#[test]
+fn human_diagnostics_report_line_and_column() {
+    let path = input(
+        "human_diagnostics_report_line_and_column.json",
+        "{\n  \"a\": 1,\n  \"é\": [1, 2 3]\n}\n",
+    );
+    let output = app().arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // The column counts `é` as one character, not two bytes.
+    assert!(stderr.contains("Failed to parse node at 3:14"), "{stderr}");
+}
+
+#[test]
+fn reads_stdin() {
+    for args in [&[][..], &["-"]] {
+        let output = run_with_stdin(args, r#"{"a": [true]}"#);
+        assert!(output.status.success(), "{args:?}: {output:?}");
+
+        let output = run_with_stdin(args, "[1,\n 2");
+        assert_eq!(output.status.code(), Some(1), "{args:?}: {output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("<stdin>:2:"), "{args:?}: {stderr}");
+    }
+}
+
+#[test]
+fn multiple_files() {
+    let valid = input("multiple_files_valid.json", "[1, 2]");
+    let invalid = input("multiple_files_invalid.json", "[1, 2");
+    let output = app().arg(&valid).arg(&invalid).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("{}: ok", valid.display())),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!("{}: 1 error", invalid.display())),
+        "{stderr}"
+    );
+
+    let output = app()
+        .arg("--format=json")
+        .arg(&invalid)
+        .arg(&valid)
+        .arg(&invalid)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let JsonValue::Array(_, diagnostics, _) = parse(&stdout).unwrap() else {
+        panic!("expected an array, got {stdout}");
+    };
+    assert_eq!(diagnostics.len(), 2, "{stdout}");
+    for diagnostic in &diagnostics {
+        let JsonValue::Str(file) = field(diagnostic, "file") else {
+            panic!("{stdout}");
+        };
+        assert_eq!(file.0, invalid.display().to_string());
+    }
+}
+
+#[test]
+fn quiet() {
+    let valid = input("quiet_valid.json", "[1, 2]");
+    let invalid = input("quiet_invalid.json", "[1, 2");
+    for (files, code) in [
+        (&[&valid][..], 0),
+        (&[&invalid], 1),
+        (&[&valid, &invalid], 1),
+        (&[&valid, &valid], 0),
+    ] {
+        for format in ["--format=human", "--format=json"] {
+            let output = app()
+                .arg("--quiet")
+                .arg(format)
+                .args(files)
+                .output()
+                .unwrap();
+            assert_eq!(output.status.code(), Some(code), "{files:?} {format}");
+            assert!(output.stdout.is_empty(), "{files:?} {format}: {output:?}");
+            assert!(output.stderr.is_empty(), "{files:?} {format}: {output:?}");
+        }
+    }
+}
+
+#[test]
+fn color() {
+    let path = input("color.json", "[1, 2");
+    let stderr = |color: &str| {
+        let output = app().arg(color).arg(&path).output().unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        String::from_utf8(output.stderr).unwrap()
+    };
+    assert!(!stderr("--color=never").contains('\x1b'));
+    assert!(stderr("--color=always").contains('\x1b'));
+    // Captured stderr is not a terminal.
+    assert!(!stderr("--color=auto").contains('\x1b'));
+}
+
+#[test]
+fn reporter() {
+    let path = input("reporter.json", "[1,\n tru]");
+    let output = app().arg("--reporter=fancy").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+    let output = app().arg("--reporter=codemap").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Unexpected token: \"tru\" at 2:2"),
+        "{stderr}"
+    );
+
+    let output = app().arg("--reporter=miette").arg(&path).output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    if cfg!(feature = "miette") {
+        assert_eq!(output.status.code(), Some(1), "{stderr}");
+        assert!(
+            stderr.contains("× invalid JSON: UnexpectedToken at bytes 5..8"),
+            "{stderr}"
+        );
+        assert!(
+            stderr.contains("╭─[") && stderr.contains("reporter.json:2:2]"),
+            "{stderr}"
+        );
+        assert!(stderr.contains("╰── unexpected \"tru\""), "{stderr}");
+    } else {
+        assert_eq!(output.status.code(), Some(2), "{stderr}");
+        assert!(stderr.starts_with("--reporter=miette needs the miette feature\n"));
+    }
+}
+
+#[test]
+fn max_errors() {
+    // Every missing comma is its own error, all inside the one array.
+    let elements: Vec<_> = (0..30).map(|_| "1 1").collect();
+    let path = input("max_errors.json", &format!("[{}]", elements.join(", ")));
+    let diagnostics = |args: &[&str]| {
+        let output = app()
+            .arg("--color=never")
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let count = stderr.matches("error[S000]").count();
+        (count, stderr)
+    };
+
+    let (count, stderr) = diagnostics(&[]);
+    assert_eq!(count, 20, "{stderr}");
+    assert!(stderr.ends_with("... and 10 more errors\n"), "{stderr}");
+
+    for args in [&["--max-errors", "3"][..], &["--max-errors=3"]] {
+        let (count, stderr) = diagnostics(args);
+        assert_eq!(count, 3, "{stderr}");
+        assert!(stderr.ends_with("... and 27 more errors\n"), "{stderr}");
+    }
+
+    let (count, stderr) = diagnostics(&["--max-errors=0"]);
+    assert_eq!(count, 30, "{stderr}");
+    assert!(!stderr.contains("more error"), "{stderr}");
+}
+
+#[test]
+fn explain() {
+    for (input, production) in [
+        ("[1, 2", "an array"),
+        (r#"{"a": [1, {"b": [2 3]}]}"#, "an array"),
+        (r#"{"a" 1}"#, "an object"),
+        (r#"{"a": }"#, "a value"),
+        (r#"["\uDC00"]"#, "a string"),
+    ] {
+        let output = run_with_stdin(&["--explain", "--color=never"], input);
+        assert_eq!(output.status.code(), Some(1), "{input}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains(&format!(" while parsing {production} at ")),
+            "{input}: {stderr}"
+        );
+
+        // Without the flag, nothing changes.
+        let output = run_with_stdin(&["--color=never"], input);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("while parsing"), "{input}: {stderr}");
+    }
+
+    // Errors on later lines of NDJSON are explained from their own line.
+    let output = run_with_stdin(
+        &["--explain", "--ndjson", "--format=json"],
+        "[1]\n{\"a\": 1, \"b\"}\n[1, 2\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let JsonValue::Array(_, diagnostics, _) = parse(&stdout).unwrap() else {
+        panic!("expected an array, got {stdout}");
+    };
+    let productions: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| match field(diagnostic, "production") {
+            JsonValue::Str(production) => production.0.as_str(),
+            _ => panic!("{stdout}"),
+        })
+        .collect();
+    assert_eq!(productions, ["Object", "Array"], "{stdout}");
+}
+
+/// The human diagnostics, word for word, as stored under `tests/snapshots`. After
+/// a deliberate change, accept the new output with `cargo insta review`.
+#[test]
+fn diagnostic_snapshots() {
+    for (name, stdin) in [
+        ("missing_token", "[1, 2"),
+        ("unexpected_token", "[1, foo]"),
+        ("nested_failed_node", "{\n  \"a\": [1, {\"b\": [2 3]}]\n}\n"),
+        // Underlines the node being parsed around the token too.
+        ("enclosing_node", "{\n  \"a\": {\n    \"b\": nul\n  }\n}\n"),
+    ] {
+        let output = run_with_stdin(&["--color=never"], stdin);
+        assert_eq!(output.status.code(), Some(1), "{name}: {output:?}");
+        insta::assert_snapshot!(name, String::from_utf8(output.stderr).unwrap(), stdin);
+    }
+}
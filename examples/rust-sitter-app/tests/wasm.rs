@@ -0,0 +1,37 @@
+//! Runs headless with `wasm-pack test --headless --firefox examples/rust-sitter-app
+//! --no-default-features --features wasm`.
+
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use js_sys::{Array, JsString, Object, Reflect, JSON};
+use rust_sitter_app::wasm::parse_json;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn parses_a_small_document() {
+    let value = parse_json(r#"{"a": [1, "two", null], "b": true, "__proto__": 2}"#).unwrap();
+    assert!(value.is_instance_of::<Object>());
+    let a: Array = Reflect::get(&value, &"a".into()).unwrap().into();
+    assert_eq!(a.length(), 3);
+    assert_eq!(a.get(0).as_f64(), Some(1.0));
+    assert_eq!(a.get(1).as_string().as_deref(), Some("two"));
+    assert!(a.get(2).is_null());
+    assert_eq!(Reflect::get(&value, &"b".into()).unwrap(), JsValue::TRUE);
+    // An own property, as `JSON.parse` makes it, not the prototype.
+    assert_eq!(
+        JSON::stringify(&value).unwrap(),
+        JsString::from(r#"{"a":[1,"two",null],"b":true,"__proto__":2}"#)
+    );
+}
+
+#[wasm_bindgen_test]
+fn errors_are_json() {
+    let errors = parse_json("[1, tru]").unwrap_err().as_string().unwrap();
+    assert_eq!(
+        errors,
+        r#"[{"reason":"UnexpectedToken","start":4,"end":7}]"#
+    );
+}
@@ -1,10 +1,10 @@
 use std::{env, fs};
 
-fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match serde_json::from_str::<serde_json::Value>(&src) {
+    match serde_json_app::parse(&src) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -15,8 +15,9 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
                 std::hint::black_box(json);
             }
         }
-        Err(e) => eprintln!("{e}"),
-    }
-
-    Ok(())
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
 }
@@ -0,0 +1,89 @@
+//! `serde_json` itself, as the baseline the other examples are measured
+//! against.
+//!
+//! [`parse_value`] is plain `serde_json::from_str`, and is what the benchmark
+//! runs. [`parse`] converts its result to the same `JsonValue` shape as the
+//! other examples, so it can run the shared corpus.
+
+use serde_json::Value;
+
+pub use ast::JsonValue;
+
+/// Parses a complete JSON document into a [`serde_json::Value`].
+pub fn parse_value(input: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(input)
+}
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> serde_json::Result<JsonValue> {
+    parse_value(input).map(from_value)
+}
+
+/// Converts a [`serde_json::Value`]. `serde_json` nests at most 128 levels deep,
+/// so this can recurse.
+///
+/// Objects come out in `serde_json`'s order, which sorts the keys, with only
+/// the last of a repeated key kept.
+pub fn from_value(value: Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Bool(b) => JsonValue::Bool(b),
+        // Without `arbitrary_precision`, every number fits an `f64`.
+        Value::Number(n) => JsonValue::Number(n.as_f64().unwrap()),
+        Value::String(s) => JsonValue::String(s),
+        Value::Array(items) => JsonValue::Array(items.into_iter().map(from_value).collect()),
+        Value::Object(members) => JsonValue::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key, from_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Like [`corpus::same_value`], except that objects are compared
+    /// by key, since `serde_json` doesn't keep document order.
+    fn same_value(value: &JsonValue, expected: &corpus::Value) -> bool {
+        use corpus::Value;
+        match (value, expected) {
+            (JsonValue::Null, Value::Null) => true,
+            (JsonValue::Bool(b), Value::Bool(e)) => b == e,
+            (JsonValue::Number(n), Value::Number(e)) => n == e,
+            (JsonValue::String(s), Value::String(e)) => s == e,
+            (JsonValue::Array(items), Value::Array(e)) => {
+                items.len() == e.len() && items.iter().zip(*e).all(|(v, e)| same_value(v, e))
+            }
+            (JsonValue::Object(members), Value::Object(e)) => {
+                members.len() == e.len()
+                    && e.iter()
+                        .all(|(ek, ev)| members.iter().any(|(k, v)| k == ek && same_value(v, ev)))
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn repeated_keys() {
+        assert_eq!(
+            parse(r#"{"b": 1, "a": 2, "b": 3}"#).unwrap(),
+            JsonValue::Object(vec![
+                ("a".to_owned(), JsonValue::Number(2.0)),
+                ("b".to_owned(), JsonValue::Number(3.0)),
+            ])
+        );
+    }
+}
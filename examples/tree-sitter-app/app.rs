@@ -0,0 +1,23 @@
+use std::{env, fs};
+
+fn main() {
+    let filename = env::args().nth(1).expect("Expected file argument");
+    let src = fs::read_to_string(&filename).expect("Failed to read file");
+
+    match tree_sitter_app::parse(src.as_str()) {
+        Ok(json) => {
+            #[cfg(debug_assertions)]
+            {
+                println!("{:#?}", json);
+            }
+            #[cfg(not(debug_assertions))]
+            {
+                std::hint::black_box(json);
+            }
+        }
+        Err(err) => {
+            eprintln!("{filename}:{err}");
+            std::process::exit(1);
+        }
+    }
+}
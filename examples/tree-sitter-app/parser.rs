@@ -0,0 +1,365 @@
+//! JSON through the `tree-sitter` crate and the C grammar from
+//! `tree-sitter-json`, walking the syntax tree it builds into a [`JsonValue`].
+//!
+//! This is the runtime under the rust-sitter example driven by hand, with a
+//! grammar compiled ahead of time instead of one generated from Rust types, so
+//! it measures what a generic, error-recovering parser costs against the
+//! tailored ones. The grammar is meant for editors and is looser than JSON: it
+//! takes comments, any number of top-level values, `1.`, raw control
+//! characters in strings and any Unicode whitespace, so the walk rejects
+//! whatever strict JSON does not allow.
+
+use std::{fmt, ops::Range};
+
+use tree_sitter::{Node, Parser};
+
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// Why and where parsing stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The bytes of the offending node, or an empty span where a token is
+    /// missing.
+    pub span: Range<usize>,
+    /// A lowercase description such as `"syntax error"`.
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.span, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+fn error(node: Node<'_>, message: &'static str) -> ParseError {
+    ParseError {
+        span: node.byte_range(),
+        message,
+    }
+}
+
+pub fn parse(input: &str) -> Result<JsonValue> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_json::LANGUAGE.into())
+        .expect("tree-sitter-json matches the tree-sitter runtime");
+    // The grammar has no `+` in exponents, so it is handed a `0` in place of
+    // any `+` after an `e`, which leaves every offset where it was. `number`
+    // checks the literal as written, and anywhere else a `+` was an error
+    // already and the `0` stays one.
+    let bytes = input.as_bytes();
+    let tree = parser
+        .parse_with(
+            &mut |offset, _| {
+                let rest = &bytes[offset.min(bytes.len())..];
+                if rest.first() == Some(&b'+')
+                    && offset > 0
+                    && matches!(bytes[offset - 1], b'e' | b'E')
+                {
+                    return &b"0"[..];
+                }
+                let end = rest
+                    .windows(2)
+                    .position(|pair| matches!(pair, [b'e' | b'E', b'+']))
+                    .map_or(rest.len(), |i| i + 1);
+                &rest[..end]
+            },
+            None,
+        )
+        .expect("parsing without a timeout always produces a tree");
+    let root = tree.root_node();
+    if root.has_error() {
+        return Err(syntax_error(root));
+    }
+    check_tokens(root, input)?;
+
+    let mut cursor = root.walk();
+    let mut values = root.named_children(&mut cursor);
+    let Some(value) = values.next() else {
+        return Err(ParseError {
+            span: input.len()..input.len(),
+            message: "expected a value",
+        });
+    };
+    if let Some(extra) = values.next() {
+        return Err(error(extra, "expected end of input"));
+    }
+    walk(value, input, 0)
+}
+
+/// The first node tree-sitter could not fit into the grammar, or had to make up.
+fn syntax_error(root: Node<'_>) -> ParseError {
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_error() {
+            return error(node, "syntax error");
+        }
+        if node.is_missing() {
+            return error(node, "missing token");
+        }
+        // Only descend into the nodes with an error somewhere inside.
+        if node.has_error() && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                // `has_error` promised there was one.
+                return error(root, "syntax error");
+            }
+        }
+    }
+}
+
+/// Checks that only JSON's whitespace separates the tokens, which the grammar
+/// leaves to its `\s` extra along with comments. Strings are checked as a whole
+/// by [`string`], since the grammar lets whitespace in before a closing quote.
+fn check_tokens(root: Node<'_>, input: &str) -> Result<()> {
+    let mut end = 0;
+    let check_gap = |from: usize, to: usize| {
+        let gap = &input[from..to];
+        match gap.find(|c| !matches!(c, ' ' | '\t' | '\r' | '\n')) {
+            Some(i) => {
+                let c = gap[i..].chars().next().unwrap();
+                Err(ParseError {
+                    span: from + i..from + i + c.len_utf8(),
+                    message: "unexpected character",
+                })
+            }
+            None => Ok(()),
+        }
+    };
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if node.kind() == "comment" {
+            return Err(error(node, "comments are not JSON"));
+        }
+        if node.kind() != "string" && cursor.goto_first_child() {
+            continue;
+        }
+        if node.child_count() == 0 || node.kind() == "string" {
+            check_gap(end, node.start_byte())?;
+            end = node.end_byte();
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return check_gap(end, input.len());
+            }
+        }
+    }
+}
+
+fn walk(node: Node<'_>, input: &str, depth: usize) -> Result<JsonValue> {
+    let text = &input[node.byte_range()];
+    match node.kind() {
+        "null" => Ok(JsonValue::Null),
+        "true" => Ok(JsonValue::Bool(true)),
+        "false" => Ok(JsonValue::Bool(false)),
+        "number" => number(node, text).map(JsonValue::Number),
+        "string" => string(node, text).map(JsonValue::String),
+        "array" | "object" if depth == MAX_DEPTH => Err(ParseError {
+            span: node.start_byte()..node.start_byte() + 1,
+            message: "nesting too deep",
+        }),
+        "array" => {
+            let mut cursor = node.walk();
+            node.named_children(&mut cursor)
+                .map(|value| walk(value, input, depth + 1))
+                .collect::<Result<_>>()
+                .map(JsonValue::Array)
+        }
+        "object" => {
+            let mut cursor = node.walk();
+            node.named_children(&mut cursor)
+                .map(|pair| {
+                    let key = pair.child_by_field_name("key").expect("a pair has a key");
+                    let value = pair
+                        .child_by_field_name("value")
+                        .expect("a pair has a value");
+                    Ok((
+                        string(key, &input[key.byte_range()])?,
+                        walk(value, input, depth + 1)?,
+                    ))
+                })
+                .collect::<Result<_>>()
+                .map(JsonValue::Object)
+        }
+        _ => Err(error(node, "expected a value")),
+    }
+}
+
+/// Checks `text` against `-?(0|[1-9]\d*)(\.\d+)?([eE][+-]?\d+)?`, which the
+/// grammar is looser than, then leaves the conversion to the standard library.
+fn number(node: Node<'_>, text: &str) -> Result<f64> {
+    fn digits(s: &str) -> &str {
+        s.trim_start_matches(|c: char| c.is_ascii_digit())
+    }
+    let rest = text.strip_prefix('-').unwrap_or(text);
+    let rest = match rest.strip_prefix('0') {
+        Some(rest) => rest,
+        None if rest.starts_with(|c: char| c.is_ascii_digit()) => digits(rest),
+        None => return Err(error(node, "expected a digit")),
+    };
+    let rest = match rest.strip_prefix('.') {
+        Some(frac) if digits(frac).len() < frac.len() => digits(frac),
+        Some(_) => return Err(error(node, "expected a digit")),
+        None => rest,
+    };
+    let rest = match rest.strip_prefix(['e', 'E']) {
+        Some(exp) => {
+            let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            if digits(exp).len() == exp.len() {
+                return Err(error(node, "expected a digit"));
+            }
+            digits(exp)
+        }
+        None => rest,
+    };
+    if !rest.is_empty() {
+        return Err(error(node, "expected a digit"));
+    }
+    let n: f64 = text.parse().unwrap();
+    if !n.is_finite() {
+        return Err(error(node, "number out of range"));
+    }
+    Ok(n)
+}
+
+/// Decodes the string literal `text`, quotes and all, pairing up the surrogates
+/// from `\u` escapes as rust-sitter's `unescape` does.
+fn string(node: Node<'_>, text: &str) -> Result<String> {
+    let body = &text[1..text.len() - 1];
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    let hex4 = |chars: &mut std::str::Chars<'_>| {
+        let digits = chars.as_str().get(..4)?;
+        let unit = u16::from_str_radix(digits, 16).ok()?;
+        // `from_str_radix` would take a sign.
+        digits.bytes().all(|b| b.is_ascii_hexdigit()).then(|| {
+            chars.nth(3);
+            unit
+        })
+    };
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('/') => '/',
+                Some('b') => '\x08',
+                Some('f') => '\x0C',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('u') => {
+                    let high =
+                        hex4(&mut chars).ok_or_else(|| error(node, "expected four hex digits"))?;
+                    if !(0xD800..=0xDBFF).contains(&high) {
+                        char::from_u32(u32::from(high))
+                            .ok_or_else(|| error(node, "unpaired surrogate"))?
+                    } else {
+                        let low = chars
+                            .as_str()
+                            .strip_prefix("\\u")
+                            .and_then(|_| {
+                                chars.nth(1);
+                                hex4(&mut chars)
+                            })
+                            .filter(|low| (0xDC00..=0xDFFF).contains(low))
+                            .ok_or_else(|| error(node, "unpaired surrogate"))?;
+                        let c = 0x10000
+                            + ((u32::from(high) - 0xD800) << 10)
+                            + (u32::from(low) - 0xDC00);
+                        char::from_u32(c).unwrap()
+                    }
+                }
+                _ => return Err(error(node, "invalid escape")),
+            },
+            '\0'..='\x1F' => return Err(error(node, "control character in string")),
+            c => c,
+        };
+        out.push(c);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn looser_grammar_is_tightened() {
+        for input in [
+            "// comment\n1",
+            "[1 /* comment */]",
+            "1 2",
+            "",
+            " \n",
+            "1.",
+            "-01",
+            "1e",
+            "\"a\nb\"",
+            "\"a\tb\"",
+            "\"a\n\"",
+            "\"\\u12\"",
+            "\"\\uD800\"",
+            "\"\\uD800\\u0041\"",
+            "[1,\u{A0}2]",
+            "[1,\x0C2]",
+            "[true+1]",
+            "1e+",
+            "+1",
+            "\u{2028}null",
+        ] {
+            assert!(parse(input).is_err(), "{input:?}: {:?}", parse(input));
+        }
+        assert_eq!(
+            parse("[1,\x0C2]"),
+            Err(ParseError {
+                span: 3..4,
+                message: "unexpected character"
+            })
+        );
+        assert_eq!(
+            parse("1 2"),
+            Err(ParseError {
+                span: 2..3,
+                message: "expected end of input"
+            })
+        );
+        assert_eq!(
+            parse(r#"["a\u00e9\ud83d\ude00\/", 1E+2, -0.5e-1]"#),
+            Ok(JsonValue::Array(vec![
+                JsonValue::String("aé😀/".to_owned()),
+                JsonValue::Number(100.0),
+                JsonValue::Number(-0.05),
+            ]))
+        );
+    }
+
+    #[test]
+    fn nesting_limit() {
+        assert_eq!(
+            corpus::check_max_depth(parse),
+            ParseError {
+                span: MAX_DEPTH..MAX_DEPTH + 1,
+                message: "nesting too deep"
+            }
+        );
+    }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+}
@@ -1,19 +1,10 @@
-mod json;
-mod parser;
-
 use std::{env, fs};
 
-use winnow::error::ContextError;
-use winnow::prelude::*;
-
 fn main() {
     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
         .expect("Failed to read file");
 
-    match parser::json::<ContextError>
-        .parse(src.as_str())
-        .map_err(|e| e.to_string())
-    {
+    match winnow_app::parse(src.as_str()).map_err(|e| e.to_string()) {
         Ok(json) => {
             #[cfg(debug_assertions)]
             {
@@ -1,11 +1,5 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum JsonValue {
-    Null,
-    Boolean(bool),
-    Str(String),
-    Num(f64),
-    Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
-}
+/// The shared [`ast::JsonValue`], with strings that borrow from the input
+/// unless they contain escapes, which have to be decoded into a fresh `String`.
+pub type JsonValue<'i> = ast::JsonValue<Cow<'i, str>>;
@@ -0,0 +1,5 @@
+pub mod json;
+pub mod parser;
+
+pub use json::JsonValue;
+pub use parser::parse;
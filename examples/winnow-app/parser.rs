@@ -1,24 +1,39 @@
-use std::collections::HashMap;
+//! A JSON parser built from winnow combinators.
+//!
+//! Each parser names the production of the rust-sitter grammar
+//! (`examples/rust-sitter-app/src/parser.rs`) it corresponds to, so the two can
+//! be read side by side. The big difference is whitespace: rust-sitter skips
+//! its `Whitespace` extra between any two tokens, while here `ws` has to be
+//! placed by hand around every token that may be padded.
+
+use std::borrow::Cow;
 use std::str;
 
 use winnow::prelude::*;
 use winnow::{
-    ascii::float,
+    ascii::{digit0, digit1},
     combinator::cut_err,
     combinator::empty,
     combinator::fail,
     combinator::peek,
-    combinator::{alt, dispatch},
+    combinator::separated,
+    combinator::{alt, dispatch, opt},
     combinator::{delimited, preceded, separated_pair, terminated},
-    combinator::{repeat, separated},
-    error::{AddContext, ParserError, StrContext},
-    token::{any, none_of, take, take_while},
+    error::{AddContext, ContextError, ParseError, ParserError, StrContext},
+    token::{any, one_of, take_while},
 };
 
 use crate::json::JsonValue;
 
+pub use ast::MAX_DEPTH;
+
 pub type Stream<'i> = &'i str;
 
+/// Parses a complete JSON document, rejecting anything after it.
+pub fn parse(input: &str) -> Result<JsonValue<'_>, ParseError<Stream<'_>, ContextError>> {
+    json::<ContextError>.parse(input)
+}
+
 /// The root element of a JSON parser is any value
 ///
 /// A parser has the following signature:
@@ -33,32 +48,34 @@ pub type Stream<'i> = &'i str;
 /// implements the required traits.
 pub fn json<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<JsonValue, E> {
-    delimited(ws, json_value, ws).parse_next(input)
+) -> PResult<JsonValue<'i>, E> {
+    delimited(ws, |i: &mut _| json_value(i, 0), ws).parse_next(input)
 }
 
-/// `alt` is a combinator that tries multiple parsers one by one, until
-/// one of them succeeds
+/// `JsonValue`: one variant per kind of value.
+///
+/// `depth` counts the arrays and objects enclosing this value.
 fn json_value<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<JsonValue, E> {
+    depth: usize,
+) -> PResult<JsonValue<'i>, E> {
     // `dispatch` gives you `match`-like behavior compared to `alt` successively trying different
     // implementations.
     dispatch!(peek(any);
         'n' => null.value(JsonValue::Null),
-        't' => true_.map(JsonValue::Boolean),
-        'f' => false_.map(JsonValue::Boolean),
-        '"' => string.map(JsonValue::Str),
-        '+' => float.map(JsonValue::Num),
-        '-' => float.map(JsonValue::Num),
-        '0'..='9' => float.map(JsonValue::Num),
-        '[' => array.map(JsonValue::Array),
-        '{' => object.map(JsonValue::Object),
+        't' => true_.map(JsonValue::Bool),
+        'f' => false_.map(JsonValue::Bool),
+        '"' => string.map(JsonValue::String),
+        '-' | '0'..='9' => number.map(JsonValue::Number),
+        '[' => (|i: &mut _| array(i, depth + 1)).map(JsonValue::Array),
+        '{' => (|i: &mut _| object(i, depth + 1)).map(JsonValue::Object),
         _ => fail,
     )
     .parse_next(input)
 }
 
+/// `JsonValue::Null`, `#[leaf(text = "null")]`.
+///
 /// `tag(string)` generates a parser that recognizes the argument string.
 ///
 /// This also shows returning a sub-slice of the original input
@@ -68,6 +85,8 @@ fn null<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<&'i s
     "null".parse_next(input)
 }
 
+/// `JsonValue::True`, `#[leaf(text = "true")]`.
+///
 /// We can combine `tag` with other functions, like `value` which returns a given constant value on
 /// success.
 fn true_<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<bool, E> {
@@ -76,6 +95,8 @@ fn true_<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<bool
     "true".value(true).parse_next(input)
 }
 
+/// `JsonValue::False`, `#[leaf(text = "false")]`.
+///
 /// We can combine `tag` with other functions, like `value` which returns a given constant value on
 /// success.
 fn false_<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<bool, E> {
@@ -84,24 +105,42 @@ fn false_<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<boo
     "false".value(false).parse_next(input)
 }
 
-/// This parser gathers all `char`s up into a `String`with a parse to recognize the double quote
-/// character, before the string (using `preceded`) and after the string (using `terminated`).
+/// `JsonNumber`, whose leaf pattern is `-?(0|[1-9]\d*)(\.\d+)?([eE][+-]?\d+)?`.
+///
+/// A tuple of parsers runs them in sequence, `opt` is the regex `?`, and `take`
+/// hands back the slice the whole sequence matched. Numbers too large for an
+/// `f64` are an error, like rust-sitter's `NumberOutOfRange`.
+fn number<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
+    input: &mut Stream<'i>,
+) -> PResult<f64, E> {
+    (
+        opt('-'),
+        alt(("0", (one_of('1'..='9'), digit0).take())),
+        opt(('.', cut_err(digit1))),
+        opt((one_of(['e', 'E']), opt(one_of(['+', '-'])), cut_err(digit1))),
+    )
+        .take()
+        .parse_to::<f64>()
+        .verify(|n: &f64| n.is_finite())
+        .context(StrContext::Label("number"))
+        .parse_next(input)
+}
+
+/// `JsonString`, whose leaf pattern is `"([^"\\\x00-\x1f]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*"`.
+///
+/// This parser gathers the characters between the double quotes (using `preceded` and
+/// `terminated`). Strings without escapes are handed back as a slice of the input, with no
+/// allocation at all.
 fn string<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<String, E> {
+) -> PResult<Cow<'i, str>, E> {
     preceded(
         '\"',
         // `cut_err` transforms an `ErrMode::Backtrack(e)` to `ErrMode::Cut(e)`, signaling to
         // combinators like  `alt` that they should not try other parsers. We were in the
         // right branch (since we found the `"` character) but encountered an error when
         // parsing the string
-        cut_err(terminated(
-            repeat(0.., character).fold(String::new, |mut string, c| {
-                string.push(c);
-                string
-            }),
-            '\"',
-        )),
+        cut_err(terminated(string_body, '\"')),
     )
     // `context` lets you add a static string to errors to provide more information in the
     // error chain (to indicate which parser had an error)
@@ -110,10 +149,29 @@ fn string<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
 }
 
 /// You can mix the above declarative parsing with an imperative style to handle more unique cases,
-/// like escaping
-fn character<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<char, E> {
-    let c = none_of('\"').parse_next(input)?;
-    if c == '\\' {
+/// like only allocating once the first escape shows up
+fn string_body<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<Cow<'i, str>, E> {
+    let unescaped = take_while(0.., plain).parse_next(input)?;
+    if !input.starts_with('\\') {
+        return Ok(Cow::Borrowed(unescaped));
+    }
+    let mut string = unescaped.to_owned();
+    while input.starts_with('\\') {
+        string.push(escape.parse_next(input)?);
+        string.push_str(take_while(0.., plain).parse_next(input)?);
+    }
+    Ok(Cow::Owned(string))
+}
+
+/// The `[^"\\\x00-\x1f]` alternative: characters that stand for themselves.
+fn plain(c: char) -> bool {
+    c != '\"' && c != '\\' && c >= ' '
+}
+
+/// The `\\["\\/bfnrt]` and `\\u[0-9a-fA-F]{4}` alternatives.
+fn escape<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<char, E> {
+    preceded(
+        '\\',
         dispatch!(any;
           '"' => empty.value('"'),
           '\\' => empty.value('\\'),
@@ -125,13 +183,13 @@ fn character<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<
           't' => empty.value('\t'),
           'u' => unicode_escape,
           _ => fail,
-        )
-        .parse_next(input)
-    } else {
-        Ok(c)
-    }
+        ),
+    )
+    .parse_next(input)
 }
 
+/// Lone surrogates have no `char` to decode to, so they are rejected, as in rust-sitter's
+/// `unescape`.
 fn unicode_escape<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<char, E> {
     alt((
         // Not a surrogate
@@ -154,23 +212,40 @@ fn unicode_escape<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PRe
     .parse_next(input)
 }
 
+/// `[0-9a-fA-F]{4}`: `from_str_radix` alone would also take a leading `+`.
 fn u16_hex<'i, E: ParserError<Stream<'i>>>(input: &mut Stream<'i>) -> PResult<u16, E> {
-    take(4usize)
+    take_while(4, |c: char| c.is_ascii_hexdigit())
         .verify_map(|s| u16::from_str_radix(s, 16).ok())
         .parse_next(input)
 }
 
+/// Fails, without backtracking, once `depth` passes [`MAX_DEPTH`].
+fn check_depth<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
+    input: &mut Stream<'i>,
+    depth: usize,
+) -> PResult<(), E> {
+    if depth > MAX_DEPTH {
+        return cut_err(fail)
+            .context(StrContext::Label("nesting depth"))
+            .parse_next(input);
+    }
+    Ok(())
+}
+
+/// `JsonValue::Array`: `[`, then values `#[delimited(",")]`, then `]`.
+///
 /// Some combinators, like `separated` or `many0`, will call a parser repeatedly,
 /// accumulating results in a `Vec`, until it encounters an error.
 /// If you want more control on the parser application, check out the `iterator`
 /// combinator (cf `examples/iterator.rs`)
 fn array<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<Vec<JsonValue>, E> {
+    depth: usize,
+) -> PResult<Vec<JsonValue<'i>>, E> {
     preceded(
-        ('[', ws),
+        ('[', |i: &mut _| check_depth(i, depth), ws),
         cut_err(terminated(
-            separated(0.., json_value, (ws, ',', ws)),
+            separated(0.., |i: &mut _| json_value(i, depth), (ws, ',', ws)),
             (ws, ']'),
         )),
     )
@@ -178,13 +253,15 @@ fn array<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     .parse_next(input)
 }
 
+/// `JsonValue::Object`: `{`, then `Property`s `#[delimited(",")]`, then `}`.
 fn object<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<HashMap<String, JsonValue>, E> {
+    depth: usize,
+) -> PResult<Vec<(Cow<'i, str>, JsonValue<'i>)>, E> {
     preceded(
-        ('{', ws),
+        ('{', |i: &mut _| check_depth(i, depth), ws),
         cut_err(terminated(
-            separated(0.., key_value, (ws, ',', ws)),
+            separated(0.., |i: &mut _| key_value(i, depth), (ws, ',', ws)),
             (ws, '}'),
         )),
     )
@@ -192,12 +269,19 @@ fn object<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     .parse_next(input)
 }
 
+/// `Property`: a `JsonString` name, `:`, then a `JsonValue`.
 fn key_value<'i, E: ParserError<Stream<'i>> + AddContext<Stream<'i>, StrContext>>(
     input: &mut Stream<'i>,
-) -> PResult<(String, JsonValue), E> {
-    separated_pair(string, cut_err((ws, ':', ws)), json_value).parse_next(input)
+    depth: usize,
+) -> PResult<(Cow<'i, str>, JsonValue<'i>), E> {
+    separated_pair(string, cut_err((ws, ':', ws)), |i: &mut _| {
+        json_value(i, depth)
+    })
+    .parse_next(input)
 }
 
+/// The `Whitespace` extra, except that it has to be asked for explicitly.
+///
 /// Parser combinators are constructed from the bottom up:
 /// first we write parsers for the smallest elements (here a space character),
 /// then we'll combine them in larger parsers
@@ -221,22 +305,19 @@ mod test {
 
     #[test]
     fn json_string() {
-        assert_eq!(
-            string::<Error<'_>>.parse_peek("\"\""),
-            Ok(("", "".to_string()))
-        );
+        assert_eq!(string::<Error<'_>>.parse_peek("\"\""), Ok(("", "".into())));
         assert_eq!(
             string::<Error<'_>>.parse_peek("\"abc\""),
-            Ok(("", "abc".to_string()))
+            Ok(("", "abc".into()))
         );
         assert_eq!(
             string::<Error<'_>>
                 .parse_peek("\"abc\\\"\\\\\\/\\b\\f\\n\\r\\t\\u0001\\u2014\u{2014}def\""),
-            Ok(("", "abc\"\\/\x08\x0C\n\r\t\x01——def".to_string())),
+            Ok(("", "abc\"\\/\x08\x0C\n\r\t\x01——def".into())),
         );
         assert_eq!(
             string::<Error<'_>>.parse_peek("\"\\uD83D\\uDE10\""),
-            Ok(("", "😐".to_string()))
+            Ok(("", "😐".into()))
         );
 
         assert!(string::<Error<'_>>.parse_peek("\"").is_err());
@@ -248,88 +329,90 @@ mod test {
             .parse_peek("\"\\uD800\\uD800\"")
             .is_err());
         assert!(string::<Error<'_>>.parse_peek("\"\\uDC00\"").is_err());
+        assert!(string::<Error<'_>>.parse_peek("\"\\u+123\"").is_err());
+    }
+
+    #[test]
+    fn json_string_borrows_unless_escaped() {
+        let Ok(("", Cow::Borrowed("abc"))) = string::<Error<'_>>.parse_peek("\"abc\"") else {
+            panic!("expected a borrowed string");
+        };
+        let Ok(("", Cow::Owned(s))) = string::<Error<'_>>.parse_peek("\"a\\nc\"") else {
+            panic!("expected an owned string");
+        };
+        assert_eq!(s, "a\nc");
     }
 
     #[test]
     fn json_object() {
-        use JsonValue::{Num, Object, Str};
+        use ast::JsonValue::{Number, Object, String};
 
         let input = r#"{"a":42,"b":"x"}"#;
 
-        let expected = Object(
-            vec![
-                ("a".to_string(), Num(42.0)),
-                ("b".to_string(), Str("x".to_string())),
-            ]
-            .into_iter()
-            .collect(),
-        );
+        let expected = Object(vec![
+            ("a".into(), Number(42.0)),
+            ("b".into(), String("x".into())),
+        ]);
 
         assert_eq!(json::<Error<'_>>.parse_peek(input), Ok(("", expected)));
     }
 
     #[test]
     fn json_array() {
-        use JsonValue::{Array, Num, Str};
+        use ast::JsonValue::{Array, Number, String};
 
         let input = r#"[42,"x"]"#;
 
-        let expected = Array(vec![Num(42.0), Str("x".to_string())]);
+        let expected = Array(vec![Number(42.0), String("x".into())]);
 
         assert_eq!(json::<Error<'_>>.parse_peek(input), Ok(("", expected)));
     }
 
     #[test]
     fn json_whitespace() {
-        use JsonValue::{Array, Boolean, Null, Num, Object, Str};
-
-        let input = r#"
-  {
-    "null" : null,
-    "true"  :true ,
-    "false":  false  ,
-    "number" : 123e4 ,
-    "string" : " abc 123 " ,
-    "array" : [ false , 1 , "two" ] ,
-    "object" : { "a" : 1.0 , "b" : "c" } ,
-    "empty_array" : [  ] ,
-    "empty_object" : {   }
-  }
-  "#;
+        use ast::JsonValue::{Array, Bool, Null, Number, Object, String};
 
         assert_eq!(
-            json::<Error<'_>>.parse_peek(input),
+            json::<Error<'_>>.parse_peek(corpus::WHITESPACE),
             Ok((
                 "",
-                Object(
-                    vec![
-                        ("null".to_string(), Null),
-                        ("true".to_string(), Boolean(true)),
-                        ("false".to_string(), Boolean(false)),
-                        ("number".to_string(), Num(123e4)),
-                        ("string".to_string(), Str(" abc 123 ".to_string())),
-                        (
-                            "array".to_string(),
-                            Array(vec![Boolean(false), Num(1.0), Str("two".to_string())])
-                        ),
-                        (
-                            "object".to_string(),
-                            Object(
-                                vec![
-                                    ("a".to_string(), Num(1.0)),
-                                    ("b".to_string(), Str("c".to_string())),
-                                ]
-                                .into_iter()
-                                .collect()
-                            )
-                        ),
-                        ("empty_array".to_string(), Array(vec![]),),
-                        ("empty_object".to_string(), Object(HashMap::new()),),
-                    ]
-                    .into_iter()
-                    .collect()
-                )
+                Object(vec![
+                    ("null".into(), Null),
+                    ("true".into(), Bool(true)),
+                    ("false".into(), Bool(false)),
+                    ("number".into(), Number(123e4)),
+                    ("string".into(), String(" abc 123 ".into())),
+                    (
+                        "array".into(),
+                        Array(vec![Bool(false), Number(1.0), String("two".into())])
+                    ),
+                    (
+                        "object".into(),
+                        Object(vec![
+                            ("a".into(), Number(1.0)),
+                            ("b".into(), String("c".into()))
+                        ])
+                    ),
+                    ("empty_array".into(), Array(vec![])),
+                    ("empty_object".into(), Object(vec![])),
+                ])
             ))
         );
     }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn max_depth() {
+        corpus::check_max_depth(parse);
+        assert!(parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
 }
@@ -0,0 +1,495 @@
+//! JSON split into a hand-rolled lexer, [`Lexer`], and a parser over its
+//! tokens, using nothing but the standard library and the shared `ast`.
+//!
+//! This is the logos example's shape without the crate, so it sets the floor
+//! for dependency count and compile time. The hand-written example goes
+//! further and parses straight off the bytes; this one keeps the two stages
+//! apart, as the libraries do.
+
+use std::{fmt, iter::Peekable, ops::Range};
+
+pub use ast::{JsonValue, MAX_DEPTH};
+
+/// Why and where lexing or parsing stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The bytes of the offending token, or an empty span at the end of the
+    /// input if it ended too soon.
+    pub span: Range<usize>,
+    /// A lowercase description such as `"expected ':'"`.
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.span, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// All meaningful JSON tokens.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    BraceOpen,
+    BraceClose,
+    BracketOpen,
+    BracketClose,
+    Colon,
+    Comma,
+    Null,
+    Bool(bool),
+    Number(f64),
+    /// A string literal, with its escapes decoded.
+    String(String),
+}
+
+/// The tokens of a JSON document, each with the bytes it spans, skipping the
+/// whitespace between them.
+pub struct Lexer<'i> {
+    input: &'i str,
+    pos: usize,
+}
+
+impl<'i> Lexer<'i> {
+    pub fn new(input: &'i str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self, start: usize, message: &'static str) -> ParseError {
+        ParseError {
+            span: start..self.pos,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Lexes the token starting at `self.pos`, which is not whitespace.
+    fn token(&mut self) -> Result<Token> {
+        let punctuation = match self.peek() {
+            Some(b'{') => Some(Token::BraceOpen),
+            Some(b'}') => Some(Token::BraceClose),
+            Some(b'[') => Some(Token::BracketOpen),
+            Some(b']') => Some(Token::BracketClose),
+            Some(b':') => Some(Token::Colon),
+            Some(b',') => Some(Token::Comma),
+            _ => None,
+        };
+        if let Some(token) = punctuation {
+            self.pos += 1;
+            return Ok(token);
+        }
+        match self.peek() {
+            Some(b'"') => self.string().map(Token::String),
+            Some(b'-' | b'0'..=b'9') => self.number().map(Token::Number),
+            _ => self.keyword(),
+        }
+    }
+
+    fn keyword(&mut self) -> Result<Token> {
+        let rest = &self.input[self.pos..];
+        for (word, token) in [
+            ("null", Token::Null),
+            ("true", Token::Bool(true)),
+            ("false", Token::Bool(false)),
+        ] {
+            if rest.starts_with(word) {
+                self.pos += word.len();
+                return Ok(token);
+            }
+        }
+        // Point at the whole character, which may be more than one byte.
+        let start = self.pos;
+        self.pos += rest.chars().next().map_or(0, char::len_utf8);
+        Err(self.error(start, "unexpected character"))
+    }
+
+    /// Scans `-?(0|[1-9]\d*)(\.\d+)?([eE][+-]?\d+)?`, then leaves the
+    /// conversion to the standard library.
+    fn number(&mut self) -> Result<f64> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error(start, "expected a digit")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits1(start)?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits1(start)?;
+        }
+        let n: f64 = self.input[start..self.pos].parse().unwrap();
+        if !n.is_finite() {
+            return Err(self.error(start, "number out of range"));
+        }
+        Ok(n)
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn digits1(&mut self, start: usize) -> Result<()> {
+        if !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(self.error(start, "expected a digit"));
+        }
+        self.digits();
+        Ok(())
+    }
+
+    /// Lexes a string literal, starting on its opening quote, and decodes it.
+    fn string(&mut self) -> Result<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let run = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            out.push_str(&self.input[run..self.pos]);
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => out.push(self.escape()?),
+                Some(_) => return Err(self.error(start, "control character in string")),
+                None => return Err(self.error(start, "unterminated string")),
+            }
+        }
+    }
+
+    /// Decodes one escape sequence, starting on its backslash, pairing up the
+    /// surrogates from `\u` escapes as rust-sitter's `unescape` does.
+    fn escape(&mut self) -> Result<char> {
+        let start = self.pos;
+        self.pos += 1;
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0C',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex4(start)?;
+                if !(0xD800..=0xDBFF).contains(&high) {
+                    return char::from_u32(u32::from(high))
+                        .ok_or_else(|| self.error(start, "unpaired surrogate"));
+                }
+                if !self.input[self.pos..].starts_with("\\u") {
+                    return Err(self.error(start, "unpaired surrogate"));
+                }
+                self.pos += 2;
+                let low = self.hex4(start)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error(start, "unpaired surrogate"));
+                }
+                let c = 0x10000 + ((u32::from(high) - 0xD800) << 10) + (u32::from(low) - 0xDC00);
+                return Ok(char::from_u32(c).unwrap());
+            }
+            _ => {
+                self.pos = (self.pos + 1).min(self.input.len());
+                return Err(self.error(start, "invalid escape"));
+            }
+        };
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn hex4(&mut self, start: usize) -> Result<u16> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error(start, "expected four hex digits"))?;
+        self.pos += 4;
+        Ok(u16::from_str_radix(digits, 16).unwrap())
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<(Token, Range<usize>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.skip_whitespace();
+        if self.pos == self.input.len() {
+            return None;
+        }
+        let start = self.pos;
+        let token = self.token();
+        if token.is_err() {
+            // Nothing sensible follows a token that failed to lex.
+            self.pos = self.input.len();
+        }
+        Some(token.map(|token| (token, start..self.pos)))
+    }
+}
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue> {
+    let mut parser = Parser {
+        tokens: Lexer::new(input).peekable(),
+        end: input.len(),
+        depth: 0,
+    };
+    let (token, span) = parser.next("expected a value")?;
+    let value = parser.value(token, span)?;
+    match parser.tokens.next() {
+        None => Ok(value),
+        Some(Ok((_, span))) => Err(ParseError {
+            span,
+            message: "expected end of input",
+        }),
+        Some(Err(err)) => Err(err),
+    }
+}
+
+struct Parser<'i> {
+    tokens: Peekable<Lexer<'i>>,
+    /// The length of the input, where errors about it ending too soon point.
+    end: usize,
+    /// The number of arrays and objects enclosing the next token.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// The next token, or an error saying what was `expected` if the input
+    /// ends first.
+    fn next(&mut self, expected: &'static str) -> Result<(Token, Range<usize>)> {
+        self.tokens.next().unwrap_or(Err(ParseError {
+            span: self.end..self.end,
+            message: expected,
+        }))
+    }
+
+    /// Finishes parsing the value that starts with `token`.
+    fn value(&mut self, token: Token, span: Range<usize>) -> Result<JsonValue> {
+        match token {
+            Token::Null => Ok(JsonValue::Null),
+            Token::Bool(b) => Ok(JsonValue::Bool(b)),
+            Token::Number(n) => Ok(JsonValue::Number(n)),
+            Token::String(s) => Ok(JsonValue::String(s)),
+            Token::BracketOpen | Token::BraceOpen if self.depth == MAX_DEPTH => Err(ParseError {
+                span,
+                message: "nesting too deep",
+            }),
+            Token::BracketOpen => self.nested(Self::array),
+            Token::BraceOpen => self.nested(Self::object),
+            _ => Err(ParseError {
+                span,
+                message: "expected a value",
+            }),
+        }
+    }
+
+    /// Runs `f` one level deeper.
+    fn nested(&mut self, f: fn(&mut Self) -> Result<JsonValue>) -> Result<JsonValue> {
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Parses the rest of an array, after its `[`.
+    fn array(&mut self) -> Result<JsonValue> {
+        let mut items = Vec::new();
+        let (mut token, mut span) = self.next("expected a value or ']'")?;
+        if token == Token::BracketClose {
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value(token, span)?);
+            match self.next("expected ',' or ']'")? {
+                (Token::Comma, _) => (token, span) = self.next("expected a value")?,
+                (Token::BracketClose, _) => return Ok(JsonValue::Array(items)),
+                (_, span) => {
+                    return Err(ParseError {
+                        span,
+                        message: "expected ',' or ']'",
+                    })
+                }
+            }
+        }
+    }
+
+    /// Parses the rest of an object, after its `{`.
+    fn object(&mut self) -> Result<JsonValue> {
+        let mut members = Vec::new();
+        let (mut token, mut span) = self.next("expected a string key or '}'")?;
+        if token == Token::BraceClose {
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            let Token::String(key) = token else {
+                return Err(ParseError {
+                    span,
+                    message: "expected a string key",
+                });
+            };
+            match self.next("expected ':'")? {
+                (Token::Colon, _) => {}
+                (_, span) => {
+                    return Err(ParseError {
+                        span,
+                        message: "expected ':'",
+                    })
+                }
+            }
+            let (value, value_span) = self.next("expected a value")?;
+            members.push((key, self.value(value, value_span)?));
+            match self.next("expected ',' or '}'")? {
+                (Token::Comma, _) => (token, span) = self.next("expected a string key")?,
+                (Token::BraceClose, _) => return Ok(JsonValue::Object(members)),
+                (_, span) => {
+                    return Err(ParseError {
+                        span,
+                        message: "expected ',' or '}'",
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Result<Token>> {
+        Lexer::new(input)
+            .map(|token| token.map(|(token, _)| token))
+            .collect()
+    }
+
+    #[test]
+    fn lexer() {
+        assert_eq!(
+            tokens(r#"{ } [ ] : , true false null -0.5E-2 "a\"é😐""#),
+            vec![
+                Ok(Token::BraceOpen),
+                Ok(Token::BraceClose),
+                Ok(Token::BracketOpen),
+                Ok(Token::BracketClose),
+                Ok(Token::Colon),
+                Ok(Token::Comma),
+                Ok(Token::Bool(true)),
+                Ok(Token::Bool(false)),
+                Ok(Token::Null),
+                Ok(Token::Number(-0.005)),
+                Ok(Token::String("a\"é😐".to_owned())),
+            ]
+        );
+        // The lexer splits a leading zero off; it is the parser that rejects
+        // two numbers in a row.
+        assert_eq!(
+            tokens("01"),
+            vec![Ok(Token::Number(0.0)), Ok(Token::Number(1.0))]
+        );
+        let spans: Vec<_> = Lexer::new(" [1, \"é\"]")
+            .map(|token| token.unwrap().1)
+            .collect();
+        assert_eq!(spans, [1..2, 2..3, 3..4, 5..9, 9..10]);
+        // Lexing stops at the first error.
+        assert_eq!(
+            tokens("[é, 1]"),
+            vec![
+                Ok(Token::BracketOpen),
+                Err(ParseError {
+                    span: 1..3,
+                    message: "unexpected character"
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn canonical_cases() {
+        corpus::check_canonical_cases(parse, corpus::same_value);
+    }
+
+    #[test]
+    fn json_test_suite() {
+        corpus::check_json_test_suite(|input| parse(input).is_ok(), &[]);
+    }
+
+    #[test]
+    fn error_spans() {
+        let error = |input| parse(input).unwrap_err();
+        assert_eq!(
+            error(r#"{"a" 1}"#),
+            ParseError {
+                span: 5..6,
+                message: "expected ':'"
+            }
+        );
+        assert_eq!(
+            error(r#"["é", "\uD800"]"#),
+            ParseError {
+                span: 8..14,
+                message: "unpaired surrogate"
+            }
+        );
+        assert_eq!(
+            error("[1e400]"),
+            ParseError {
+                span: 1..6,
+                message: "number out of range"
+            }
+        );
+        assert_eq!(
+            error("[1,"),
+            ParseError {
+                span: 3..3,
+                message: "expected a value"
+            }
+        );
+        assert_eq!(
+            error("[1] 2"),
+            ParseError {
+                span: 4..5,
+                message: "expected end of input"
+            }
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        assert_eq!(
+            corpus::check_max_depth(parse),
+            ParseError {
+                span: MAX_DEPTH..MAX_DEPTH + 1,
+                message: "nesting too deep"
+            }
+        );
+    }
+}
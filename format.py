@@ -20,7 +20,7 @@ def main():
     print("Name | Overhead (release) | Build (debug) | Parse (release) | Downloads | Version")
     print("-----|--------------------|---------------|-----------------|-----------|--------")
     for case in cases:
-        if case["name"] != "null":
+        if case["crate"]:
             count_link = "![Download count](https://img.shields.io/crates/dr/{})".format(case["crate"])
         else:
             count_link = "-"